@@ -0,0 +1,74 @@
+//! An on-disk record of the sources that have already been checked, so
+//! `lovely check` only has to redo the files that changed since.
+//!
+//! Entries are keyed by a hash of the compiler version and the source, so
+//! they go stale on their own when either changes. A file's result also
+//! depends on the configuration and the files it includes or embeds, so
+//! `lovely check` puts those in the source it looks up as well.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_DIR: &str = ".lovely-cache";
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Whether `source` is known to check cleanly.
+    pub fn contains(&self, source: &str) -> bool {
+        self.entry(source).exists()
+    }
+
+    /// Records that `source` checks cleanly.
+    pub fn insert(&self, source: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry(source), "")
+    }
+
+    fn entry(&self, source: &str) -> PathBuf {
+        let key = hash(&[
+            env!("CARGO_PKG_VERSION").as_bytes(),
+            b"\0",
+            source.as_bytes(),
+        ]);
+        self.dir.join(format!("{key:016x}"))
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the std hashers is the same from one build to the next.
+fn hash(parts: &[&[u8]]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_follow_the_source() {
+        let dir = std::env::temp_dir().join(format!("lovely-cache-test-{}", std::process::id()));
+        let cache = Cache::new(&dir);
+
+        assert!(!cache.contains("x :: 1"));
+        cache.insert("x :: 1").unwrap();
+        assert!(cache.contains("x :: 1"));
+        assert!(!cache.contains("x :: 2"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
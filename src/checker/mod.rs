@@ -1,45 +1,397 @@
 #![allow(dead_code)]
 
+use std::fmt::{self, Display};
+
 use crate::{
     parser::ast::{
-        Expression, ExpressionKind, ExpressionStatement, FunctionParameter,
+        Expression, ExpressionKind, ExpressionStatement, FunctionArgument, FunctionParameter,
         InfixOperator::{self, *},
+        MatchArm, Pattern, PatternKind,
         PrefixOperator::{self, *},
         Program, Type,
     },
     span::Span,
 };
-use scopes::{Scope, ScopeId, ScopedType, ScopedVariable};
+pub use prelude::Prelude;
+use scopes::{
+    ParameterLabels, Scope, ScopeId, ScopedMethod, ScopedType, ScopedVariable, TypeKind,
+    VariantFields,
+};
 
+mod prelude;
 mod scopes;
 
 pub type TypeId = usize;
 type VariableId = usize;
 
+/// The types every program has, whatever its prelude, in the order of their ids.
+const BUILTIN_TYPES: [&str; 6] = ["Int", "Bool", "Unit", "String", "Char", "Float"];
 const INT_ID: usize = 0;
 const BOOL_ID: usize = 1;
 const UNIT_ID: usize = 2;
+const STRING_ID: usize = 3;
+const CHAR_ID: usize = 4;
+const FLOAT_ID: usize = 5;
+
+/// The sized int types and the values they can hold. Their ids follow the
+/// other builtin types, in this order.
+const SIZED_INT_TYPES: [(&str, i128, i128); 8] = [
+    ("i8", i8::MIN as i128, i8::MAX as i128),
+    ("i16", i16::MIN as i128, i16::MAX as i128),
+    ("i32", i32::MIN as i128, i32::MAX as i128),
+    ("i64", i64::MIN as i128, i64::MAX as i128),
+    ("u8", 0, u8::MAX as i128),
+    ("u16", 0, u16::MAX as i128),
+    ("u32", 0, u32::MAX as i128),
+    ("u64", 0, u64::MAX as i128),
+];
+const FIRST_SIZED_INT_ID: usize = BUILTIN_TYPES.len();
+
+/// The type of an expression that didn't check, after the sized int types.
+/// It goes with every other type, so that one error doesn't set off more of
+/// them, and its name isn't an identifier, so programs can't refer to it.
+const ERROR_ID: usize = FIRST_SIZED_INT_ID + SIZED_INT_TYPES.len();
+const ERROR_TYPE_NAME: &str = "{error}";
 
 pub struct Checker {
     cur_scope: ScopeId,
     scopes: Vec<Scope>,
     types: Vec<ScopedType>,
     variables: Vec<ScopedVariable>,
+    methods: Vec<ScopedMethod>,
+    loops: Vec<EnclosingLoop>,
+    /// What the function being checked returns, outside of which there's
+    /// nothing to `return` from.
+    return_type: Option<TypeId>,
+    /// Whether the function being checked returns what its first `return`
+    /// or its body does, which is `return_type` once it's known.
+    infer_return_type: bool,
+    /// How many function bodies the statement being checked is in.
+    function_depth: usize,
     type_errors: Vec<Error>,
+    warnings: Vec<Warning>,
 }
 
-#[derive(Debug)]
-struct Error {
-    span: Span,
-    kind: ErrorKind,
+/// A loop that `break` and `continue` expressions can refer to.
+struct EnclosingLoop {
+    label: Option<String>,
+    accepts_break_value: bool,
+    break_type: Option<TypeId>,
 }
-#[derive(Debug)]
-enum ErrorKind {
-    TypeMismatch { expected: TypeId, got: TypeId },
-    VariableNotFound { name: String },
-    TypeNotFound { ty: Type },
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub span: Span,
+    pub kind: ErrorKind,
+}
+#[derive(Debug, Clone)]
+pub enum ErrorKind {
+    TypeMismatch {
+        expected: TypeId,
+        got: TypeId,
+    },
+    VariableNotFound {
+        name: String,
+    },
+    TypeNotFound {
+        ty: Type,
+    },
+    MethodNotFound {
+        type_id: TypeId,
+        name: String,
+    },
+    MethodAlreadyDefined {
+        type_id: TypeId,
+        name: String,
+    },
+    MissingSelfParameter {
+        method: String,
+    },
+    UnexpectedSelfParameter,
+    ArgumentCountMismatch {
+        expected: usize,
+        got: usize,
+    },
+    OperatorNotImplemented {
+        operator: InfixOperator,
+        type_id: TypeId,
+    },
+    /// The operator was declared without a method, or the left operand doesn't have it.
+    CustomOperatorNotImplemented {
+        operator: String,
+        type_id: TypeId,
+    },
+    CannotInferParameterType {
+        name: String,
+    },
+    /// `none` where nothing says what type of option it is.
+    CannotInferNoneType,
+    /// The left operand of `??`, the target of `?.` or the type `none` is
+    /// expected to have isn't an option.
+    NotAnOption {
+        type_id: TypeId,
+    },
+    /// `ok(value)` or `err(error)` where nothing says what the other type of
+    /// the result is.
+    CannotInferResultType,
+    /// The operand of `?` or the type `ok` or `err` is expected to have
+    /// isn't a result.
+    NotAResult {
+        type_id: TypeId,
+    },
+    /// `?` somewhere other than in a function declared to return a result,
+    /// which its error could be returned as. `return_type` is what the
+    /// function returns, if that's known.
+    TryOutsideResultFunction {
+        return_type: Option<TypeId>,
+    },
+    /// A pattern in a parameter that doesn't match every argument, like a
+    /// literal or an enum variant.
+    RefutableParameterPattern,
+    /// A tuple pattern with this many elements, for a value of a type that
+    /// isn't a tuple with as many.
+    TuplePatternMismatch {
+        type_id: TypeId,
+        elements: usize,
+    },
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    ReturnOutsideFunction,
+    UnexpectedBreakValue,
+    NotAFunction {
+        type_id: TypeId,
+    },
+    FieldNotFound {
+        type_id: TypeId,
+        field: String,
+    },
+    /// The variable was declared with `::`, or is a parameter or a binding
+    /// from a pattern. One declared with `::` has the span of its declaration,
+    /// and the suggestion to declare it with `:=` instead.
+    AssignToImmutable {
+        name: String,
+        declaration: Option<Span>,
+        suggestion: Option<Suggestion>,
+    },
+    /// Something other than a variable or one of its fields, like an enum
+    /// variant or a field of what a function returns.
+    InvalidAssignmentTarget,
+    VariantNotFound {
+        type_id: TypeId,
+        variant: String,
+    },
+    UnknownArgumentLabel {
+        label: String,
+    },
+    /// The span of the error is the second argument with the label.
+    DuplicateArgumentLabel {
+        label: String,
+        first: Span,
+    },
+    MissingArgumentLabel {
+        label: String,
+    },
+    /// The span of the error is the label of the second parameter with it.
+    DuplicateParameterLabel {
+        label: String,
+        first: Span,
+    },
+    IntLiteralOutOfRange {
+        type_id: TypeId,
+    },
+    /// The function's last statement ends with a semicolon, so it returns `unit`.
+    DiscardedReturnValue {
+        expected: TypeId,
+    },
+    /// An `include` or `embed_text` in a program that wasn't resolved against
+    /// the files it refers to.
+    UnresolvedFile {
+        path: String,
+    },
+    /// A function that's used before its declaration, other than in the body
+    /// of another function, so it could be called before it's bound.
+    UsedBeforeDeclaration {
+        name: String,
+        declaration: Span,
+    },
+    /// Something that parses but can't be checked yet, like a range, which
+    /// has no type until there are generic types to give it.
+    Unsupported {
+        feature: &'static str,
+    },
+}
+/// A change to the source that fixes an error, replacing what's at `span`
+/// with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// An error or warning along with the checker that found it, which knows the
+/// types it refers to, from [`Error::display`] or [`Warning::display`].
+pub struct Diagnostic<'a, T> {
+    diagnostic: &'a T,
+    checker: &'a Checker,
+}
+
+impl Display for Diagnostic<'_, Error> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ErrorKind::*;
+        let Span { start, end } = self.diagnostic.span;
+        let ty = |type_id: &TypeId| self.checker.type_name(*type_id);
+        write!(f, "error at {start}..{end}: ")?;
+        match &self.diagnostic.kind {
+            TypeMismatch { expected, got } => {
+                write!(f, "expected {}, got {}", ty(expected), ty(got))
+            }
+            VariableNotFound { name } => write!(f, "there's no variable `{name}`"),
+            TypeNotFound { ty } => write!(f, "there's no type `{ty}`"),
+            MethodNotFound { type_id, name } => {
+                write!(f, "{} has no method `{name}`", ty(type_id))
+            }
+            MethodAlreadyDefined { type_id, name } => {
+                write!(f, "{} already has a method `{name}`", ty(type_id))
+            }
+            MissingSelfParameter { method } => {
+                write!(f, "the method `{method}` doesn't take `self` first")
+            }
+            UnexpectedSelfParameter => write!(f, "only methods take `self`"),
+            ArgumentCountMismatch { expected, got } => {
+                write!(f, "expected {expected} arguments, got {got}")
+            }
+            OperatorNotImplemented { operator, type_id } => {
+                write!(f, "{} doesn't have the operator `{operator}`", ty(type_id))
+            }
+            CustomOperatorNotImplemented { operator, type_id } => {
+                write!(f, "{} doesn't have the operator `{operator}`", ty(type_id))
+            }
+            CannotInferParameterType { name } => {
+                write!(f, "the parameter `{name}` needs a type")
+            }
+            CannotInferNoneType => write!(f, "nothing says what `none` is an option of"),
+            NotAnOption { type_id } => write!(f, "{} isn't an option", ty(type_id)),
+            CannotInferResultType => {
+                write!(f, "nothing says what the other type of the result is")
+            }
+            NotAResult { type_id } => write!(f, "{} isn't a result", ty(type_id)),
+            TryOutsideResultFunction { return_type } => {
+                write!(f, "`?` has to be in a function that returns a result")?;
+                match return_type {
+                    Some(type_id) => write!(f, ", not {}", ty(type_id)),
+                    None => Ok(()),
+                }
+            }
+            RefutableParameterPattern => {
+                write!(f, "the pattern of a parameter has to match every argument")
+            }
+            TuplePatternMismatch { type_id, elements } => write!(
+                f,
+                "a pattern of {elements} elements can't match {}",
+                ty(type_id)
+            ),
+            BreakOutsideLoop => write!(f, "`break` outside of a loop"),
+            ContinueOutsideLoop => write!(f, "`continue` outside of a loop"),
+            ReturnOutsideFunction => write!(f, "`return` outside of a function"),
+            UnexpectedBreakValue => write!(f, "only `loop` can be broken out of with a value"),
+            NotAFunction { type_id } => write!(f, "{} isn't a function", ty(type_id)),
+            FieldNotFound { type_id, field } => {
+                write!(f, "{} has no field `{field}`", ty(type_id))
+            }
+            AssignToImmutable {
+                name, declaration, ..
+            } => {
+                write!(f, "`{name}` can't be assigned to")?;
+                match declaration {
+                    Some(Span { start, end }) => {
+                        write!(f, ", since it's declared with `::` at {start}..{end}")
+                    }
+                    None => Ok(()),
+                }
+            }
+            InvalidAssignmentTarget => {
+                write!(f, "only variables and their fields can be assigned to")
+            }
+            VariantNotFound { type_id, variant } => {
+                write!(f, "{} has no variant `{variant}`", ty(type_id))
+            }
+            UnknownArgumentLabel { label } => write!(f, "no parameter has the label `{label}`"),
+            DuplicateArgumentLabel {
+                label,
+                first: Span { start, end },
+            } => write!(f, "the label `{label}` was already given at {start}..{end}"),
+            MissingArgumentLabel { label } => write!(f, "missing the label `{label}`"),
+            DuplicateParameterLabel {
+                label,
+                first: Span { start, end },
+            } => write!(f, "the label `{label}` was already taken at {start}..{end}"),
+            IntLiteralOutOfRange { type_id } => {
+                write!(f, "the literal doesn't fit in {}", ty(type_id))
+            }
+            DiscardedReturnValue { expected } => write!(
+                f,
+                "the function returns {}, but its last statement ends with a semicolon",
+                ty(expected)
+            ),
+            UnresolvedFile { path } => write!(f, "`{path}` wasn't included"),
+            UsedBeforeDeclaration {
+                name,
+                declaration: Span { start, end },
+            } => write!(
+                f,
+                "`{name}` is used before its declaration at {start}..{end}"
+            ),
+            Unsupported { feature } => write!(f, "not supported yet: {feature}"),
+        }
+    }
+}
+
+impl Display for Diagnostic<'_, Warning> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Span { start, end } = self.diagnostic.span;
+        write!(f, "warning at {start}..{end}: ")?;
+        match self.diagnostic.kind {
+            WarningKind::UnusedValue { type_id } => write!(
+                f,
+                "the value of type {} isn't used",
+                self.checker.type_name(type_id)
+            ),
+        }
+    }
 }
+
 impl Error {
+    /// The error as a message, with the names of the types in it.
+    pub fn display<'a>(&'a self, checker: &'a Checker) -> Diagnostic<'a, Error> {
+        Diagnostic {
+            diagnostic: self,
+            checker,
+        }
+    }
+
+    /// Whether this is about an expression that has the error type, and so
+    /// only happened because of an error that was already reported.
+    fn follows_earlier_error(&self) -> bool {
+        match self.kind {
+            ErrorKind::TypeMismatch { expected, got } => expected == ERROR_ID || got == ERROR_ID,
+            ErrorKind::MethodNotFound { type_id, .. }
+            | ErrorKind::OperatorNotImplemented { type_id, .. }
+            | ErrorKind::CustomOperatorNotImplemented { type_id, .. }
+            | ErrorKind::NotAFunction { type_id }
+            | ErrorKind::NotAnOption { type_id }
+            | ErrorKind::NotAResult { type_id }
+            | ErrorKind::TryOutsideResultFunction {
+                return_type: Some(type_id),
+            }
+            | ErrorKind::FieldNotFound { type_id, .. }
+            | ErrorKind::VariantNotFound { type_id, .. }
+            | ErrorKind::IntLiteralOutOfRange { type_id }
+            | ErrorKind::TuplePatternMismatch { type_id, .. }
+            | ErrorKind::DiscardedReturnValue { expected: type_id } => type_id == ERROR_ID,
+            _ => false,
+        }
+    }
+
     fn type_mismatch(expected: TypeId, got: TypeId, span: Span) -> Error {
         Error {
             span,
@@ -60,417 +412,3989 @@ impl Error {
             kind: ErrorKind::TypeNotFound { ty },
         }
     }
+    fn method_not_found(type_id: TypeId, name: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::MethodNotFound {
+                type_id,
+                name: name.to_string(),
+            },
+        }
+    }
+    fn method_already_defined(type_id: TypeId, name: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::MethodAlreadyDefined {
+                type_id,
+                name: name.to_string(),
+            },
+        }
+    }
+    fn missing_self_parameter(method: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::MissingSelfParameter {
+                method: method.to_string(),
+            },
+        }
+    }
+    fn unexpected_self_parameter(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::UnexpectedSelfParameter,
+        }
+    }
+    fn operator_not_implemented(operator: InfixOperator, type_id: TypeId, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::OperatorNotImplemented { operator, type_id },
+        }
+    }
+    fn custom_operator_not_implemented(operator: &str, type_id: TypeId, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::CustomOperatorNotImplemented {
+                operator: operator.to_string(),
+                type_id,
+            },
+        }
+    }
+    fn cannot_infer_parameter_type(name: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::CannotInferParameterType {
+                name: name.to_string(),
+            },
+        }
+    }
+    fn cannot_infer_none_type(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::CannotInferNoneType,
+        }
+    }
+    fn not_an_option(type_id: TypeId, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::NotAnOption { type_id },
+        }
+    }
+    fn cannot_infer_result_type(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::CannotInferResultType,
+        }
+    }
+    fn not_a_result(type_id: TypeId, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::NotAResult { type_id },
+        }
+    }
+    fn try_outside_result_function(return_type: Option<TypeId>, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::TryOutsideResultFunction { return_type },
+        }
+    }
+    fn refutable_parameter_pattern(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::RefutableParameterPattern,
+        }
+    }
+    fn tuple_pattern_mismatch(type_id: TypeId, elements: usize, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::TuplePatternMismatch { type_id, elements },
+        }
+    }
+    fn break_outside_loop(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::BreakOutsideLoop,
+        }
+    }
+    fn continue_outside_loop(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::ContinueOutsideLoop,
+        }
+    }
+    fn return_outside_function(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::ReturnOutsideFunction,
+        }
+    }
+    fn unexpected_break_value(span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::UnexpectedBreakValue,
+        }
+    }
+    fn int_literal_out_of_range(type_id: TypeId, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::IntLiteralOutOfRange { type_id },
+        }
+    }
+    fn argument_count_mismatch(expected: usize, got: usize, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::ArgumentCountMismatch { expected, got },
+        }
+    }
+    fn not_a_function(type_id: TypeId, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::NotAFunction { type_id },
+        }
+    }
+    fn field_not_found(type_id: TypeId, field: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::FieldNotFound {
+                type_id,
+                field: field.to_string(),
+            },
+        }
+    }
+    fn assign_to_immutable(variable: &ScopedVariable, span: Span) -> Error {
+        let (declaration, suggestion) = match variable.declaration {
+            Some((declaration, separator)) => (
+                Some(declaration),
+                Some(Suggestion {
+                    span: separator,
+                    replacement: "=".to_string(),
+                }),
+            ),
+            None => (None, None),
+        };
+        Error {
+            span,
+            kind: ErrorKind::AssignToImmutable {
+                name: variable.name.clone(),
+                declaration,
+                suggestion,
+            },
+        }
+    }
+    fn used_before_declaration(variable: &ScopedVariable, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::UsedBeforeDeclaration {
+                name: variable.name.clone(),
+                declaration: variable
+                    .declaration
+                    .map_or(span, |(declaration, _)| declaration),
+            },
+        }
+    }
+    fn variant_not_found(type_id: TypeId, variant: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::VariantNotFound {
+                type_id,
+                variant: variant.to_string(),
+            },
+        }
+    }
+    fn unknown_argument_label(label: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::UnknownArgumentLabel {
+                label: label.to_string(),
+            },
+        }
+    }
+    fn duplicate_argument_label(label: &str, first: Span, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::DuplicateArgumentLabel {
+                label: label.to_string(),
+                first,
+            },
+        }
+    }
+    fn duplicate_parameter_label(label: &str, first: Span, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::DuplicateParameterLabel {
+                label: label.to_string(),
+                first,
+            },
+        }
+    }
+    fn missing_argument_label(label: &str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::MissingArgumentLabel {
+                label: label.to_string(),
+            },
+        }
+    }
+    fn discarded_return_value(expected: TypeId, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::DiscardedReturnValue { expected },
+        }
+    }
+    fn unsupported(feature: &'static str, span: Span) -> Error {
+        Error {
+            span,
+            kind: ErrorKind::Unsupported { feature },
+        }
+    }
+}
+
+/// Something that type checks, but probably isn't what was meant.
+#[derive(Debug)]
+pub struct Warning {
+    pub span: Span,
+    pub kind: WarningKind,
+}
+#[derive(Debug)]
+pub enum WarningKind {
+    /// A statement that isn't `unit` has its value thrown away by a semicolon.
+    UnusedValue { type_id: TypeId },
+}
+
+impl Warning {
+    /// The warning as a message, with the names of the types in it.
+    pub fn display<'a>(&'a self, checker: &'a Checker) -> Diagnostic<'a, Warning> {
+        Diagnostic {
+            diagnostic: self,
+            checker,
+        }
+    }
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-// impl Checker {
-//     pub fn new() -> Self {
-//         Self {
-//             cur_scope: 0,
-//             scopes: vec![Scope { parent_scope: None }],
-//             types: vec![
-//                 // builtin types
-//                 ScopedType::named("Int", 0),
-//                 ScopedType::named("Bool", 0),
-//                 ScopedType::named("Unit", 0),
-//             ],
-//             variables: vec![],
-//             type_errors: vec![],
-//         }
-//     }
-//
-//     fn create_scope(&mut self, parent_id: Option<ScopeId>) -> ScopeId {
-//         self.scopes.push(Scope {
-//             parent_scope: parent_id,
-//         });
-//         self.scopes.len() - 1
-//     }
-//
-//     fn check_type_name(&self, ty: &Type, scope_id: ScopeId) -> Option<TypeId> {
-//         let cur_scope = &self.scopes[scope_id];
-//         let Type::Ident(name) = ty;
-//         if let Some(type_id) = self
-//             .types
-//             .iter()
-//             .position(|t| t.scope_id == scope_id && &t.name == name)
-//         {
-//             Some(type_id)
-//         } else if let Some(parent_id) = cur_scope.parent_scope {
-//             self.check_type_name(ty, parent_id)
-//         } else {
-//             None
-//         }
-//     }
-//
-//     fn check_variable_name(
-//         &self,
-//         var_name: &str,
-//         scope_id: ScopeId,
-//     ) -> Option<(VariableId, TypeId)> {
-//         let cur_scope = &self.scopes[scope_id];
-//         if let Some((index, variable)) = self
-//             .variables
-//             .iter()
-//             .enumerate()
-//             .find(|t| t.1.scope_id == scope_id && t.1.name == var_name)
-//         {
-//             Some((index, variable.type_id))
-//         } else if let Some(parent_id) = cur_scope.parent_scope {
-//             self.check_variable_name(var_name, parent_id)
-//         } else {
-//             None
-//         }
-//     }
-//
-//     fn add_variable(&mut self, var_name: &str, var_type: TypeId) -> VariableId {
-//         let variable_id = self.variables.len();
-//         self.variables
-//             .push(ScopedVariable::new(var_name, self.cur_scope, var_type));
-//         variable_id
-//     }
-//
-//     pub fn check_program(&mut self, program: &Program) -> CheckedProgram {
-//         CheckedProgram {
-//             stmts: program
-//                 .0
-//                 .iter()
-//                 .map(|s| self.check_expression_statment(s).unwrap())
-//                 .collect(),
-//         }
-//     }
-//
-//     fn check_expression_statment(
-//         &mut self,
-//         stmt: &ExpressionStatement,
-//     ) -> Result<CheckedExpressionStatement, Error> {
-//         Ok(CheckedExpressionStatement {
-//             discarded: stmt.discarded,
-//             expr: self.check_expression(&stmt.expr, None)?,
-//         })
-//     }
-//
-//     fn check_expression(
-//         &mut self,
-//         expr: &Expression,
-//         type_hint: Option<TypeId>,
-//     ) -> Result<CheckedExpression, Error> {
-//         match &expr.kind {
-//             ExpressionKind::Unit => {
-//                 self.typed_expression(CheckedExpressionData::Unit, expr.span, UNIT_ID, type_hint)
-//             }
-//             ExpressionKind::BoolLiteral(value) => self.typed_expression(
-//                 CheckedExpressionData::BoolLiteral(*value),
-//                 expr.span,
-//                 BOOL_ID,
-//                 type_hint,
-//             ),
-//             ExpressionKind::IntLiteral(value) => self.typed_expression(
-//                 CheckedExpressionData::IntLiteral(*value),
-//                 expr.span,
-//                 INT_ID,
-//                 type_hint,
-//             ),
-//             ExpressionKind::Prefix {
-//                 operator,
-//                 expression,
-//             } => match operator {
-//                 LogicalNot => {
-//                     let res = self.check_expression(expression, Some(BOOL_ID))?;
-//                     self.typed_expression(
-//                         CheckedExpressionData::Prefix {
-//                             operator: *operator,
-//                             expression: Box::new(res),
-//                         },
-//                         expr.span,
-//                         BOOL_ID,
-//                         type_hint,
-//                     )
-//                 }
-//                 Negative => {
-//                     let res = self.check_expression(expression, Some(INT_ID))?;
-//                     self.typed_expression(
-//                         CheckedExpressionData::Prefix {
-//                             operator: *operator,
-//                             expression: Box::new(res),
-//                         },
-//                         expr.span,
-//                         INT_ID,
-//                         type_hint,
-//                     )
-//                 }
-//             },
-//             ExpressionKind::Infix {
-//                 left,
-//                 operator,
-//                 right,
-//             } => match operator {
-//                 // ints
-//                 Plus | Minus | Divide | Multiply => {
-//                     let left = self.check_expression(left, Some(INT_ID))?;
-//                     let right = self.check_expression(right, Some(INT_ID))?;
-//                     self.typed_expression(
-//                         CheckedExpressionData::Infix {
-//                             left: Box::new(left),
-//                             operator: *operator,
-//                             right: Box::new(right),
-//                         },
-//                         expr.span,
-//                         INT_ID,
-//                         type_hint,
-//                     )
-//                 }
-//                 LessThan | GreaterThan | LessThanOrEqual | GreaterThanOrEqual => {
-//                     let left = self.check_expression(left, Some(INT_ID))?;
-//                     let right = self.check_expression(right, Some(INT_ID))?;
-//                     self.typed_expression(
-//                         CheckedExpressionData::Infix {
-//                             left: Box::new(left),
-//                             operator: *operator,
-//                             right: Box::new(right),
-//                         },
-//                         expr.span,
-//                         BOOL_ID,
-//                         type_hint,
-//                     )
-//                 }
-//
-//                 // anything
-//                 Equal | NotEqual => {
-//                     let left = self.check_expression(left, None)?;
-//                     let right = self.check_expression(right, None)?;
-//                     if left.type_id != right.type_id {
-//                         Err(Error::type_mismatch(left.type_id, right.type_id, expr.span))
-//                     } else {
-//                         self.typed_expression(
-//                             CheckedExpressionData::Infix {
-//                                 left: Box::new(left),
-//                                 operator: *operator,
-//                                 right: Box::new(right),
-//                             },
-//                             expr.span,
-//                             BOOL_ID,
-//                             type_hint,
-//                         )
-//                     }
-//                 }
-//             },
-//             ExpressionKind::VariableDecl {
-//                 name,
-//                 value,
-//                 mutable,
-//                 ty,
-//             } => {
-//                 let r_value = if let Some(ty) = ty {
-//                     self.check_expression(value, self.check_type_name(ty, self.cur_scope))?
-//                 } else {
-//                     self.check_expression(value, None)?
-//                 };
-//                 let id = self.add_variable(name, r_value.type_id);
-//                 self.typed_expression(
-//                     CheckedExpressionData::VariableDecl {
-//                         name: name.to_string(),
-//                         value: Box::new(r_value),
-//                         mutable: *mutable,
-//                         variable_id: id,
-//                     },
-//                     expr.span,
-//                     UNIT_ID,
-//                     type_hint,
-//                 )
-//             }
-//             ExpressionKind::Ident(name) => {
-//                 if let Some((var_id, var_type)) = self.check_variable_name(name, self.cur_scope) {
-//                     self.typed_expression(
-//                         CheckedExpressionData::Ident {
-//                             name: name.to_string(),
-//                             variable_id: var_id,
-//                         },
-//                         expr.span,
-//                         var_type,
-//                         type_hint,
-//                     )
-//                 } else {
-//                     Err(Error::variable_not_found(name, expr.span))
-//                 }
-//             }
-//             ExpressionKind::Function {
-//                 parameters,
-//                 return_type,
-//                 body,
-//             } => {
-//                 // create a new scope
-//                 let new_scope = self.create_scope(Some(self.cur_scope));
-//                 self.cur_scope = new_scope;
-//
-//                 let mut checked_params = vec![];
-//
-//                 // add params as local variables in said scope
-//                 for param in parameters {
-//                     match param {
-//                         FunctionParameter::LabeledAtCallsite {
-//                             internal_name,
-//                             external_name,
-//                             ty,
-//                         } => {
-//                             if let Some(type_id) = self.check_type_name(ty, self.cur_scope) {
-//                                 checked_params.push(CheckedFunctionParameter::LabeledAtCallsite {
-//                                     internal_name: internal_name.clone(),
-//                                     external_name: external_name.clone(),
-//                                     type_id,
-//                                 });
-//                                 self.add_variable(internal_name, type_id);
-//                             } else {
-//                                 return Err(Error::type_not_found(ty.clone(), expr.span));
-//                             }
-//                         }
-//                         FunctionParameter::UnlabeledAtCallsite { name, ty } => {
-//                             if let Some(type_id) = self.check_type_name(ty, self.cur_scope) {
-//                                 checked_params.push(
-//                                     CheckedFunctionParameter::UnlabeledAtCallsite {
-//                                         name: name.clone(),
-//                                         type_id,
-//                                     },
-//                                 );
-//                                 self.add_variable(name, type_id);
-//                             } else {
-//                                 return Err(Error::type_not_found(ty.clone(), expr.span));
-//                             }
-//                         }
-//                     }
-//                 }
-//
-//                 // check the body
-//                 let checked_expr_stmts = body
-//                     .iter()
-//                     .map(|s| self.check_expression_statment(s).unwrap())
-//                     .collect::<Vec<_>>();
-//
-//                 let Some(last_stmt) = checked_expr_stmts.last() else {
-//                     todo!("empty function body, only valid if return type is unit");
-//                 };
-//                 if last_stmt.discarded {
-//                     todo!(
-//                         "discarded last statement in function body, only valid if return type is unit"
-//                     );
-//                 }
-//
-//                 // get return type
-//                 let return_type = if let Some(ty) = return_type {
-//                     ty
-//                 } else {
-//                     &Type::Ident("Unit".to_string())
-//                 };
-//                 let Some(return_type_id) = self.check_type_name(return_type, self.cur_scope) else {
-//                     return Err(Error::type_not_found(return_type.clone(), expr.span));
-//                 };
-//
-//                 // check if the last statement is of the same type as the return type
-//                 if last_stmt.expr.type_id != return_type_id {
-//                     return Err(Error::type_mismatch(
-//                         return_type_id,
-//                         last_stmt.expr.type_id,
-//                         expr.span,
-//                     ));
-//                 }
-//
-//                 self.typed_expression(
-//                     CheckedExpressionData::Function {
-//                         parameters: checked_params,
-//                         return_type: return_type_id,
-//                         body: checked_expr_stmts,
-//                     },
-//                     expr.span,
-//                     todo!("function types"),
-//                     type_hint,
-//                 )
-//             }
-//             ExpressionKind::FunctionCall { .. } => todo!(),
-//         }
-//     }
-//
-//     fn typed_expression(
-//         &self,
-//         res: CheckedExpressionData,
-//         span: Span,
-//         res_type: TypeId,
-//         type_hint: Option<TypeId>,
-//     ) -> Result<CheckedExpression, Error> {
-//         if let Some(type_hint) = type_hint {
-//             if type_hint == res_type {
-//                 Ok(CheckedExpression::new(res, res_type))
-//             } else {
-//                 Err(Error::type_mismatch(type_hint, res_type, span))
-//             }
-//         } else {
-//             Ok(CheckedExpression::new(res, res_type))
-//         }
-//     }
-// }
-//
-// #[derive(PartialEq, Eq, Debug)]
-// pub struct CheckedProgram {
-//     stmts: Vec<CheckedExpressionStatement>,
-// }
-//
-// #[derive(PartialEq, Eq, Debug)]
-// struct CheckedExpressionStatement {
-//     expr: CheckedExpression,
-//     discarded: bool,
-// }
-//
-// #[derive(PartialEq, Eq, Debug)]
-// struct CheckedExpression {
-//     type_id: TypeId,
-//     data: CheckedExpressionData,
-// }
-// impl CheckedExpression {
-//     pub fn new(data: CheckedExpressionData, type_id: TypeId) -> Self {
-//         Self { type_id, data }
-//     }
-// }
-//
-// #[derive(PartialEq, Eq, Debug)]
-// enum CheckedExpressionData {
-//     Unit,
-//     BoolLiteral(bool),
-//     IntLiteral(isize),
-//     Ident {
-//         name: String,
-//         variable_id: VariableId,
-//     },
-//
-//     Prefix {
-//         operator: PrefixOperator,
-//         expression: Box<CheckedExpression>,
-//     },
-//     Infix {
-//         left: Box<CheckedExpression>,
-//         operator: InfixOperator,
-//         right: Box<CheckedExpression>,
-//     },
-//
-//     VariableDecl {
-//         name: String,
-//         value: Box<CheckedExpression>,
-//         mutable: bool,
-//         variable_id: VariableId,
-//     },
-//
-//     Function {
-//         parameters: Vec<CheckedFunctionParameter>,
-//         return_type: TypeId,
-//         body: Vec<CheckedExpressionStatement>,
-//     },
-// }
-//
-// #[derive(PartialEq, Eq, Debug)]
-// pub enum CheckedFunctionParameter {
-//     LabeledAtCallsite {
-//         internal_name: String,
-//         external_name: Option<String>,
-//         type_id: TypeId,
-//     },
-//     UnlabeledAtCallsite {
-//         name: String,
-//         type_id: TypeId,
-//     },
-// }
+impl Checker {
+    pub fn new() -> Self {
+        Self::with_prelude(&Prelude::default()).expect("the empty prelude refers to no types")
+    }
+
+    /// A checker with the prelude's types and functions in the outermost
+    /// scope, or an error for the first type a function refers to that isn't
+    /// a builtin type or one of the prelude's.
+    pub fn with_prelude(prelude: &Prelude) -> Result<Self, Error> {
+        let builtin_types = BUILTIN_TYPES
+            .into_iter()
+            .chain(SIZED_INT_TYPES.map(|(name, _, _)| name))
+            .chain([ERROR_TYPE_NAME]);
+        let mut checker = Self {
+            cur_scope: 0,
+            scopes: vec![Scope { parent_scope: None }],
+            types: builtin_types
+                .chain(prelude.types.iter().map(String::as_str))
+                .map(|name| ScopedType::named(name, 0))
+                .collect(),
+            variables: vec![],
+            methods: vec![],
+            loops: vec![],
+            return_type: None,
+            infer_return_type: false,
+            function_depth: 0,
+            type_errors: vec![],
+            warnings: vec![],
+        };
+
+        for function in &prelude.functions {
+            let mut type_id = |name: &str| {
+                let ty = Type::Ident(name.to_string());
+                checker
+                    .check_type_name(&ty, 0)
+                    .ok_or_else(|| Error::type_not_found(ty, Span::from_range(0, 0)))
+            };
+            let parameters = function
+                .parameters
+                .iter()
+                .map(|(_, ty)| type_id(ty))
+                .collect::<Result<_, _>>()?;
+            let return_type = type_id(&function.return_type)?;
+            let function_type = checker.function_type(parameters, return_type);
+            let variable_id = checker.add_variable(&function.name, function_type);
+            checker.variables[variable_id].parameter_labels = Some(
+                function
+                    .parameters
+                    .iter()
+                    .map(|(label, _)| label.clone())
+                    .collect(),
+            );
+        }
+        Ok(checker)
+    }
+
+    fn create_scope(&mut self, parent_id: Option<ScopeId>) -> ScopeId {
+        self.scopes.push(Scope {
+            parent_scope: parent_id,
+        });
+        self.scopes.len() - 1
+    }
+
+    fn check_type_name(&mut self, ty: &Type, scope_id: ScopeId) -> Option<TypeId> {
+        let name = match ty {
+            Type::Ident(name) => name,
+            Type::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.check_type_name(element, scope_id))
+                    .collect::<Option<_>>()?;
+                return Some(self.tuple_type(elements));
+            }
+            // `Option` and `Result` are the only types that take arguments
+            Type::Applied { name, arguments } => {
+                return match (name.as_str(), &arguments[..]) {
+                    ("Option", [inner]) => {
+                        let inner = self.check_type_name(inner, scope_id)?;
+                        Some(self.option_type(inner))
+                    }
+                    ("Result", [ok, err]) => {
+                        let ok = self.check_type_name(ok, scope_id)?;
+                        let err = self.check_type_name(err, scope_id)?;
+                        Some(self.result_type(ok, err))
+                    }
+                    _ => None,
+                };
+            }
+            Type::Function {
+                parameters,
+                return_type,
+            } => {
+                let parameters = parameters
+                    .iter()
+                    .map(|parameter| self.check_type_name(parameter, scope_id))
+                    .collect::<Option<_>>()?;
+                let return_type = match return_type {
+                    Some(ty) => self.check_type_name(ty, scope_id)?,
+                    None => UNIT_ID,
+                };
+                return Some(self.function_type(parameters, return_type));
+            }
+        };
+        let cur_scope = &self.scopes[scope_id];
+        if let Some(type_id) = self.types.iter().position(|t| {
+            t.scope_id == scope_id
+                && matches!(
+                    &t.kind,
+                    TypeKind::Name(type_name)
+                    | TypeKind::Struct { name: type_name, .. }
+                    | TypeKind::Enum { name: type_name, .. }
+                        if type_name == name
+                )
+        }) {
+            Some(type_id)
+        } else if let Some(parent_id) = cur_scope.parent_scope {
+            self.check_type_name(ty, parent_id)
+        } else {
+            None
+        }
+    }
+
+    fn check_variable_name(
+        &self,
+        var_name: &str,
+        scope_id: ScopeId,
+    ) -> Option<(VariableId, TypeId)> {
+        let cur_scope = &self.scopes[scope_id];
+        if let Some((index, variable)) = self
+            .variables
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|t| t.1.scope_id == scope_id && t.1.name == var_name)
+        {
+            Some((index, variable.type_id))
+        } else if let Some(parent_id) = cur_scope.parent_scope {
+            self.check_variable_name(var_name, parent_id)
+        } else {
+            None
+        }
+    }
+
+    fn check_method_name(
+        &self,
+        receiver_type_id: TypeId,
+        method_name: &str,
+        scope_id: ScopeId,
+    ) -> Option<&ScopedMethod> {
+        let cur_scope = &self.scopes[scope_id];
+        if let Some(method) = self.methods.iter().find(|m| {
+            m.scope_id == scope_id
+                && m.receiver_type_id == receiver_type_id
+                && m.name == method_name
+        }) {
+            Some(method)
+        } else if let Some(parent_id) = cur_scope.parent_scope {
+            self.check_method_name(receiver_type_id, method_name, parent_id)
+        } else {
+            None
+        }
+    }
+
+    fn add_variable(&mut self, var_name: &str, var_type: TypeId) -> VariableId {
+        let variable_id = self.variables.len();
+        self.variables
+            .push(ScopedVariable::new(var_name, self.cur_scope, var_type));
+        variable_id
+    }
+
+    fn add_method(
+        &mut self,
+        method_name: &str,
+        receiver_type_id: TypeId,
+        method_type: TypeId,
+        parameter_labels: ParameterLabels,
+    ) {
+        self.methods.push(ScopedMethod::new(
+            method_name,
+            receiver_type_id,
+            self.cur_scope,
+            method_type,
+            parameter_labels,
+        ));
+    }
+
+    fn function_type(&mut self, parameters: Vec<TypeId>, return_type: TypeId) -> TypeId {
+        let function_type = ScopedType::function(parameters, return_type, 0);
+        if let Some(type_id) = self.types.iter().position(|t| t.kind == function_type.kind) {
+            return type_id;
+        }
+        self.types.push(function_type);
+        self.types.len() - 1
+    }
+
+    /// Declares a struct or enum in the current scope, before its fields are
+    /// checked so that they can refer to it.
+    fn declare_type(&mut self, name: &str) -> TypeId {
+        self.types.push(ScopedType::named(name, self.cur_scope));
+        self.types.len() - 1
+    }
+
+    /// The type of a field of a struct or enum variant. One that doesn't
+    /// exist is the error type, so that making the struct or variant doesn't
+    /// lead to more errors, and the first such error is kept in `first_error`.
+    fn field_type(&mut self, ty: &Type, span: Span, first_error: &mut Option<Error>) -> TypeId {
+        self.check_type_name(ty, self.cur_scope).unwrap_or_else(|| {
+            first_error.get_or_insert_with(|| Error::type_not_found(ty.clone(), span));
+            ERROR_ID
+        })
+    }
+
+    fn tuple_type(&mut self, elements: Vec<TypeId>) -> TypeId {
+        let tuple_type = ScopedType::tuple(elements, 0);
+        if let Some(type_id) = self.types.iter().position(|t| t.kind == tuple_type.kind) {
+            return type_id;
+        }
+        self.types.push(tuple_type);
+        self.types.len() - 1
+    }
+
+    fn option_type(&mut self, inner: TypeId) -> TypeId {
+        let option_type = ScopedType::option(inner, 0);
+        if let Some(type_id) = self.types.iter().position(|t| t.kind == option_type.kind) {
+            return type_id;
+        }
+        self.types.push(option_type);
+        self.types.len() - 1
+    }
+
+    fn result_type(&mut self, ok: TypeId, err: TypeId) -> TypeId {
+        let result_type = ScopedType::result(ok, err, 0);
+        if let Some(type_id) = self.types.iter().position(|t| t.kind == result_type.kind) {
+            return type_id;
+        }
+        self.types.push(result_type);
+        self.types.len() - 1
+    }
+
+    /// What an option of `type_id` holds, if it is an option.
+    fn option_inner(&self, type_id: TypeId) -> Option<TypeId> {
+        match self.types[type_id].kind {
+            TypeKind::Option(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    /// The `ok` and `err` types of a result, if `type_id` is one.
+    fn result_types(&self, type_id: TypeId) -> Option<(TypeId, TypeId)> {
+        match self.types[type_id].kind {
+            TypeKind::Result { ok, err } => Some((ok, err)),
+            _ => None,
+        }
+    }
+
+    pub fn check_program(&mut self, program: &Program) -> CheckedProgram {
+        self.try_check_program(program).unwrap()
+    }
+
+    /// Checks the program, returning the first type error if there are any.
+    pub fn try_check_program(&mut self, program: &Program) -> Result<CheckedProgram, Error> {
+        let checked = self.check_program_recovering(program);
+        match self.type_errors.first() {
+            Some(err) => Err(err.clone()),
+            None => Ok(checked),
+        }
+    }
+
+    /// Checks the whole program however many type errors it has, for tools
+    /// that want every error, or the types of what did check. A statement
+    /// with an error gets the error type, and so does the variable it
+    /// declares, unless the declaration says what type it has.
+    pub fn check_program_recovering(&mut self, program: &Program) -> CheckedProgram {
+        let _span = tracing::info_span!("check").entered();
+        CheckedProgram {
+            stmts: self.check_block(&program.0, None),
+        }
+    }
+
+    /// The type errors of everything checked so far, in the order they were
+    /// found, leaving out the ones that only follow from an earlier one.
+    pub fn errors(&self) -> &[Error] {
+        &self.type_errors
+    }
+
+    /// The warnings of everything checked so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The type as it's written, like `fun(Int) Option(Bool)`.
+    pub fn type_name(&self, type_id: TypeId) -> String {
+        let names = |type_ids: &[TypeId]| {
+            type_ids
+                .iter()
+                .map(|&type_id| self.type_name(type_id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match &self.types[type_id].kind {
+            TypeKind::Name(name) | TypeKind::Struct { name, .. } | TypeKind::Enum { name, .. } => {
+                name.clone()
+            }
+            TypeKind::Function {
+                parameters,
+                return_type: UNIT_ID,
+            } => format!("fun({})", names(parameters)),
+            TypeKind::Function {
+                parameters,
+                return_type,
+            } => format!(
+                "fun({}) {}",
+                names(parameters),
+                self.type_name(*return_type)
+            ),
+            TypeKind::Tuple(elements) if elements.len() == 1 => format!("({},)", names(elements)),
+            TypeKind::Tuple(elements) => format!("({})", names(elements)),
+            TypeKind::Option(inner) => format!("Option({})", self.type_name(*inner)),
+            TypeKind::Result { ok, err } => {
+                format!("Result({}, {})", self.type_name(*ok), self.type_name(*err))
+            }
+        }
+    }
+
+    fn check_expression_statment(
+        &mut self,
+        stmt: &ExpressionStatement,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpressionStatement, Error> {
+        let expr = self.check_expression(&stmt.expr, type_hint)?;
+        if stmt.discarded && expr.type_id != UNIT_ID {
+            self.warnings.push(Warning {
+                span: stmt.expr.span,
+                kind: WarningKind::UnusedValue {
+                    type_id: expr.type_id,
+                },
+            });
+        }
+        Ok(CheckedExpressionStatement {
+            discarded: stmt.discarded,
+            expr,
+        })
+    }
+
+    fn check_expression(
+        &mut self,
+        expr: &Expression,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        match &expr.kind {
+            ExpressionKind::Unit => {
+                self.typed_expression(CheckedExpressionData::Unit, expr.span, UNIT_ID, type_hint)
+            }
+            ExpressionKind::BoolLiteral(value) => self.typed_expression(
+                CheckedExpressionData::BoolLiteral(*value),
+                expr.span,
+                BOOL_ID,
+                type_hint,
+            ),
+            ExpressionKind::IntLiteral(_) | ExpressionKind::SuffixedIntLiteral { .. } => {
+                self.check_int_literal(expr, false, type_hint)
+            }
+            ExpressionKind::FloatLiteral(value) => self.typed_expression(
+                CheckedExpressionData::FloatLiteral(*value),
+                expr.span,
+                FLOAT_ID,
+                type_hint,
+            ),
+            ExpressionKind::StringLiteral(value) => self.typed_expression(
+                CheckedExpressionData::StringLiteral(value.clone()),
+                expr.span,
+                STRING_ID,
+                type_hint,
+            ),
+            ExpressionKind::CharLiteral(value) => self.typed_expression(
+                CheckedExpressionData::CharLiteral(*value),
+                expr.span,
+                CHAR_ID,
+                type_hint,
+            ),
+            ExpressionKind::Prefix {
+                operator,
+                expression,
+            } => match operator {
+                LogicalNot => {
+                    let res = self.check_expression(expression, Some(BOOL_ID))?;
+                    self.typed_expression(
+                        CheckedExpressionData::Prefix {
+                            operator: *operator,
+                            expression: Box::new(res),
+                        },
+                        expr.span,
+                        BOOL_ID,
+                        type_hint,
+                    )
+                }
+                Negative => {
+                    let res = match expression.kind {
+                        // so that e.g. `-128i8` fits
+                        ExpressionKind::IntLiteral(_)
+                        | ExpressionKind::SuffixedIntLiteral { .. } => {
+                            self.check_int_literal(expression, true, type_hint)?
+                        }
+                        _ => self.check_expression(expression, int_hint(type_hint))?,
+                    };
+                    if res.type_id != FLOAT_ID
+                        && int_range(res.type_id).is_none_or(|(min, _)| min >= 0)
+                    {
+                        return Err(Error::type_mismatch(INT_ID, res.type_id, expression.span));
+                    }
+                    let type_id = res.type_id;
+                    self.typed_expression(
+                        CheckedExpressionData::Prefix {
+                            operator: *operator,
+                            expression: Box::new(res),
+                        },
+                        expr.span,
+                        type_id,
+                        type_hint,
+                    )
+                }
+            },
+            // `x |> f(y)` is `f(x, y)`
+            ExpressionKind::Infix {
+                left,
+                operator: Pipe,
+                right,
+            } => self.check_call(right, Some(left), expr.span, type_hint),
+            // `a ?? b` is what `a` holds, or `b` if it's `none`
+            ExpressionKind::Infix {
+                left,
+                operator: Coalesce,
+                right,
+            } => {
+                let left = self.check_expression(left, None)?;
+                let Some(type_id) = self.option_inner(left.type_id) else {
+                    return Err(Error::not_an_option(left.type_id, expr.span));
+                };
+                let right = self.check_expression(right, Some(type_id))?;
+                self.typed_expression(
+                    CheckedExpressionData::Infix {
+                        left: Box::new(left),
+                        operator: Coalesce,
+                        right: Box::new(right),
+                    },
+                    expr.span,
+                    type_id,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Infix {
+                left,
+                operator,
+                right,
+            } => {
+                // a literal on the left takes the int type the result should have,
+                // anything else decides the type of the operation itself
+                let left_hint = match (&left.kind, operator) {
+                    (
+                        ExpressionKind::IntLiteral(_),
+                        Plus | Minus | Divide | Multiply | Power | ShiftLeft | ShiftRight,
+                    ) => int_hint(type_hint),
+                    (_, And | Or) => Some(BOOL_ID),
+                    _ => None,
+                };
+                let left = self.check_expression(left, left_hint)?;
+                match operator {
+                    // ints, and floats, which can't be shifted
+                    Plus | Minus | Divide | Multiply | Power | ShiftLeft | ShiftRight
+                        if is_int_type(left.type_id)
+                            || left.type_id == FLOAT_ID
+                                && !matches!(operator, ShiftLeft | ShiftRight) =>
+                    {
+                        let right = self.check_expression(right, Some(left.type_id))?;
+                        let type_id = left.type_id;
+                        self.typed_expression(
+                            CheckedExpressionData::Infix {
+                                left: Box::new(left),
+                                operator: *operator,
+                                right: Box::new(right),
+                            },
+                            expr.span,
+                            type_id,
+                            type_hint,
+                        )
+                    }
+                    LessThan | GreaterThan | LessThanOrEqual | GreaterThanOrEqual
+                        if is_number_type(left.type_id) =>
+                    {
+                        let right = self.check_expression(right, Some(left.type_id))?;
+                        self.typed_expression(
+                            CheckedExpressionData::Infix {
+                                left: Box::new(left),
+                                operator: *operator,
+                                right: Box::new(right),
+                            },
+                            expr.span,
+                            BOOL_ID,
+                            type_hint,
+                        )
+                    }
+
+                    // any builtin type
+                    Equal | NotEqual if is_builtin_type(left.type_id) => {
+                        let right = self.check_expression(right, Some(left.type_id))?;
+                        self.typed_expression(
+                            CheckedExpressionData::Infix {
+                                left: Box::new(left),
+                                operator: *operator,
+                                right: Box::new(right),
+                            },
+                            expr.span,
+                            BOOL_ID,
+                            type_hint,
+                        )
+                    }
+
+                    // the right operand is only evaluated when the left one
+                    // doesn't already decide the result
+                    And | Or => {
+                        let right = self.check_expression(right, Some(BOOL_ID))?;
+                        self.typed_expression(
+                            CheckedExpressionData::Infix {
+                                left: Box::new(left),
+                                operator: *operator,
+                                right: Box::new(right),
+                            },
+                            expr.span,
+                            BOOL_ID,
+                            type_hint,
+                        )
+                    }
+
+                    Compose => {
+                        let res = self.check_composition(left, right, expr.span)?;
+                        let type_id = res.type_id;
+                        self.typed_expression(res.data, expr.span, type_id, type_hint)
+                    }
+
+                    // everything else is looked up as a method on the left operand
+                    _ => {
+                        let res = self.check_operator_method(left, *operator, right, expr.span)?;
+                        let type_id = res.type_id;
+                        self.typed_expression(res.data, expr.span, type_id, type_hint)
+                    }
+                }
+            }
+            ExpressionKind::CustomInfix {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.check_expression(left, None)?;
+                let Some(method) = operator.method.as_deref().filter(|method| {
+                    self.check_method_name(left.type_id, method, self.cur_scope)
+                        .is_some()
+                }) else {
+                    return Err(Error::custom_operator_not_implemented(
+                        &operator.symbol,
+                        left.type_id,
+                        expr.span,
+                    ));
+                };
+                let call = self.call_operator_method(left, method, right, expr.span)?;
+                let type_id = call.type_id;
+                self.typed_expression(call.data, expr.span, type_id, type_hint)
+            }
+            ExpressionKind::ChainedComparison {
+                operands,
+                operators,
+            } => {
+                let mut checked_operands = vec![self.check_expression(&operands[0], None)?];
+                for (operator, operand) in operators.iter().zip(&operands[1..]) {
+                    let left_type = checked_operands.last().unwrap().type_id;
+                    let right_type =
+                        self.comparison_operand_type(left_type, *operator, expr.span)?;
+                    checked_operands.push(self.check_expression(operand, Some(right_type))?);
+                }
+
+                self.typed_expression(
+                    CheckedExpressionData::ChainedComparison {
+                        operands: checked_operands,
+                        operators: operators.clone(),
+                    },
+                    expr.span,
+                    BOOL_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                mutable,
+                ty,
+                separator,
+                ..
+            } => {
+                let annotated_type = ty
+                    .as_ref()
+                    .map(|(ty, span)| {
+                        self.check_type_name(ty, self.cur_scope)
+                            .ok_or_else(|| Error::type_not_found(ty.clone(), *span))
+                    })
+                    .transpose()?;
+                let r_value = self.check_expression(value, annotated_type)?;
+                let id = self.add_variable(name, r_value.type_id);
+                self.variables[id].parameter_labels = self.parameter_labels(&r_value);
+                self.variables[id].mutable = *mutable;
+                self.variables[id].declaration = Some((expr.span, *separator));
+                self.typed_expression(
+                    CheckedExpressionData::VariableDecl {
+                        name: name.to_string(),
+                        value: Box::new(r_value),
+                        mutable: *mutable,
+                        variable_id: id,
+                    },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Block(body) => {
+                let scope = self.create_scope(Some(self.cur_scope));
+                let body = self.in_scope(scope, |checker| checker.check_block(body, type_hint));
+                let type_id = match body.last() {
+                    Some(last) if !last.discarded => last.expr.type_id,
+                    _ => UNIT_ID,
+                };
+                self.typed_expression(
+                    CheckedExpressionData::Block(body),
+                    expr.span,
+                    type_id,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Assign { target, value } => {
+                // nothing can be indexed until there are arrays and strings
+                if let ExpressionKind::Index { .. } = target.kind {
+                    return Err(Error::unsupported("assigning to an index", target.span));
+                }
+                let target = self.check_expression(target, None)?;
+                let Some(variable_id) = assigned_variable(&target) else {
+                    return Err(Error {
+                        span: expr.span,
+                        kind: ErrorKind::InvalidAssignmentTarget,
+                    });
+                };
+                let variable = &self.variables[variable_id];
+                if !variable.mutable {
+                    return Err(Error::assign_to_immutable(variable, expr.span));
+                }
+                let value = self.check_expression(value, Some(target.type_id))?;
+                self.typed_expression(
+                    CheckedExpressionData::Assign {
+                        target: Box::new(target),
+                        value: Box::new(value),
+                    },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Ident(name) => {
+                if let Some((var_id, var_type)) = self.check_variable_name(name, self.cur_scope) {
+                    self.check_declared(var_id, expr.span)?;
+                    self.typed_expression(
+                        CheckedExpressionData::Ident {
+                            name: name.to_string(),
+                            variable_id: var_id,
+                        },
+                        expr.span,
+                        var_type,
+                        type_hint,
+                    )
+                } else if name == "none" {
+                    // an option of whatever type is expected
+                    match type_hint {
+                        Some(type_id)
+                            if type_id == ERROR_ID || self.option_inner(type_id).is_some() =>
+                        {
+                            Ok(CheckedExpression::new(
+                                CheckedExpressionData::OptionNone,
+                                type_id,
+                            ))
+                        }
+                        Some(type_id) => Err(Error::not_an_option(type_id, expr.span)),
+                        None => Err(Error::cannot_infer_none_type(expr.span)),
+                    }
+                } else {
+                    Err(Error::variable_not_found(name, expr.span))
+                }
+            }
+            ExpressionKind::Function { .. } => {
+                let res = self.check_function(expr, None, type_hint)?;
+                let type_id = res.type_id;
+                self.typed_expression(res.data, expr.span, type_id, type_hint)
+            }
+            ExpressionKind::FunctionCall { .. }
+            | ExpressionKind::Call { .. }
+            | ExpressionKind::MethodCall { .. } => {
+                self.check_call(expr, None, expr.span, type_hint)
+            }
+            ExpressionKind::Tuple(elements) => {
+                let element_hints = match type_hint.map(|type_id| &self.types[type_id].kind) {
+                    Some(TypeKind::Tuple(hints)) if hints.len() == elements.len() => {
+                        hints.iter().copied().map(Some).collect()
+                    }
+                    _ => vec![None; elements.len()],
+                };
+                let elements = elements
+                    .iter()
+                    .zip(element_hints)
+                    .map(|(element, hint)| self.check_expression(element, hint))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let element_types = elements.iter().map(|element| element.type_id).collect();
+                let type_id = self.tuple_type(element_types);
+                self.typed_expression(
+                    CheckedExpressionData::Tuple(elements),
+                    expr.span,
+                    type_id,
+                    type_hint,
+                )
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                if let Some(type_id) = self.named_enum(target) {
+                    return self.check_variant(type_id, field, None, &[], expr.span, type_hint);
+                }
+                let target = self.check_expression(target, None)?;
+                let Some((index, field_type)) = self.find_field(target.type_id, field) else {
+                    return Err(Error::field_not_found(target.type_id, field, expr.span));
+                };
+                self.typed_expression(
+                    CheckedExpressionData::Field {
+                        target: Box::new(target),
+                        index,
+                    },
+                    expr.span,
+                    field_type,
+                    type_hint,
+                )
+            }
+            ExpressionKind::SafeFieldAccess { target, field } => {
+                let target = self.check_expression(target, None)?;
+                let Some(inner) = self.option_inner(target.type_id) else {
+                    return Err(Error::not_an_option(target.type_id, expr.span));
+                };
+                let Some((index, field_type)) = self.find_field(inner, field) else {
+                    return Err(Error::field_not_found(inner, field, expr.span));
+                };
+                // a field that's an option already isn't wrapped in another
+                let type_id = match self.option_inner(field_type) {
+                    Some(_) => field_type,
+                    None => self.option_type(field_type),
+                };
+                self.typed_expression(
+                    CheckedExpressionData::SafeField {
+                        target: Box::new(target),
+                        index,
+                    },
+                    expr.span,
+                    type_id,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Index { target, index } => {
+                // nothing can be indexed until there are arrays and strings,
+                // so this checks like anything else with an error
+                self.check_expression(target, None)?;
+                self.check_expression(index, None)?;
+                self.type_errors
+                    .push(Error::unsupported("indexing", expr.span));
+                Ok(CheckedExpression::new(
+                    CheckedExpressionData::Error,
+                    ERROR_ID,
+                ))
+            }
+            ExpressionKind::Impl { ty, methods } => {
+                let Some(type_id) = self.check_type_name(ty, self.cur_scope) else {
+                    return Err(Error::type_not_found(ty.clone(), expr.span));
+                };
+
+                // register every signature first so methods can call each other
+                let mut signatures = vec![];
+                for method in methods {
+                    let ExpressionKind::Function { parameters, .. } = &method.function.kind else {
+                        unreachable!("the parser only produces functions as methods")
+                    };
+                    if !matches!(parameters.first(), Some(FunctionParameter::SelfParameter)) {
+                        return Err(Error::missing_self_parameter(
+                            &method.name,
+                            method.function.span,
+                        ));
+                    }
+                    if self.methods.iter().any(|m| {
+                        m.scope_id == self.cur_scope
+                            && m.receiver_type_id == type_id
+                            && m.name == method.name
+                    }) {
+                        return Err(Error::method_already_defined(
+                            type_id,
+                            &method.name,
+                            method.function.span,
+                        ));
+                    }
+                    let scope = self.create_scope(Some(self.cur_scope));
+                    let signature = self.in_scope(scope, |checker| {
+                        checker.check_function_signature(&method.function, Some(type_id), None)
+                    })?;
+                    let labels = signature.parameters[1..]
+                        .iter()
+                        .map(|p| p.label().map(str::to_string))
+                        .collect();
+                    self.add_method(&method.name, type_id, signature.type_id, labels);
+                    signatures.push((scope, signature));
+                }
+
+                let mut checked_methods = vec![];
+                for (method, (scope, signature)) in methods.iter().zip(signatures) {
+                    checked_methods.push(CheckedMethod {
+                        name: method.name.clone(),
+                        function: self.check_function_body(&method.function, scope, signature)?,
+                    });
+                }
+
+                self.typed_expression(
+                    CheckedExpressionData::Impl {
+                        type_id,
+                        methods: checked_methods,
+                    },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Struct { name, fields, .. } => {
+                let type_id = self.declare_type(name);
+                let mut first_error = None;
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        let type_id = self.field_type(&field.ty, field.span, &mut first_error);
+                        (field.name.clone(), type_id)
+                    })
+                    .collect();
+                self.types[type_id].kind = TypeKind::Struct {
+                    name: name.clone(),
+                    fields,
+                };
+                if let Some(err) = first_error {
+                    return Err(err);
+                }
+
+                self.typed_expression(
+                    CheckedExpressionData::Struct { type_id },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Enum { name, variants, .. } => {
+                let type_id = self.declare_type(name);
+                let mut first_error = None;
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let fields = variant
+                            .fields
+                            .iter()
+                            .map(|field| {
+                                let type_id =
+                                    self.field_type(&field.ty, variant.span, &mut first_error);
+                                (field.label.clone(), type_id)
+                            })
+                            .collect();
+                        (variant.name.clone(), fields)
+                    })
+                    .collect();
+                self.types[type_id].kind = TypeKind::Enum {
+                    name: name.clone(),
+                    variants,
+                };
+                if let Some(err) = first_error {
+                    return Err(err);
+                }
+
+                self.typed_expression(
+                    CheckedExpressionData::Enum { type_id },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::OperatorDecl(operator) => self.typed_expression(
+                CheckedExpressionData::OperatorDecl(operator.symbol.clone()),
+                expr.span,
+                UNIT_ID,
+                type_hint,
+            ),
+            ExpressionKind::Match { value, arms } => {
+                let value = self.check_expression(value, None)?;
+
+                // every arm has to have the same type as the first one
+                let mut arm_type = type_hint;
+                let mut checked_arms = vec![];
+                for arm in arms {
+                    let checked_arm = self.check_match_arm(arm, value.type_id, arm_type)?;
+                    arm_type = Some(checked_arm.body.type_id);
+                    checked_arms.push(checked_arm);
+                }
+
+                self.typed_expression(
+                    CheckedExpressionData::Match {
+                        value: Box::new(value),
+                        arms: checked_arms,
+                    },
+                    expr.span,
+                    arm_type.unwrap_or(UNIT_ID),
+                    type_hint,
+                )
+            }
+            // there's nothing to iterate over until there are arrays and ranges
+            ExpressionKind::For { .. } => Err(Error::unsupported("for loops", expr.span)),
+            ExpressionKind::Range { .. } => Err(Error::unsupported("ranges", expr.span)),
+            ExpressionKind::While {
+                label,
+                condition,
+                body,
+            } => {
+                let condition = self.check_expression(condition, Some(BOOL_ID))?;
+
+                self.loops.push(EnclosingLoop {
+                    label: label.clone(),
+                    accepts_break_value: false,
+                    break_type: None,
+                });
+                let scope = self.create_scope(Some(self.cur_scope));
+                let body = self.in_scope(scope, |checker| checker.check_block(body, None));
+                self.loops.pop();
+
+                self.typed_expression(
+                    CheckedExpressionData::While {
+                        label: label.clone(),
+                        condition: Box::new(condition),
+                        body,
+                    },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Loop { label, body } => {
+                self.loops.push(EnclosingLoop {
+                    label: label.clone(),
+                    accepts_break_value: true,
+                    break_type: type_hint,
+                });
+                let scope = self.create_scope(Some(self.cur_scope));
+                let body = self.in_scope(scope, |checker| checker.check_block(body, None));
+                let enclosing_loop = self.loops.pop().unwrap();
+
+                // the loop evaluates to whatever it's broken out of with
+                self.typed_expression(
+                    CheckedExpressionData::Loop {
+                        label: label.clone(),
+                        body,
+                    },
+                    expr.span,
+                    enclosing_loop.break_type.unwrap_or(UNIT_ID),
+                    type_hint,
+                )
+            }
+            ExpressionKind::Break { label, value } => {
+                let Some(loop_index) = self.find_loop(label.as_deref()) else {
+                    return Err(Error::break_outside_loop(expr.span));
+                };
+
+                let value = match value {
+                    Some(value) => {
+                        let enclosing_loop = &self.loops[loop_index];
+                        if !enclosing_loop.accepts_break_value {
+                            return Err(Error::unexpected_break_value(value.span));
+                        }
+                        let value = self.check_expression(value, enclosing_loop.break_type)?;
+                        self.loops[loop_index].break_type = Some(value.type_id);
+                        Some(Box::new(value))
+                    }
+                    // breaking without a value is the same as breaking with `unit`
+                    None => {
+                        let enclosing_loop = &mut self.loops[loop_index];
+                        if enclosing_loop.accepts_break_value {
+                            match enclosing_loop.break_type {
+                                Some(break_type) if break_type != UNIT_ID => {
+                                    return Err(Error::type_mismatch(
+                                        break_type, UNIT_ID, expr.span,
+                                    ));
+                                }
+                                _ => enclosing_loop.break_type = Some(UNIT_ID),
+                            }
+                        }
+                        None
+                    }
+                };
+
+                self.typed_expression(
+                    CheckedExpressionData::Break {
+                        label: label.clone(),
+                        value,
+                    },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Continue { label } => {
+                if self.find_loop(label.as_deref()).is_none() {
+                    return Err(Error::continue_outside_loop(expr.span));
+                }
+
+                self.typed_expression(
+                    CheckedExpressionData::Continue {
+                        label: label.clone(),
+                    },
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            ExpressionKind::Return(value) => {
+                if self.return_type.is_none() && !self.infer_return_type {
+                    return Err(Error::return_outside_function(expr.span));
+                }
+                let return_type = self.return_type;
+                // returning without a value is the same as returning `unit`
+                let value = match value {
+                    Some(value) => Some(Box::new(self.check_expression(value, return_type)?)),
+                    None => None,
+                };
+                let value_type = value.as_ref().map_or(UNIT_ID, |value| value.type_id);
+                match return_type {
+                    None => self.return_type = Some(value_type),
+                    Some(return_type) if value.is_none() && !types_match(return_type, UNIT_ID) => {
+                        return Err(Error::type_mismatch(return_type, UNIT_ID, expr.span));
+                    }
+                    Some(_) => {}
+                }
+                self.typed_expression(
+                    CheckedExpressionData::Return(value),
+                    expr.span,
+                    UNIT_ID,
+                    type_hint,
+                )
+            }
+            // `value?` is what `value` holds if it's `ok`, and otherwise returns its error
+            ExpressionKind::Try(value) => {
+                let value = self.check_expression(value, None)?;
+                let Some((ok, err)) = self.result_types(value.type_id) else {
+                    return Err(Error::not_a_result(value.type_id, expr.span));
+                };
+                let Some((_, returned_err)) = self
+                    .return_type
+                    .and_then(|return_type| self.result_types(return_type))
+                else {
+                    return Err(Error::try_outside_result_function(
+                        self.return_type,
+                        expr.span,
+                    ));
+                };
+                if !types_match(returned_err, err) {
+                    return Err(Error::type_mismatch(returned_err, err, expr.span));
+                }
+                self.typed_expression(
+                    CheckedExpressionData::Try(Box::new(value)),
+                    expr.span,
+                    ok,
+                    type_hint,
+                )
+            }
+            // unless the program was configured first, every statement is kept
+            ExpressionKind::Cfg { expr, .. } => self.check_expression(expr, type_hint),
+            ExpressionKind::Include(path) | ExpressionKind::EmbedText(path) => Err(Error {
+                span: expr.span,
+                kind: ErrorKind::UnresolvedFile { path: path.clone() },
+            }),
+        }
+    }
+
+    /// Finds the innermost loop with the given label, or the innermost loop
+    /// if there's no label.
+    fn find_loop(&self, label: Option<&str>) -> Option<usize> {
+        match label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rposition(|l| l.label.as_deref() == Some(label)),
+            None => self.loops.len().checked_sub(1),
+        }
+    }
+
+    /// Checks every statement, carrying on past the ones with errors. The
+    /// last one is checked against `type_hint`, unless it's discarded.
+    fn check_block(
+        &mut self,
+        body: &[ExpressionStatement],
+        type_hint: Option<TypeId>,
+    ) -> Vec<CheckedExpressionStatement> {
+        let hoisted = body
+            .iter()
+            .map(|stmt| self.hoist_function(stmt, body))
+            .collect::<Vec<_>>();
+        body.iter()
+            .zip(hoisted)
+            .enumerate()
+            .map(|(i, (stmt, hoisted))| {
+                let type_hint = type_hint.filter(|_| i == body.len() - 1 && !stmt.discarded);
+                let first_new_variable = self.variables.len();
+                // one whose signature uses a type declared earlier in the
+                // block can still call itself
+                let hoisted = hoisted.or_else(|| self.hoist_function(stmt, body));
+                let checked = match hoisted {
+                    Some(hoisted) => self.check_hoisted_function(stmt, hoisted),
+                    None => self.check_expression_statment(stmt, type_hint),
+                };
+                checked.unwrap_or_else(|err| {
+                    self.recover_from(err, stmt, first_new_variable);
+                    CheckedExpressionStatement {
+                        expr: CheckedExpression::new(CheckedExpressionData::Error, ERROR_ID),
+                        discarded: stmt.discarded,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Declares a function ahead of the rest of the block, so that it can be
+    /// called from its own body and the bodies of functions declared before
+    /// it, if it's declared
+    /// with `::`, says what it returns, and is the only thing in the block
+    /// with its name. A function whose signature has errors isn't, and they
+    /// are reported when its declaration is checked without it.
+    fn hoist_function(
+        &mut self,
+        stmt: &ExpressionStatement,
+        body: &[ExpressionStatement],
+    ) -> Option<HoistedFunction> {
+        let ExpressionKind::VariableDecl {
+            name,
+            value,
+            mutable: false,
+            ty: None,
+            separator,
+            ..
+        } = &stmt.expr.kind
+        else {
+            return None;
+        };
+        let ExpressionKind::Function {
+            return_type: Some(_),
+            ..
+        } = &value.kind
+        else {
+            return None;
+        };
+        let declarations = body
+            .iter()
+            .filter(|other| {
+                matches!(&other.expr.kind, ExpressionKind::VariableDecl { name: other, .. } if other == name)
+            })
+            .count();
+        if declarations > 1 {
+            return None;
+        }
+
+        let scope = self.create_scope(Some(self.cur_scope));
+        let signature = self
+            .in_scope(scope, |checker| {
+                checker.check_function_signature(value, None, None)
+            })
+            .ok()?;
+        let variable_id = self.add_variable(name, signature.type_id);
+        self.variables[variable_id].declaration = Some((stmt.expr.span, *separator));
+        self.variables[variable_id].hoisted_at = Some(self.function_depth);
+        self.variables[variable_id].parameter_labels = Some(
+            signature
+                .parameters
+                .iter()
+                .map(|p| p.label().map(str::to_string))
+                .collect(),
+        );
+        Some(HoistedFunction {
+            scope,
+            signature,
+            variable_id,
+        })
+    }
+
+    /// Makes sure that a function that was hoisted is only used before its
+    /// declaration in the body of another function, which can't be called
+    /// until the block it's in has been run up to the declaration.
+    fn check_declared(&self, variable_id: VariableId, span: Span) -> Result<(), Error> {
+        let variable = &self.variables[variable_id];
+        match variable.hoisted_at {
+            Some(depth) if depth == self.function_depth => {
+                Err(Error::used_before_declaration(variable, span))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the declaration of a function that [`Self::hoist_function`]
+    /// already declared.
+    fn check_hoisted_function(
+        &mut self,
+        stmt: &ExpressionStatement,
+        hoisted: HoistedFunction,
+    ) -> Result<CheckedExpressionStatement, Error> {
+        let ExpressionKind::VariableDecl { name, value, .. } = &stmt.expr.kind else {
+            unreachable!("only function declarations are hoisted")
+        };
+        self.variables[hoisted.variable_id].hoisted_at = None;
+        let function = self.check_function_body(value, hoisted.scope, hoisted.signature)?;
+        Ok(CheckedExpressionStatement {
+            discarded: stmt.discarded,
+            expr: CheckedExpression::new(
+                CheckedExpressionData::VariableDecl {
+                    name: name.to_string(),
+                    value: Box::new(function),
+                    mutable: false,
+                    variable_id: hoisted.variable_id,
+                },
+                UNIT_ID,
+            ),
+        })
+    }
+
+    /// Reports the error unless it follows from one that already was, and
+    /// declares the variable the statement would have if it didn't get that
+    /// far, so that using it doesn't make for more errors.
+    fn recover_from(&mut self, err: Error, stmt: &ExpressionStatement, first_new_variable: usize) {
+        if !err.follows_earlier_error() {
+            self.type_errors.push(err);
+        }
+        if let ExpressionKind::VariableDecl { name, ty, .. } = &stmt.expr.kind
+            && !self.variables[first_new_variable..]
+                .iter()
+                .any(|variable| variable.scope_id == self.cur_scope && variable.name == *name)
+        {
+            let type_id = ty
+                .as_ref()
+                .and_then(|(ty, _)| self.check_type_name(ty, self.cur_scope))
+                .unwrap_or(ERROR_ID);
+            self.add_variable(name, type_id);
+        }
+    }
+
+    /// Checks a call, or with `piped`, what `piped |> call` calls: `piped` is
+    /// passed before the other arguments, so it goes to the first unlabeled
+    /// parameter. Anything other than a call is called with just `piped`.
+    fn check_call(
+        &mut self,
+        call: &Expression,
+        piped: Option<&Expression>,
+        span: Span,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        let (callee, arguments) = match &call.kind {
+            ExpressionKind::FunctionCall { name, arguments } => {
+                let Some((variable_id, function_type)) =
+                    self.check_variable_name(name, self.cur_scope)
+                else {
+                    return match name.as_str() {
+                        "some" => self.check_some(piped, arguments, span, type_hint),
+                        "ok" | "err" => {
+                            self.check_result_variant(name, piped, arguments, span, type_hint)
+                        }
+                        _ => self.check_construction(name, piped, arguments, span, type_hint),
+                    };
+                };
+                let TypeKind::Function {
+                    parameters,
+                    return_type,
+                } = self.types[function_type].kind.clone()
+                else {
+                    return Err(Error::not_a_function(function_type, span));
+                };
+                self.check_declared(variable_id, span)?;
+
+                // without a declaration to go by, every argument is positional
+                let labels = self.variables[variable_id]
+                    .parameter_labels
+                    .clone()
+                    .unwrap_or_else(|| vec![None; parameters.len()]);
+                let arguments =
+                    self.check_arguments(piped, arguments, &parameters, &labels, span)?;
+
+                return self.typed_expression(
+                    CheckedExpressionData::FunctionCall {
+                        name: name.to_string(),
+                        variable_id,
+                        arguments,
+                    },
+                    span,
+                    return_type,
+                    type_hint,
+                );
+            }
+            ExpressionKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => {
+                return self.check_method_call(receiver, method, piped, arguments, span, type_hint);
+            }
+            ExpressionKind::Call { callee, arguments } => (&**callee, &arguments[..]),
+            // `x |> some` is `some(x)`, and the same goes for `ok` and `err`
+            ExpressionKind::Ident(name)
+                if matches!(name.as_str(), "some" | "ok" | "err")
+                    && self.check_variable_name(name, self.cur_scope).is_none() =>
+            {
+                return match name.as_str() {
+                    "some" => self.check_some(piped, &[], span, type_hint),
+                    _ => self.check_result_variant(name, piped, &[], span, type_hint),
+                };
+            }
+            _ => (call, &[][..]),
+        };
+
+        let callee = self.check_expression(callee, None)?;
+        let TypeKind::Function {
+            parameters,
+            return_type,
+        } = self.types[callee.type_id].kind.clone()
+        else {
+            return Err(Error::not_a_function(callee.type_id, span));
+        };
+
+        let labels = self
+            .parameter_labels(&callee)
+            .unwrap_or_else(|| vec![None; parameters.len()]);
+        let arguments = self.check_arguments(piped, arguments, &parameters, &labels, span)?;
+
+        self.typed_expression(
+            CheckedExpressionData::Call {
+                callee: Box::new(callee),
+                arguments,
+            },
+            span,
+            return_type,
+            type_hint,
+        )
+    }
+
+    /// Checks `receiver.method(arguments)`, or makes a variant when the
+    /// receiver names an enum.
+    fn check_method_call(
+        &mut self,
+        receiver: &Expression,
+        method: &str,
+        piped: Option<&Expression>,
+        arguments: &[FunctionArgument],
+        span: Span,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        if let Some(type_id) = self.named_enum(receiver) {
+            return self.check_variant(type_id, method, piped, arguments, span, type_hint);
+        }
+        let receiver = self.check_expression(receiver, None)?;
+        let Some(scoped_method) = self.check_method_name(receiver.type_id, method, self.cur_scope)
+        else {
+            let Some(type_id) = int_conversion(receiver.type_id, method) else {
+                return Err(Error::method_not_found(receiver.type_id, method, span));
+            };
+            let argument_count = arguments.len() + usize::from(piped.is_some());
+            if argument_count != 0 {
+                return Err(Error::argument_count_mismatch(0, argument_count, span));
+            }
+            return self.typed_expression(
+                CheckedExpressionData::MethodCall {
+                    receiver: Box::new(receiver),
+                    method: method.to_string(),
+                    arguments: vec![],
+                },
+                span,
+                type_id,
+                type_hint,
+            );
+        };
+        let labels = scoped_method.parameter_labels.clone();
+        let TypeKind::Function {
+            parameters,
+            return_type,
+        } = self.types[scoped_method.type_id].kind.clone()
+        else {
+            unreachable!("methods always have function types")
+        };
+
+        // the first parameter is always `self`, which is the receiver
+        let checked_arguments =
+            self.check_arguments(piped, arguments, &parameters[1..], &labels, span)?;
+
+        self.typed_expression(
+            CheckedExpressionData::MethodCall {
+                receiver: Box::new(receiver),
+                method: method.to_string(),
+                arguments: checked_arguments,
+            },
+            span,
+            return_type,
+            type_hint,
+        )
+    }
+
+    /// Checks `some(value)`, which makes an option of whatever type `value` is.
+    fn check_some(
+        &mut self,
+        piped: Option<&Expression>,
+        arguments: &[FunctionArgument],
+        span: Span,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        let value = single_argument(piped, arguments, span)?;
+        let inner_hint = type_hint.and_then(|type_id| self.option_inner(type_id));
+        let value = self.check_expression(value, inner_hint)?;
+        let type_id = self.option_type(value.type_id);
+        self.typed_expression(
+            CheckedExpressionData::OptionSome(Box::new(value)),
+            span,
+            type_id,
+            type_hint,
+        )
+    }
+
+    /// Checks `ok(value)` or `err(error)`, which take the rest of their type
+    /// from the result they're expected to be.
+    fn check_result_variant(
+        &mut self,
+        name: &str,
+        piped: Option<&Expression>,
+        arguments: &[FunctionArgument],
+        span: Span,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        let value = single_argument(piped, arguments, span)?;
+        let Some(type_id) = type_hint else {
+            return Err(Error::cannot_infer_result_type(span));
+        };
+        let (ok, err) = match self.result_types(type_id) {
+            Some(types) => types,
+            None if type_id == ERROR_ID => (ERROR_ID, ERROR_ID),
+            None => return Err(Error::not_a_result(type_id, span)),
+        };
+        let data = if name == "ok" {
+            CheckedExpressionData::ResultOk(Box::new(self.check_expression(value, Some(ok))?))
+        } else {
+            CheckedExpressionData::ResultErr(Box::new(self.check_expression(value, Some(err))?))
+        };
+        Ok(CheckedExpression::new(data, type_id))
+    }
+
+    /// Checks `Point(x: 1, y: 2)`, which makes a struct, with each field
+    /// passed as the labeled argument of the same name.
+    fn check_construction(
+        &mut self,
+        name: &str,
+        piped: Option<&Expression>,
+        arguments: &[FunctionArgument],
+        span: Span,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        let type_id = self.check_type_name(&Type::Ident(name.to_string()), self.cur_scope);
+        let Some((type_id, TypeKind::Struct { fields, .. })) =
+            type_id.map(|type_id| (type_id, self.types[type_id].kind.clone()))
+        else {
+            return Err(Error::variable_not_found(name, span));
+        };
+
+        let (labels, field_types): (ParameterLabels, Vec<_>) = fields
+            .into_iter()
+            .map(|(name, type_id)| (Some(name), type_id))
+            .unzip();
+        let fields = self.check_arguments(piped, arguments, &field_types, &labels, span)?;
+        self.typed_expression(
+            CheckedExpressionData::Construction { fields },
+            span,
+            type_id,
+            type_hint,
+        )
+    }
+
+    /// The index and type of the field of a struct, or element of a tuple,
+    /// called `field`.
+    fn find_field(&self, type_id: TypeId, field: &str) -> Option<(usize, TypeId)> {
+        match &self.types[type_id].kind {
+            TypeKind::Tuple(elements) => field
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| Some((index, *elements.get(index)?))),
+            TypeKind::Struct { fields, .. } => fields
+                .iter()
+                .position(|(name, _)| name == field)
+                .map(|index| (index, fields[index].1)),
+            _ => None,
+        }
+    }
+
+    /// The enum `expr` names, if it's the name of one rather than of a
+    /// variable, as in `Shape.Circle(radius: 1.0)`.
+    fn named_enum(&mut self, expr: &Expression) -> Option<TypeId> {
+        let ExpressionKind::Ident(name) = &expr.kind else {
+            return None;
+        };
+        if self.check_variable_name(name, self.cur_scope).is_some() {
+            return None;
+        }
+        let type_id = self.check_type_name(&Type::Ident(name.clone()), self.cur_scope)?;
+        matches!(self.types[type_id].kind, TypeKind::Enum { .. }).then_some(type_id)
+    }
+
+    /// The index and fields of the variant of `type_id` called `variant`.
+    fn find_variant(
+        &self,
+        type_id: TypeId,
+        variant: &str,
+        span: Span,
+    ) -> Result<(usize, VariantFields), Error> {
+        let TypeKind::Enum { variants, .. } = &self.types[type_id].kind else {
+            return Err(Error::variant_not_found(type_id, variant, span));
+        };
+        variants
+            .iter()
+            .position(|(name, _)| name == variant)
+            .map(|index| (index, variants[index].1.clone()))
+            .ok_or_else(|| Error::variant_not_found(type_id, variant, span))
+    }
+
+    /// Checks `Shape.Circle(radius: 1.0)` or `Shape.Empty`, which make a
+    /// variant of an enum, with its fields passed like arguments.
+    fn check_variant(
+        &mut self,
+        type_id: TypeId,
+        variant: &str,
+        piped: Option<&Expression>,
+        arguments: &[FunctionArgument],
+        span: Span,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        let (variant, fields) = self.find_variant(type_id, variant, span)?;
+        let (labels, field_types): (ParameterLabels, Vec<_>) = fields.into_iter().unzip();
+        let fields = self.check_arguments(piped, arguments, &field_types, &labels, span)?;
+        self.typed_expression(
+            CheckedExpressionData::Variant { variant, fields },
+            span,
+            type_id,
+            type_hint,
+        )
+    }
+
+    fn check_match_arm(
+        &mut self,
+        arm: &MatchArm,
+        value_type: TypeId,
+        arm_type: Option<TypeId>,
+    ) -> Result<CheckedMatchArm, Error> {
+        // bindings from the pattern are only visible inside the arm
+        let scope = self.create_scope(Some(self.cur_scope));
+        self.in_scope(scope, |checker| {
+            Ok(CheckedMatchArm {
+                pattern: checker.check_pattern(&arm.pattern, value_type)?,
+                body: checker.check_expression(&arm.body, arm_type)?,
+            })
+        })
+    }
+
+    fn check_pattern(
+        &mut self,
+        pattern: &Pattern,
+        type_id: TypeId,
+    ) -> Result<CheckedPattern, Error> {
+        let (checked_pattern, pattern_type) = match &pattern.kind {
+            PatternKind::Wildcard => return Ok(CheckedPattern::Wildcard),
+            PatternKind::Binding(name) => {
+                let variable_id = self.add_variable(name, type_id);
+                return Ok(CheckedPattern::Binding {
+                    name: name.to_string(),
+                    variable_id,
+                });
+            }
+            PatternKind::Unit => (CheckedPattern::Unit, UNIT_ID),
+            PatternKind::BoolLiteral(value) => (CheckedPattern::BoolLiteral(*value), BOOL_ID),
+            PatternKind::IntLiteral(value) => {
+                // int patterns match any int type the value fits in
+                let pattern_type = if is_int_type(type_id) {
+                    type_id
+                } else {
+                    INT_ID
+                };
+                let (min, max) = int_range(pattern_type).unwrap();
+                if !(min..=max).contains(value) {
+                    return Err(Error::int_literal_out_of_range(pattern_type, pattern.span));
+                }
+                (CheckedPattern::IntLiteral(*value), pattern_type)
+            }
+            PatternKind::Variant {
+                ty,
+                variant,
+                fields,
+            } => {
+                let ty = Type::Ident(ty.clone());
+                let Some(enum_type) = self.check_type_name(&ty, self.cur_scope) else {
+                    return Err(Error::type_not_found(ty, pattern.span));
+                };
+                let (variant, field_types) = self.find_variant(enum_type, variant, pattern.span)?;
+                if fields.len() != field_types.len() {
+                    return Err(Error::argument_count_mismatch(
+                        field_types.len(),
+                        fields.len(),
+                        pattern.span,
+                    ));
+                }
+                // fields are matched in the order they're declared in, and
+                // a label only says which one is meant
+                let fields = fields
+                    .iter()
+                    .zip(field_types)
+                    .map(|(field, (label, field_type))| {
+                        if let Some(given) = &field.label
+                            && field.label != label
+                        {
+                            return Err(Error::unknown_argument_label(given, field.pattern.span));
+                        }
+                        self.check_pattern(&field.pattern, field_type)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (CheckedPattern::Variant { variant, fields }, enum_type)
+            }
+            PatternKind::Tuple(elements) => {
+                // the elements of something that's already an error still
+                // bind their names, so uses of them aren't reported as well
+                let element_types = match &self.types[type_id].kind {
+                    TypeKind::Tuple(types) if types.len() == elements.len() => types.clone(),
+                    _ if type_id == ERROR_ID => vec![ERROR_ID; elements.len()],
+                    _ => {
+                        return Err(Error::tuple_pattern_mismatch(
+                            type_id,
+                            elements.len(),
+                            pattern.span,
+                        ));
+                    }
+                };
+                let elements = elements
+                    .iter()
+                    .zip(element_types)
+                    .map(|(element, element_type)| self.check_pattern(element, element_type))
+                    .collect::<Result<_, _>>()?;
+                return Ok(CheckedPattern::Tuple(elements));
+            }
+        };
+
+        if !types_match(type_id, pattern_type) {
+            return Err(Error::type_mismatch(type_id, pattern_type, pattern.span));
+        }
+        Ok(checked_pattern)
+    }
+
+    fn check_operator_method(
+        &mut self,
+        left: CheckedExpression,
+        operator: InfixOperator,
+        right: &Expression,
+        span: Span,
+    ) -> Result<CheckedExpression, Error> {
+        let method = operator_method_name(operator);
+        if self
+            .check_method_name(left.type_id, method, self.cur_scope)
+            .is_none()
+        {
+            return Err(Error::operator_not_implemented(
+                operator,
+                left.type_id,
+                span,
+            ));
+        }
+        let call = self.call_operator_method(left, method, right, span)?;
+        let return_type = call.type_id;
+
+        match operator {
+            Plus | Minus | Divide | Multiply | Power | ShiftLeft | ShiftRight => Ok(call),
+            Equal | LessThan | GreaterThan | LessThanOrEqual | GreaterThanOrEqual => {
+                if return_type != BOOL_ID {
+                    return Err(Error::type_mismatch(BOOL_ID, return_type, span));
+                }
+                Ok(call)
+            }
+            // `a != b` is `!a.eq(b)`
+            NotEqual => {
+                if return_type != BOOL_ID {
+                    return Err(Error::type_mismatch(BOOL_ID, return_type, span));
+                }
+                Ok(CheckedExpression::new(
+                    CheckedExpressionData::Prefix {
+                        operator: LogicalNot,
+                        expression: Box::new(call),
+                    },
+                    BOOL_ID,
+                ))
+            }
+            And | Or => unreachable!("logical operators only take bools"),
+            Compose => unreachable!("composition isn't resolved to a method"),
+            Pipe => unreachable!("pipelines are checked as calls"),
+            Coalesce => unreachable!("`??` only takes options"),
+        }
+    }
+
+    /// Applies the method an operator stands for, which has to take one
+    /// argument besides `self`, to its operands.
+    fn call_operator_method(
+        &mut self,
+        left: CheckedExpression,
+        method: &str,
+        right: &Expression,
+        span: Span,
+    ) -> Result<CheckedExpression, Error> {
+        let method_type = self
+            .check_method_name(left.type_id, method, self.cur_scope)
+            .map(|m| m.type_id)
+            .ok_or_else(|| Error::method_not_found(left.type_id, method, span))?;
+        let TypeKind::Function {
+            parameters,
+            return_type,
+        } = self.types[method_type].kind.clone()
+        else {
+            unreachable!("methods always have function types")
+        };
+
+        // `self` plus the right hand side
+        let [_, right_type] = parameters[..] else {
+            return Err(Error::argument_count_mismatch(
+                parameters.len() - 1,
+                1,
+                span,
+            ));
+        };
+        let right = self.check_expression(right, Some(right_type))?;
+
+        Ok(CheckedExpression::new(
+            CheckedExpressionData::MethodCall {
+                receiver: Box::new(left),
+                method: method.to_string(),
+                arguments: vec![CheckedFunctionArgument {
+                    label: None,
+                    value: right,
+                }],
+            },
+            return_type,
+        ))
+    }
+
+    /// The type the right operand of a comparison in a chain has to have, given
+    /// the type of the left one. Like a single comparison, it's builtin for ints
+    /// and resolved to a method returning `Bool` on anything else.
+    fn comparison_operand_type(
+        &self,
+        left_type: TypeId,
+        operator: InfixOperator,
+        span: Span,
+    ) -> Result<TypeId, Error> {
+        if is_number_type(left_type) {
+            return Ok(left_type);
+        }
+
+        let method = operator_method_name(operator);
+        let Some(method) = self.check_method_name(left_type, method, self.cur_scope) else {
+            return Err(Error::operator_not_implemented(operator, left_type, span));
+        };
+        let TypeKind::Function {
+            parameters,
+            return_type,
+        } = &self.types[method.type_id].kind
+        else {
+            unreachable!("methods always have function types")
+        };
+        let [_, right_type] = parameters[..] else {
+            return Err(Error::argument_count_mismatch(
+                parameters.len() - 1,
+                1,
+                span,
+            ));
+        };
+        if *return_type != BOOL_ID {
+            return Err(Error::type_mismatch(BOOL_ID, *return_type, span));
+        }
+        Ok(right_type)
+    }
+
+    /// `f ~> g` is the function that passes its argument to `f`, and what `f`
+    /// returns to `g`.
+    fn check_composition(
+        &mut self,
+        first: CheckedExpression,
+        second: &Expression,
+        span: Span,
+    ) -> Result<CheckedExpression, Error> {
+        let (input, intermediate) = self.unary_function_type(first.type_id, span)?;
+        let second = self.check_expression(second, None)?;
+        let (second_input, output) = self.unary_function_type(second.type_id, span)?;
+        if second_input != intermediate {
+            return Err(Error::type_mismatch(second_input, intermediate, span));
+        }
+
+        Ok(CheckedExpression::new(
+            CheckedExpressionData::Infix {
+                left: Box::new(first),
+                operator: Compose,
+                right: Box::new(second),
+            },
+            self.function_type(vec![input], output),
+        ))
+    }
+
+    /// The parameter and return type of a function type with one parameter.
+    fn unary_function_type(&self, type_id: TypeId, span: Span) -> Result<(TypeId, TypeId), Error> {
+        match &self.types[type_id].kind {
+            TypeKind::Function {
+                parameters,
+                return_type,
+            } => match parameters[..] {
+                [parameter] => Ok((parameter, *return_type)),
+                _ => Err(Error::argument_count_mismatch(parameters.len(), 1, span)),
+            },
+            _ => Err(Error::not_a_function(type_id, span)),
+        }
+    }
+
+    /// Checks an int literal, which is an `Int` unless it has a suffix or the
+    /// expected type is another int type.
+    fn check_int_literal(
+        &mut self,
+        literal: &Expression,
+        negated: bool,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        let (value, type_id) = match &literal.kind {
+            ExpressionKind::IntLiteral(value) => {
+                (*value as i128, int_hint(type_hint).unwrap_or(INT_ID))
+            }
+            ExpressionKind::SuffixedIntLiteral { value, suffix } => {
+                let ty = Type::Ident(suffix.clone());
+                let type_id = self
+                    .check_type_name(&ty, 0)
+                    .expect("the lexer only accepts sized int types as suffixes");
+                (*value as i128, type_id)
+            }
+            _ => unreachable!("not an int literal"),
+        };
+
+        let (min, max) = int_range(type_id).unwrap();
+        let signed_value = if negated { -value } else { value };
+        if !(min..=max).contains(&signed_value) {
+            return Err(Error::int_literal_out_of_range(type_id, literal.span));
+        }
+
+        self.typed_expression(
+            CheckedExpressionData::IntLiteral(value),
+            literal.span,
+            type_id,
+            type_hint,
+        )
+    }
+
+    /// Checks the arguments of a call against the parameters they're matched
+    /// to. Labeled arguments can be passed in any order, while unlabeled ones
+    /// fill the unlabeled parameters from left to right, starting with the
+    /// `piped` one from `piped |> f(arguments)`.
+    fn check_arguments(
+        &mut self,
+        piped: Option<&Expression>,
+        arguments: &[FunctionArgument],
+        parameters: &[TypeId],
+        labels: &ParameterLabels,
+        span: Span,
+    ) -> Result<Vec<CheckedFunctionArgument>, Error> {
+        reject_spread(arguments)?;
+        let arguments = piped
+            .map(|value| (None, value))
+            .into_iter()
+            .chain(arguments.iter().map(|a| (a.label.as_ref(), &a.value)))
+            .collect::<Vec<_>>();
+        if parameters.len() != arguments.len() {
+            return Err(Error::argument_count_mismatch(
+                parameters.len(),
+                arguments.len(),
+                span,
+            ));
+        }
+
+        let mut positional = (0..labels.len()).filter(|&i| labels[i].is_none());
+        // the argument passed for each parameter so far
+        let mut passed: Vec<Option<Span>> = vec![None; parameters.len()];
+        let mut checked_arguments = vec![];
+        for &(label, value) in &arguments {
+            let index = match label {
+                Some(label) => {
+                    let Some(index) = labels.iter().position(|l| l.as_ref() == Some(label)) else {
+                        return Err(Error::unknown_argument_label(label, value.span));
+                    };
+                    if let Some(first) = passed[index] {
+                        return Err(Error::duplicate_argument_label(label, first, value.span));
+                    }
+                    index
+                }
+                None => match positional.next() {
+                    Some(index) => index,
+                    // there are as many arguments as parameters, so a labeled
+                    // parameter must be missing its label
+                    None => {
+                        let missing = labels
+                            .iter()
+                            .flatten()
+                            .find(|&l| !arguments.iter().any(|&(label, _)| label == Some(l)));
+                        return Err(match missing {
+                            Some(missing) => Error::missing_argument_label(missing, value.span),
+                            // unless two parameters have the same label, which
+                            // only a program that wasn't parsed can have
+                            None => Error::argument_count_mismatch(
+                                labels.iter().filter(|l| l.is_none()).count(),
+                                arguments.iter().filter(|(l, _)| l.is_none()).count(),
+                                value.span,
+                            ),
+                        });
+                    }
+                },
+            };
+            passed[index] = Some(value.span);
+            checked_arguments.push(CheckedFunctionArgument {
+                label: label.cloned(),
+                value: self.check_expression(value, Some(parameters[index]))?,
+            });
+        }
+
+        Ok(checked_arguments)
+    }
+
+    /// The labels a variable bound to `value` has to be called with, as far as
+    /// they can be known.
+    fn parameter_labels(&self, value: &CheckedExpression) -> Option<ParameterLabels> {
+        match &value.data {
+            CheckedExpressionData::Function { parameters, .. } => Some(
+                parameters
+                    .iter()
+                    .map(|p| p.label().map(str::to_string))
+                    .collect(),
+            ),
+            CheckedExpressionData::Ident { variable_id, .. } => {
+                self.variables[*variable_id].parameter_labels.clone()
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolves the signature of a function expression in the current scope,
+    /// which should be the scope its body is checked in, so that its type
+    /// parameters are visible there.
+    ///
+    /// Unannotated parameter and return types are taken from `expected_type`
+    /// when it's a function type, e.g. for a function passed as an argument.
+    fn check_function_signature(
+        &mut self,
+        function: &Expression,
+        self_type: Option<TypeId>,
+        expected_type: Option<TypeId>,
+    ) -> Result<FunctionSignature, Error> {
+        let ExpressionKind::Function {
+            type_parameters,
+            parameters,
+            return_type,
+            ..
+        } = &function.kind
+        else {
+            unreachable!("not a function expression")
+        };
+        let span = function.span;
+        let (expected_params, expected_return) = match expected_type.map(|t| &self.types[t].kind) {
+            Some(TypeKind::Function {
+                parameters,
+                return_type,
+            }) => (parameters.clone(), Some(*return_type)),
+            _ => (vec![], None),
+        };
+
+        // type parameters are opaque types that only exist inside the function
+        let mut checked_type_params = vec![];
+        for type_param in type_parameters {
+            // there's nothing to bound a type by until there are traits
+            if let (Some((_, first)), Some((_, last))) =
+                (type_param.bounds.first(), type_param.bounds.last())
+            {
+                return Err(Error::unsupported(
+                    "trait bounds",
+                    Span::from_range(first.start, last.end),
+                ));
+            }
+            self.types
+                .push(ScopedType::named(&type_param.name, self.cur_scope));
+            checked_type_params.push(self.types.len() - 1);
+        }
+
+        // calls tell labeled parameters apart by their labels
+        let mut labels: Vec<(&str, Span)> = vec![];
+        for param in parameters {
+            if let FunctionParameter::LabeledAtCallsite {
+                internal_name,
+                external_name,
+                label_span,
+                ..
+            } = param
+            {
+                let label = external_name.as_deref().unwrap_or(internal_name);
+                if let Some(&(_, first)) = labels.iter().find(|(other, _)| *other == label) {
+                    return Err(Error::duplicate_parameter_label(label, first, *label_span));
+                }
+                labels.push((label, *label_span));
+            }
+        }
+
+        let mut checked_params = vec![];
+
+        for (index, param) in parameters.iter().enumerate() {
+            match param {
+                FunctionParameter::LabeledAtCallsite {
+                    internal_name,
+                    external_name,
+                    ty,
+                    ..
+                } => {
+                    let type_id = self.check_parameter_type(
+                        internal_name,
+                        ty.as_ref(),
+                        expected_params.get(index).copied(),
+                        span,
+                    )?;
+                    checked_params.push(CheckedFunctionParameter::LabeledAtCallsite {
+                        internal_name: internal_name.clone(),
+                        external_name: external_name.clone(),
+                        type_id,
+                    });
+                }
+                FunctionParameter::UnlabeledAtCallsite { name, ty } => {
+                    let type_id = self.check_parameter_type(
+                        name,
+                        ty.as_ref(),
+                        expected_params.get(index).copied(),
+                        span,
+                    )?;
+                    checked_params.push(CheckedFunctionParameter::UnlabeledAtCallsite {
+                        name: name.clone(),
+                        type_id,
+                    });
+                }
+                FunctionParameter::Pattern { pattern, ty } => {
+                    if !is_irrefutable(pattern) {
+                        return Err(Error::refutable_parameter_pattern(pattern.span));
+                    }
+                    let type_id = self.check_parameter_type(
+                        &pattern.to_string(),
+                        ty.as_ref(),
+                        expected_params.get(index).copied(),
+                        span,
+                    )?;
+                    // the function's scope is current, so the bindings are
+                    // visible in its body
+                    let pattern = self.check_pattern(pattern, type_id)?;
+                    checked_params.push(CheckedFunctionParameter::Pattern { pattern, type_id });
+                }
+                // there's nothing to collect the arguments into until there
+                // are arrays
+                FunctionParameter::Rest { .. } => {
+                    return Err(Error::unsupported("rest parameters", span));
+                }
+                FunctionParameter::SelfParameter => match self_type {
+                    // `self` is only allowed as the first parameter of a method
+                    Some(type_id) if index == 0 => {
+                        checked_params.push(CheckedFunctionParameter::SelfParameter { type_id });
+                    }
+                    _ => return Err(Error::unexpected_self_parameter(span)),
+                },
+            }
+        }
+
+        // get return type, which is inferred from the body if there's nothing
+        // else to go by, except for methods, whose signatures are needed
+        // before their bodies are checked
+        let infer_return_type =
+            return_type.is_none() && expected_return.is_none() && self_type.is_none();
+        let return_type_id = match (return_type, expected_return) {
+            (Some(ty), _) => self
+                .check_type_name(ty, self.cur_scope)
+                .ok_or_else(|| Error::type_not_found(ty.clone(), span))?,
+            (None, Some(type_id)) => type_id,
+            (None, None) => UNIT_ID,
+        };
+
+        let type_id = self.function_type(
+            checked_params.iter().map(|p| p.type_id()).collect(),
+            return_type_id,
+        );
+
+        Ok(FunctionSignature {
+            type_parameters: checked_type_params,
+            parameters: checked_params,
+            return_type: return_type_id,
+            infer_return_type,
+            type_id,
+        })
+    }
+
+    fn check_parameter_type(
+        &mut self,
+        name: &str,
+        ty: Option<&Type>,
+        expected_type: Option<TypeId>,
+        span: Span,
+    ) -> Result<TypeId, Error> {
+        match (ty, expected_type) {
+            (Some(ty), _) => self
+                .check_type_name(ty, self.cur_scope)
+                .ok_or_else(|| Error::type_not_found(ty.clone(), span)),
+            (None, Some(type_id)) => Ok(type_id),
+            (None, None) => Err(Error::cannot_infer_parameter_type(name, span)),
+        }
+    }
+
+    fn check_function(
+        &mut self,
+        function: &Expression,
+        self_type: Option<TypeId>,
+        expected_type: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        // create a new scope
+        let scope = self.create_scope(Some(self.cur_scope));
+        let signature = self.in_scope(scope, |checker| {
+            checker.check_function_signature(function, self_type, expected_type)
+        })?;
+        self.check_function_body(function, scope, signature)
+    }
+
+    fn check_function_body(
+        &mut self,
+        function: &Expression,
+        scope: ScopeId,
+        signature: FunctionSignature,
+    ) -> Result<CheckedExpression, Error> {
+        let ExpressionKind::Function { body, .. } = &function.kind else {
+            unreachable!("not a function expression")
+        };
+        let FunctionSignature {
+            type_parameters,
+            parameters,
+            mut return_type,
+            infer_return_type,
+            mut type_id,
+        } = signature;
+
+        // loops outside of the function can't be broken out of from inside it
+        let outer_loops = std::mem::take(&mut self.loops);
+        let outer_return_type = std::mem::replace(
+            &mut self.return_type,
+            (!infer_return_type).then_some(return_type),
+        );
+        let outer_infer_return_type =
+            std::mem::replace(&mut self.infer_return_type, infer_return_type);
+        self.function_depth += 1;
+        let checked_expr_stmts = self.in_scope(scope, |checker| {
+            // add params as local variables in said scope, except for patterns,
+            // which were bound along with the signature
+            for param in &parameters {
+                if let Some(name) = param.internal_name() {
+                    checker.add_variable(name, param.type_id());
+                }
+            }
+
+            // check the body, with the return type expected of its value
+            // unless the last statement returns on its own
+            let type_hint = match body.last() {
+                Some(last) if matches!(last.expr.kind, ExpressionKind::Return(_)) => None,
+                _ => (!infer_return_type).then_some(return_type),
+            };
+            checker.check_block(body, type_hint)
+        });
+        self.function_depth -= 1;
+        self.loops = outer_loops;
+        let returned_type = std::mem::replace(&mut self.return_type, outer_return_type);
+        self.infer_return_type = outer_infer_return_type;
+
+        if infer_return_type {
+            return_type = match (returned_type, body.last(), checked_expr_stmts.last()) {
+                (Some(type_id), _, _) => type_id,
+                (None, Some(last_stmt), Some(checked)) if !last_stmt.discarded => {
+                    checked.expr.type_id
+                }
+                _ => UNIT_ID,
+            };
+            type_id = self.function_type(
+                parameters.iter().map(|p| p.type_id()).collect(),
+                return_type,
+            );
+        }
+
+        // the last statement is the return value, unless there isn't one or
+        // it's discarded, in which case the function returns `unit`, or it
+        // returns on its own
+        match body.last() {
+            Some(last_stmt) if matches!(last_stmt.expr.kind, ExpressionKind::Return(_)) => {}
+            Some(last_stmt) if last_stmt.discarded && return_type != UNIT_ID => {
+                return Err(Error::discarded_return_value(
+                    return_type,
+                    last_stmt.expr.span,
+                ));
+            }
+            Some(last_stmt) if last_stmt.discarded => {}
+            Some(_) => {
+                let value_type = checked_expr_stmts.last().unwrap().expr.type_id;
+                if !types_match(return_type, value_type) {
+                    return Err(Error::type_mismatch(return_type, value_type, function.span));
+                }
+            }
+            None if return_type != UNIT_ID => {
+                return Err(Error::type_mismatch(return_type, UNIT_ID, function.span));
+            }
+            None => {}
+        }
+
+        Ok(CheckedExpression::new(
+            CheckedExpressionData::Function {
+                type_parameters,
+                parameters,
+                return_type,
+                body: checked_expr_stmts,
+            },
+            type_id,
+        ))
+    }
+
+    fn in_scope<T>(&mut self, scope_id: ScopeId, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outer_scope = self.cur_scope;
+        self.cur_scope = scope_id;
+        let res = f(self);
+        self.cur_scope = outer_scope;
+        res
+    }
+
+    fn typed_expression(
+        &self,
+        res: CheckedExpressionData,
+        span: Span,
+        res_type: TypeId,
+        type_hint: Option<TypeId>,
+    ) -> Result<CheckedExpression, Error> {
+        if let Some(type_hint) = type_hint {
+            if types_match(type_hint, res_type) {
+                Ok(CheckedExpression::new(res, res_type))
+            } else {
+                Err(Error::type_mismatch(type_hint, res_type, span))
+            }
+        } else {
+            Ok(CheckedExpression::new(res, res_type))
+        }
+    }
+}
+
+/// Whether a value of type `got` can be used where `expected` is, which it
+/// always can if either of them is the error type.
+fn types_match(expected: TypeId, got: TypeId) -> bool {
+    expected == got || expected == ERROR_ID || got == ERROR_ID
+}
+
+/// Whether a pattern matches every value of the type it's checked against,
+/// which is what a parameter's has to.
+fn is_irrefutable(pattern: &Pattern) -> bool {
+    match &pattern.kind {
+        PatternKind::Wildcard | PatternKind::Binding(_) | PatternKind::Unit => true,
+        PatternKind::BoolLiteral(_) | PatternKind::IntLiteral(_) | PatternKind::Variant { .. } => {
+            false
+        }
+        PatternKind::Tuple(elements) => elements.iter().all(is_irrefutable),
+    }
+}
+
+fn is_builtin_type(type_id: TypeId) -> bool {
+    type_id < BUILTIN_TYPES.len() || is_int_type(type_id)
+}
+
+fn is_int_type(type_id: TypeId) -> bool {
+    int_range(type_id).is_some()
+}
+
+fn is_number_type(type_id: TypeId) -> bool {
+    is_int_type(type_id) || type_id == FLOAT_ID
+}
+
+/// The smallest and largest value of an int type.
+fn int_range(type_id: TypeId) -> Option<(i128, i128)> {
+    if type_id == INT_ID {
+        return Some((isize::MIN as i128, isize::MAX as i128));
+    }
+    let (_, min, max) = SIZED_INT_TYPES.get(type_id.checked_sub(FIRST_SIZED_INT_ID)?)?;
+    Some((*min, *max))
+}
+
+/// The int type a builtin conversion method like `x.to_u8()` or `x.to_int()`
+/// on an int converts to.
+fn int_conversion(type_id: TypeId, method: &str) -> Option<TypeId> {
+    if !is_int_type(type_id) {
+        return None;
+    }
+    match method.strip_prefix("to_")? {
+        "int" => Some(INT_ID),
+        name => SIZED_INT_TYPES
+            .iter()
+            .position(|(type_name, _, _)| *type_name == name)
+            .map(|index| FIRST_SIZED_INT_ID + index),
+    }
+}
+
+/// The expected type, if it's an int type an int literal can take on.
+fn int_hint(type_hint: Option<TypeId>) -> Option<TypeId> {
+    type_hint.filter(|type_id| is_int_type(*type_id))
+}
+
+/// The variable an assignment to `target` changes, which is the variable
+/// itself or the one whose field it is.
+fn assigned_variable(target: &CheckedExpression) -> Option<VariableId> {
+    match &target.data {
+        CheckedExpressionData::Ident { variable_id, .. } => Some(*variable_id),
+        CheckedExpressionData::Field { target, .. } => assigned_variable(target),
+        _ => None,
+    }
+}
+
+/// Spreading an argument needs arrays or tuples that calls can take apart,
+/// which they can't yet.
+fn reject_spread(arguments: &[FunctionArgument]) -> Result<(), Error> {
+    match arguments.iter().find(|argument| argument.spread) {
+        Some(argument) => Err(Error::unsupported(
+            "spreading arguments",
+            argument.value.span,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The one argument of a builtin like `some(value)`, which can be piped in.
+fn single_argument<'a>(
+    piped: Option<&'a Expression>,
+    arguments: &'a [FunctionArgument],
+    span: Span,
+) -> Result<&'a Expression, Error> {
+    reject_spread(arguments)?;
+    match (piped, arguments) {
+        (Some(value), []) => Ok(value),
+        (None, [argument]) => match &argument.label {
+            Some(label) => Err(Error::unknown_argument_label(label, argument.value.span)),
+            None => Ok(&argument.value),
+        },
+        _ => {
+            let got = arguments.len() + usize::from(piped.is_some());
+            Err(Error::argument_count_mismatch(1, got, span))
+        }
+    }
+}
+
+/// The name of the method an infix operator resolves to when its left operand
+/// isn't a builtin type, e.g. `a + b` is checked as `a.add(b)`.
+fn operator_method_name(operator: InfixOperator) -> &'static str {
+    match operator {
+        Plus => "add",
+        Minus => "subtract",
+        Multiply => "multiply",
+        Divide => "divide",
+        Power => "power",
+        Equal | NotEqual => "eq",
+        LessThan => "less_than",
+        GreaterThan => "greater_than",
+        LessThanOrEqual => "less_than_or_equal",
+        GreaterThanOrEqual => "greater_than_or_equal",
+        ShiftLeft => "shift_left",
+        ShiftRight => "shift_right",
+        And | Or => unreachable!("logical operators only take bools"),
+        Compose => unreachable!("composition isn't resolved to a method"),
+        Pipe => unreachable!("pipelines are checked as calls"),
+        Coalesce => unreachable!("`??` only takes options"),
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct CheckedProgram {
+    stmts: Vec<CheckedExpressionStatement>,
+}
+impl CheckedProgram {
+    /// The type of the program's value, which is that of its last statement
+    /// unless there isn't one or it's discarded.
+    pub fn type_id(&self) -> TypeId {
+        match self.stmts.last() {
+            Some(last) if !last.discarded => last.expr.type_id,
+            _ => UNIT_ID,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct CheckedExpressionStatement {
+    expr: CheckedExpression,
+    discarded: bool,
+}
+
+#[derive(PartialEq, Debug)]
+struct CheckedExpression {
+    type_id: TypeId,
+    data: CheckedExpressionData,
+}
+impl CheckedExpression {
+    pub fn new(data: CheckedExpressionData, type_id: TypeId) -> Self {
+        Self { type_id, data }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum CheckedExpressionData {
+    Unit,
+    BoolLiteral(bool),
+    IntLiteral(i128),
+    FloatLiteral(f64),
+    StringLiteral(String),
+    CharLiteral(char),
+    Ident {
+        name: String,
+        variable_id: VariableId,
+    },
+
+    Prefix {
+        operator: PrefixOperator,
+        expression: Box<CheckedExpression>,
+    },
+    Infix {
+        left: Box<CheckedExpression>,
+        operator: InfixOperator,
+        right: Box<CheckedExpression>,
+    },
+    Tuple(Vec<CheckedExpression>),
+    /// A struct made from its fields, in the order they're passed in.
+    Construction {
+        fields: Vec<CheckedFunctionArgument>,
+    },
+    /// The variant of an enum at `variant`, made from its fields in the order
+    /// they're passed in.
+    Variant {
+        variant: usize,
+        fields: Vec<CheckedFunctionArgument>,
+    },
+    /// The field of a struct or the element of a tuple at `index`.
+    Field {
+        target: Box<CheckedExpression>,
+        index: usize,
+    },
+    /// The field at `index` of what an option holds, if it holds anything.
+    SafeField {
+        target: Box<CheckedExpression>,
+        index: usize,
+    },
+    OptionSome(Box<CheckedExpression>),
+    OptionNone,
+    ResultOk(Box<CheckedExpression>),
+    ResultErr(Box<CheckedExpression>),
+    /// Returns the error from the function, if the result is one.
+    Try(Box<CheckedExpression>),
+
+    /// Each operator is builtin or resolved to a method the same way as in a
+    /// single comparison, depending on the type of the operand to its left.
+    ChainedComparison {
+        operands: Vec<CheckedExpression>,
+        operators: Vec<InfixOperator>,
+    },
+
+    VariableDecl {
+        name: String,
+        value: Box<CheckedExpression>,
+        mutable: bool,
+        variable_id: VariableId,
+    },
+    Block(Vec<CheckedExpressionStatement>),
+    Assign {
+        target: Box<CheckedExpression>,
+        value: Box<CheckedExpression>,
+    },
+
+    Function {
+        type_parameters: Vec<TypeId>,
+        parameters: Vec<CheckedFunctionParameter>,
+        return_type: TypeId,
+        body: Vec<CheckedExpressionStatement>,
+    },
+
+    FunctionCall {
+        name: String,
+        variable_id: VariableId,
+        arguments: Vec<CheckedFunctionArgument>,
+    },
+    MethodCall {
+        receiver: Box<CheckedExpression>,
+        method: String,
+        arguments: Vec<CheckedFunctionArgument>,
+    },
+    Call {
+        callee: Box<CheckedExpression>,
+        arguments: Vec<CheckedFunctionArgument>,
+    },
+
+    Impl {
+        type_id: TypeId,
+        methods: Vec<CheckedMethod>,
+    },
+    Struct {
+        type_id: TypeId,
+    },
+    Enum {
+        type_id: TypeId,
+    },
+    OperatorDecl(String),
+
+    Match {
+        value: Box<CheckedExpression>,
+        arms: Vec<CheckedMatchArm>,
+    },
+
+    While {
+        label: Option<String>,
+        condition: Box<CheckedExpression>,
+        body: Vec<CheckedExpressionStatement>,
+    },
+    Loop {
+        label: Option<String>,
+        body: Vec<CheckedExpressionStatement>,
+    },
+    Break {
+        label: Option<String>,
+        value: Option<Box<CheckedExpression>>,
+    },
+    Continue {
+        label: Option<String>,
+    },
+    Return(Option<Box<CheckedExpression>>),
+
+    /// A statement with a type error, which has been reported.
+    Error,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum CheckedFunctionParameter {
+    LabeledAtCallsite {
+        internal_name: String,
+        external_name: Option<String>,
+        type_id: TypeId,
+    },
+    UnlabeledAtCallsite {
+        name: String,
+        type_id: TypeId,
+    },
+    SelfParameter {
+        type_id: TypeId,
+    },
+    /// Unlabeled at the callsite, like a parameter with `~`.
+    Pattern {
+        pattern: CheckedPattern,
+        type_id: TypeId,
+    },
+}
+impl CheckedFunctionParameter {
+    fn internal_name(&self) -> Option<&str> {
+        match self {
+            CheckedFunctionParameter::LabeledAtCallsite { internal_name, .. } => {
+                Some(internal_name)
+            }
+            CheckedFunctionParameter::UnlabeledAtCallsite { name, .. } => Some(name),
+            CheckedFunctionParameter::SelfParameter { .. } => Some("self"),
+            CheckedFunctionParameter::Pattern { .. } => None,
+        }
+    }
+
+    /// The label the argument for this parameter has to be passed with.
+    fn label(&self) -> Option<&str> {
+        match self {
+            CheckedFunctionParameter::LabeledAtCallsite {
+                internal_name,
+                external_name,
+                ..
+            } => Some(external_name.as_deref().unwrap_or(internal_name)),
+            CheckedFunctionParameter::UnlabeledAtCallsite { .. }
+            | CheckedFunctionParameter::SelfParameter { .. }
+            | CheckedFunctionParameter::Pattern { .. } => None,
+        }
+    }
+
+    fn type_id(&self) -> TypeId {
+        match self {
+            CheckedFunctionParameter::LabeledAtCallsite { type_id, .. }
+            | CheckedFunctionParameter::UnlabeledAtCallsite { type_id, .. }
+            | CheckedFunctionParameter::SelfParameter { type_id }
+            | CheckedFunctionParameter::Pattern { type_id, .. } => *type_id,
+        }
+    }
+}
+
+struct FunctionSignature {
+    type_parameters: Vec<TypeId>,
+    parameters: Vec<CheckedFunctionParameter>,
+    return_type: TypeId,
+    /// Whether `return_type` is only a placeholder until the body is checked.
+    infer_return_type: bool,
+    type_id: TypeId,
+}
+
+/// A function declared ahead of the block it's in, whose body is checked
+/// once its declaration is reached.
+struct HoistedFunction {
+    scope: ScopeId,
+    signature: FunctionSignature,
+    variable_id: VariableId,
+}
+
+#[derive(PartialEq, Debug)]
+struct CheckedFunctionArgument {
+    label: Option<String>,
+    value: CheckedExpression,
+}
+
+#[derive(PartialEq, Debug)]
+struct CheckedMethod {
+    name: String,
+    function: CheckedExpression,
+}
+
+#[derive(PartialEq, Debug)]
+struct CheckedMatchArm {
+    pattern: CheckedPattern,
+    body: CheckedExpression,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum CheckedPattern {
+    Wildcard,
+    Binding {
+        name: String,
+        variable_id: VariableId,
+    },
+    Unit,
+    BoolLiteral(bool),
+    IntLiteral(i128),
+    /// The variant of an enum at `variant`, with a pattern for each of its
+    /// fields in the order they're declared in.
+    Variant {
+        variant: usize,
+        fields: Vec<CheckedPattern>,
+    },
+    Tuple(Vec<CheckedPattern>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn check(input: &str) -> Result<Vec<CheckedExpressionStatement>, Error> {
+        let program = Parser::new(input).parse().unwrap();
+        Checker::new()
+            .try_check_program(&program)
+            .map(|checked| checked.stmts)
+    }
+
+    #[test]
+    fn int_operator_type_mismatch() {
+        let err = check("3 + true").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn return_expressions() {
+        assert!(check("f :: fun(x: Int) Int { return x; }; g :: fun() { return }").is_ok());
+
+        let err = check("f :: fun() Int { return true }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+        let err = check("f :: fun() Int { return; 1 }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: UNIT_ID
+            }
+        ));
+        let err = check("return 1").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::ReturnOutsideFunction));
+    }
+
+    #[test]
+    fn recursive_functions() {
+        assert!(
+            check("fact :: fun(~n: Int) Int { match n { 0 => 1, _ => n * fact(n - 1) } }").is_ok()
+        );
+        // siblings can call each other
+        assert!(
+            check(
+                "is_even :: fun(n: Int) Bool { match n { 0 => true, _ => is_odd(n: n - 1) } };
+                 is_odd :: fun(n: Int) Bool { match n { 0 => false, _ => is_even(n: n - 1) } };
+                 b :: is_even(n: 3);"
+            )
+            .is_ok()
+        );
+        // but a function can't be used outside of a function body before it's
+        // declared, since it isn't bound yet, nor can one that would use it
+        for (input, name, span) in [
+            ("x :: b(); b :: fun() Int { 1 }", "b", 5..8),
+            ("g :: { f }; f :: fun() Int { 1 }", "f", 7..8),
+            (
+                "y :: f(); f :: fun() Int { g() }; g :: fun() Int { y }",
+                "f",
+                5..8,
+            ),
+            (
+                "h :: fun() Int { x :: b(); b :: fun() Int { 1 }; x }",
+                "b",
+                22..25,
+            ),
+        ] {
+            let err = check(input).unwrap_err();
+            assert!(
+                matches!(&err.kind, ErrorKind::UsedBeforeDeclaration { name: n, .. } if n == name),
+                "{input}"
+            );
+            assert_eq!(err.span, Span::from_range(span.start, span.end), "{input}");
+        }
+        let err = check("x :: b(); b :: fun() Int { 1 }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::UsedBeforeDeclaration { declaration, .. }
+                if declaration == Span::from_range(10, 30)
+        ));
+        // a later declaration with the same name is the one that's used
+        let checked = check("x :: 1; x :: true; x").unwrap();
+        assert_eq!(checked[2].expr.type_id, BOOL_ID);
+        // even with a type from earlier in the block in the signature
+        assert!(
+            check(
+                "enum List { Nil, Cons(Int, List) };
+                 len :: fun(~l: List) Int { match l { List.Nil => 0, List.Cons(_, rest) => 1 + len(rest) } }"
+            )
+            .is_ok()
+        );
+
+        // not without a return type, since the body would need it, nor for
+        // mutable variables, which could be assigned something else
+        for input in [
+            "f :: fun() { f() }",
+            "f := fun() Int { f() }",
+            "x :: fun() Int { f() }; f :: fun() { 1 }",
+        ] {
+            let err = check(input).unwrap_err();
+            assert!(
+                matches!(err.kind, ErrorKind::VariableNotFound { ref name } if name == "f"),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn messages() {
+        let messages = |input: &str| {
+            let program = Parser::new(input).parse().unwrap();
+            let mut checker = Checker::new();
+            checker.check_program_recovering(&program);
+            let errors = checker
+                .errors()
+                .iter()
+                .map(|err| err.display(&checker).to_string());
+            let warnings = checker
+                .warnings()
+                .iter()
+                .map(|w| w.display(&checker).to_string());
+            errors.chain(warnings).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages("f :: fun(~x: Int) Option((Int, Bool)) { none }; b : Bool : f"),
+            ["error at 59..60: expected Bool, got fun(Int) Option((Int, Bool))"]
+        );
+        assert_eq!(
+            messages("x :: 1; x = 2; (1,);"),
+            [
+                "error at 8..13: `x` can't be assigned to, since it's declared with `::` at 0..6",
+                "warning at 15..19: the value of type (Int,) isn't used",
+            ]
+        );
+    }
+
+    #[test]
+    fn inferred_return_types() {
+        // from the body, or the first `return`
+        assert!(check("inc :: |x: Int| x + 1; inc(2) * 3").is_ok());
+        assert!(check("f :: fun(~x: Int) { return x > 0; }; b :: f(1); !b").is_ok());
+        assert!(check("f :: fun() { 1; }; u : Unit = f()").is_ok());
+
+        let err = check("f :: fun(~x: Int) { return 1; true }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+
+        // methods have to say what they return, since they can be called
+        // before their bodies are checked
+        let err = check("impl Int { one :: fun(self) { 1 } }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: UNIT_ID,
+                got: INT_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn pattern_parameters() {
+        assert!(check("f :: fun((a, b): (Int, Bool)) Bool { a > 0 && b }; f((1, true))").is_ok());
+        assert!(check("sum :: |(a, _), b: Int| a + b").is_err());
+        assert!(check("sum :: |(a, _): (Int, Unit), b: Int| a + b; sum((1, unit), 2)").is_ok());
+
+        let err = check("f :: fun((a, 1): (Int, Int)) {}").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::RefutableParameterPattern));
+        assert_eq!(err.span, Span::from_range(9, 15));
+
+        let err = check("f :: fun((a, b, c): (Int, Int)) {}").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TuplePatternMismatch { elements: 3, .. }
+        ));
+
+        let err = check("f :: fun((a, b)) {}").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::CannotInferParameterType { name } if name == "(a, b)"
+        ));
+    }
+
+    #[test]
+    fn blocks() {
+        let checked = check("x :: { y :: 1; y + 1 }; z :: { x; }").unwrap();
+        let [x, z] = &checked[..] else {
+            panic!("expected two statements");
+        };
+        let (
+            CheckedExpressionData::VariableDecl { value: x, .. },
+            CheckedExpressionData::VariableDecl { value: z, .. },
+        ) = (&x.expr.data, &z.expr.data)
+        else {
+            panic!("expected variable declarations");
+        };
+        assert_eq!((x.type_id, z.type_id), (INT_ID, UNIT_ID));
+
+        // and so is a program, which is what `lovely -e` prints the type of
+        let type_of = |input: &str| {
+            let program = Parser::new(input).parse().unwrap();
+            Checker::new().check_program(&program).type_id()
+        };
+        assert_eq!(type_of("x :: 1; x > 0"), BOOL_ID);
+        assert_eq!(type_of("x :: 1; x > 0;"), UNIT_ID);
+        assert_eq!(type_of(""), UNIT_ID);
+
+        // variables declared in a block aren't visible after it
+        let err = check("{ y :: 1; }; y").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::VariableNotFound { name } if name == "y"));
+    }
+
+    #[test]
+    fn assignments() {
+        assert!(check("x := 1; x = 2; struct P { y: Int }; p := P(y: 1); p.y = x").is_ok());
+
+        let err = check("x := 1; x = true").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+        for input in [
+            "x :: 1; x = 2",
+            "struct P { y: Int }; p :: P(y: 1); p.y = 2",
+            "f :: fun(n: Int) { n = 1 }",
+        ] {
+            let err = check(input).unwrap_err();
+            assert!(matches!(err.kind, ErrorKind::AssignToImmutable { .. }));
+        }
+        // a `::` declaration is pointed at, with the `:` to make it `:=`
+        let err = check("x : Int : 1; x = 2").unwrap_err();
+        let ErrorKind::AssignToImmutable {
+            declaration,
+            suggestion,
+            ..
+        } = err.kind
+        else {
+            panic!("expected an assignment to an immutable variable");
+        };
+        assert_eq!(declaration, Some(Span::from_range(0, 11)));
+        assert_eq!(
+            suggestion,
+            Some(Suggestion {
+                span: Span::from_range(8, 9),
+                replacement: "=".to_string(),
+            })
+        );
+        let err = check("f :: fun() Int { 1 }; f = f").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::AssignToImmutable {
+                declaration: Some(_),
+                suggestion: Some(_),
+                ..
+            }
+        ));
+        let err = check("f :: fun(n: Int) { n = 1 }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::AssignToImmutable {
+                declaration: None,
+                suggestion: None,
+                ..
+            }
+        ));
+        let err = check("enum E { A }; E.A = E.A").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::InvalidAssignmentTarget));
+    }
+
+    #[test]
+    fn logical_operators_take_bools() {
+        assert!(check("x :: 1 < 2 && true || !false").is_ok());
+        for input in ["1 && true", "true || 1"] {
+            let err = check(input).unwrap_err();
+            assert!(matches!(
+                err.kind,
+                ErrorKind::TypeMismatch {
+                    expected: BOOL_ID,
+                    got: INT_ID
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn operator_without_method() {
+        let err = check("true < false").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::OperatorNotImplemented {
+                operator: LessThan,
+                type_id: BOOL_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn prelude() {
+        let prelude = Prelude::default()
+            .with_type("Color")
+            .with_function(
+                "rgb",
+                &[(Some("r"), "Int"), (Some("g"), "Int"), (Some("b"), "Int")],
+                "Color",
+            )
+            .with_function("fill", &[(None, "Color")], "Unit");
+        let check = |input: &str| {
+            let program = Parser::new(input).parse().unwrap();
+            Checker::with_prelude(&prelude)
+                .unwrap()
+                .try_check_program(&program)
+        };
+
+        assert!(check("c : Color = rgb(r: 1, g: 2, b: 3); fill(c)").is_ok());
+        assert!(matches!(
+            check("fill(rgb(1, 2, 3))").unwrap_err().kind,
+            ErrorKind::MissingArgumentLabel { label } if label == "r"
+        ));
+        assert!(matches!(
+            check("fill(1)").unwrap_err().kind,
+            ErrorKind::TypeMismatch { got: INT_ID, .. }
+        ));
+
+        // without the prelude none of it is in scope
+        let program = Parser::new("fill(unit)").parse().unwrap();
+        assert!(matches!(
+            Checker::new().try_check_program(&program).unwrap_err().kind,
+            ErrorKind::VariableNotFound { name } if name == "fill"
+        ));
+
+        let err = Checker::with_prelude(&Prelude::default().with_function("f", &[], "Color"))
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind, ErrorKind::TypeNotFound { .. }));
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        let program = Parser::new(
+            "x :: true + 1; y :: x.foo(); f :: fun(n: Int) Int { n + false }; f(n: 1) * 2",
+        )
+        .parse()
+        .unwrap();
+        let mut checker = Checker::new();
+        let checked = checker.check_program_recovering(&program);
+        assert!(matches!(
+            checker.errors(),
+            [
+                Error {
+                    kind: ErrorKind::OperatorNotImplemented { .. },
+                    ..
+                },
+                Error {
+                    kind: ErrorKind::TypeMismatch {
+                        expected: INT_ID,
+                        got: BOOL_ID
+                    },
+                    ..
+                },
+            ]
+        ));
+        // the function still has its type, so calling it checks
+        let [.., call] = &checked.stmts[..] else {
+            unreachable!()
+        };
+        assert_eq!(call.expr.type_id, INT_ID);
+    }
+
+    #[test]
+    fn discarded_statements() {
+        let err = check("f :: fun() Int { 1; };").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::DiscardedReturnValue { expected: INT_ID }
+        ));
+        assert_eq!((err.span.start, err.span.end), (17, 18));
+
+        let err = check("f :: fun() Int {};").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: UNIT_ID
+            }
+        ));
+        assert!(check("f :: fun() {}; g :: fun() { 1 == 2; };").is_ok());
+
+        // throwing away a value that isn't `unit` is allowed, but warned about
+        let program = Parser::new("x :: 1; x + 1; loop { x; break; }")
+            .parse()
+            .unwrap();
+        let mut checker = Checker::new();
+        checker.check_program(&program);
+        let warnings = checker
+            .warnings()
+            .iter()
+            .map(|warning| (warning.span.start, &warning.kind))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            warnings[..],
+            [
+                (8, WarningKind::UnusedValue { type_id: INT_ID }),
+                (22, WarningKind::UnusedValue { type_id: INT_ID })
+            ]
+        ));
+    }
+
+    #[test]
+    fn custom_operator_without_method() {
+        for input in [
+            "operator <> (precedence: 2); 1 <> 2",
+            "operator <> (precedence: 2, method: differs); 1 <> 2",
+        ] {
+            let err = check(input).unwrap_err();
+            assert!(matches!(
+                err.kind,
+                ErrorKind::CustomOperatorNotImplemented {
+                    ref operator,
+                    type_id: INT_ID
+                } if operator == "<>"
+            ));
+        }
+    }
+
+    #[test]
+    fn comparison_method_must_return_bool() {
+        let input = "
+impl Bool {
+  less_than :: fun(self, ~other: Bool) Int { 0 }
+}
+true < false";
+        let err = check(input).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: BOOL_ID,
+                got: INT_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn type_parameters_are_opaque() {
+        let err = check("fun[T](~x: T) Int { x }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn infer_parameter_types_from_expected_type() {
+        let mut checker = Checker::new();
+        let expected = checker.function_type(vec![INT_ID, BOOL_ID], INT_ID);
+
+        let Program(stmts) = Parser::new("fun(x, ~flag) { x }").parse().unwrap();
+        let res = checker
+            .check_expression(&stmts[0].expr, Some(expected))
+            .unwrap();
+        assert_eq!(res.type_id, expected);
+
+        let err = checker.check_expression(&stmts[0].expr, None).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::CannotInferParameterType { name } if name == "x"
+        ));
+    }
+
+    #[test]
+    fn break_outside_loop() {
+        let err = check("break").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::BreakOutsideLoop));
+
+        // functions can't break out of loops they're defined in
+        let err = check("while true { fun() Unit { break; unit } }").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::BreakOutsideLoop));
+
+        let err = check("continue").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::ContinueOutsideLoop));
+    }
+
+    #[test]
+    fn break_value_in_while() {
+        let err = check("while true { break 3 }").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::UnexpectedBreakValue));
+    }
+
+    #[test]
+    fn loop_break_types() {
+        let err = check("loop { break 3; break true }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+
+        let err = check("loop { break 3; break }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: UNIT_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn argument_labels() {
+        let f = "f :: fun(~x: Int, y: Int, by z: Int) Int { x };";
+        assert!(check(&format!("{f} f(1, by: 2, y: 3)")).is_ok());
+
+        let err = check(&format!("{f} f(1, 2, by: 3)")).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::MissingArgumentLabel { label } if label == "y"));
+
+        let err = check(&format!("{f} f(1, y: 2, z: 3)")).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::UnknownArgumentLabel { label } if label == "z"));
+
+        let err = check(&format!("{f} f(x: 1, y: 2, by: 3)")).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::UnknownArgumentLabel { label } if label == "x"));
+
+        let err = check(&format!("{f} f(1, y: 2, y: 3)")).unwrap_err();
+        let ErrorKind::DuplicateArgumentLabel { label, first } = err.kind else {
+            panic!("expected a duplicate label, got {err:?}");
+        };
+        assert_eq!(label, "y");
+        // both `y:` arguments are pointed to
+        assert_eq!(err.span.start - first.start, "2, y: ".len());
+    }
+
+    #[test]
+    fn composition_types_must_line_up() {
+        let fs = "to_bool :: fun(~x: Int) Bool { x > 0 }; not :: fun(~b: Bool) Bool { !b };";
+        assert!(check(&format!("{fs} to_bool ~> not")).is_ok());
+
+        let err = check(&format!("{fs} not ~> to_bool")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+
+        let err = check(&format!("{fs} 3 ~> not")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::NotAFunction { type_id: INT_ID }
+        ));
+    }
+
+    #[test]
+    fn function_types() {
+        let fs = "twice :: fun(~f: fun(Int) Int, ~x: Int) Int { f(f(x)) };
+                  adder :: fun(~n: Int) fun(Int) Int { |x: Int| x + n };";
+        assert!(check(&format!("{fs} twice(adder(1), 2) + 1")).is_ok());
+        assert!(check(&format!("{fs} adder(1)(2) + 1")).is_ok());
+        // the parameter types of a function passed along come from the annotation
+        assert!(check(&format!("{fs} twice(|x| x * 2, 3)")).is_ok());
+        assert!(check("log : fun(String) : fun(~s: String) {}; u : Unit = log(\"hi\")").is_ok());
+
+        let err = check(&format!("{fs} twice(|x: Bool| x, 3)")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+
+        let err = check("f :: fun(~g: fun(Nope) Int) {}").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::TypeNotFound { .. }));
+        // nothing but `Option` takes type arguments
+        let err = check("f :: fun(~xs: List(Int)) {}").unwrap_err();
+        assert!(
+            matches!(err.kind, ErrorKind::TypeNotFound { ty } if ty.to_string() == "List(Int)")
+        );
+
+        // nor is an unknown one in a variable's annotation
+        let err = check("x : Foo : 5; x + 1").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::TypeNotFound { ty } if ty.to_string() == "Foo"));
+        assert_eq!(err.span, Span::from_range(4, 7));
+    }
+
+    #[test]
+    fn options() {
+        let find = "find :: fun(~n: Int) Option(Int) { match n { 0 => some(n), _ => none } };";
+        assert!(check(&format!("{find} find(1) ?? 0 + 1")).is_ok());
+        assert!(
+            check(&format!(
+                "{find} x :: find(0) |> some; y: Option(Int) = x ?? none"
+            ))
+            .is_ok()
+        );
+        // a `??` chain ends in whatever isn't an option
+        assert!(check(&format!("{find} find(1) ?? find(2) ?? 3 + 1")).is_ok());
+        // but it groups from the right, so a nested option takes parentheses
+        assert!(
+            check("x : Option(Option(Bool)) : some(some(true)); !((x ?? none) ?? false)").is_ok()
+        );
+
+        let err = check("x :: none").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::CannotInferNoneType));
+        let err = check("x : Int : none").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::NotAnOption { type_id: INT_ID }
+        ));
+        let err = check("1 ?? 2").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::NotAnOption { type_id: INT_ID }
+        ));
+        let err = check(&format!("{find} find(1) ?? true")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+        let err = check("x : Option(Int) : some(true)").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+        let err = check("some(1, 2)").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::ArgumentCountMismatch {
+                expected: 1,
+                got: 2
+            }
+        ));
+        // `some` and `none` are only builtin where nothing else has the name
+        assert!(check("some :: fun(~x: Int) Int { x }; some(1) + 1").is_ok());
+    }
+
+    #[test]
+    fn results() {
+        let parse = "parse :: fun(~s: String) Result(Int, String) {
+                         match s == \"1\" { true => ok(1), false => err(\"not a number\") }
+                     };";
+        assert!(check(&format!("{parse} r : Result(Int, String) : parse(\"1\")")).is_ok());
+        // `?` gives the `ok` value, and returns the error from the function
+        assert!(
+            check(&format!(
+                "{parse} twice :: fun(~s: String) Result(Int, String) {{ n :: parse(s)?; ok(n * 2) }}"
+            ))
+            .is_ok()
+        );
+        assert!(
+            check(&format!(
+                "{parse} f :: fun() Result(Unit, String) {{ parse(\"2\")? + 1; \"x\" |> err }}"
+            ))
+            .is_ok()
+        );
+
+        let err = check("r :: ok(1)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::CannotInferResultType));
+        let err = check("r : Option(Int) : err(1)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::NotAResult { .. }));
+        let err = check("r : Result(Int, String) : err(1)").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: STRING_ID,
+                got: INT_ID
+            }
+        ));
+        let err = check("f :: fun(~n: Int) Int { n? }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::NotAResult { type_id: INT_ID }
+        ));
+
+        // only a function returning a result with the same error type can take one
+        let err = check(&format!("{parse} parse(\"1\")?")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TryOutsideResultFunction { return_type: None }
+        ));
+        let err = check(&format!("{parse} f :: fun() Int {{ parse(\"1\")? }}")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TryOutsideResultFunction {
+                return_type: Some(INT_ID)
+            }
+        ));
+        let err = check(&format!("{parse} f :: fun() {{ parse(\"1\")?; }}")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TryOutsideResultFunction { return_type: None }
+        ));
+        let err = check(&format!(
+            "{parse} f :: fun() Result(Int, Bool) {{ ok(parse(\"1\")?) }}"
+        ))
+        .unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: BOOL_ID,
+                got: STRING_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn safe_field_access() {
+        let user = "struct User { name: String, email: Option(String) };
+                    u : Option(User) : some(User(name: \"a\", email: none));";
+        assert!(check(&format!("{user} n : Option(String) : u?.name")).is_ok());
+        // an optional field isn't wrapped again
+        assert!(check(&format!("{user} e : Option(String) : u?.email")).is_ok());
+        assert!(check(&format!("{user} s : String : u?.email ?? \"none\"")).is_ok());
+
+        let err = check(&format!("{user} u?.age")).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::FieldNotFound { .. }));
+        let err = check("struct P { x: Int }; p :: P(x: 1); p?.x").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::NotAnOption { .. }));
+        // `?.` doesn't carry on to a `.` after it
+        let err = check(&format!("{user} u?.name.len")).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::FieldNotFound { .. }));
+    }
+
+    #[test]
+    fn pipelines() {
+        let fs = "add :: fun(~a: Int, ~b: Int) Int { a + b }; scale :: fun(by: Int, ~x: Int) Int { x * by };";
+        // the piped value goes to the first unlabeled parameter
+        assert!(check(&format!("{fs} x :: 1 |> add(2) |> scale(by: 3); x + 1")).is_ok());
+        // anything else is called with just the piped value
+        assert!(check(&format!("{fs} b :: 1 |> fun(~n: Int) Bool {{ n > 0 }}; !b")).is_ok());
+
+        let err = check(&format!("{fs} 1 |> add")).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::ArgumentCountMismatch {
+                expected: 2,
+                got: 1
+            }
+        ));
+
+        let err = check("f :: fun(by: Int) Int { by }; 1 |> f").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::MissingArgumentLabel { label } if label == "by"));
+    }
+
+    #[test]
+    fn parameters_with_the_same_label() {
+        // the parser rejects these, but a program that's put together some
+        // other way can still have them
+        let mut program = Parser::new("f :: fun(x a: Int, y b: Int) Int { a }")
+            .parse()
+            .unwrap();
+        let ExpressionKind::VariableDecl { value, .. } = &mut program.0[0].expr.kind else {
+            panic!("expected a variable declaration");
+        };
+        let ExpressionKind::Function { parameters, .. } = &mut value.kind else {
+            panic!("expected a function");
+        };
+        let FunctionParameter::LabeledAtCallsite { external_name, .. } = &mut parameters[1] else {
+            panic!("expected a labeled parameter");
+        };
+        *external_name = Some("x".to_string());
+
+        let err = Checker::new().try_check_program(&program).unwrap_err();
+        let ErrorKind::DuplicateParameterLabel { label, first } = err.kind else {
+            panic!("expected a duplicate label, got {err:?}");
+        };
+        assert_eq!(label, "x");
+        assert_eq!(first, Span::from_range(9, 10));
+        assert_eq!(err.span, Span::from_range(19, 20));
+    }
+
+    #[test]
+    fn chained_comparisons() {
+        assert!(check("1 < 2 <= 3 > 0").is_ok());
+
+        // `(1 < 2) < 3` compares a bool to an int
+        let err = check("(1 < 2) < 3").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::OperatorNotImplemented {
+                operator: LessThan,
+                type_id: BOOL_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn sized_int_ranges() {
+        assert!(check("x :: 255u8; y : i8 = -128;").is_ok());
+
+        let err = check("256u8").unwrap_err();
+        assert!(
+            matches!(err.kind, ErrorKind::IntLiteralOutOfRange { type_id } if type_id == u8_id())
+        );
+
+        let err = check("x : i8 = 128;").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::IntLiteralOutOfRange { .. }));
+
+        // unsigned ints can't be negated
+        let err = check("x :: 1u8; -x").unwrap_err();
+        assert!(
+            matches!(err.kind, ErrorKind::TypeMismatch { expected: INT_ID, got } if got == u8_id())
+        );
+
+        // and different int types don't mix without a conversion
+        let err = check("x :: 1u8; y :: 2; x + y").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch { got: INT_ID, .. }
+        ));
+        assert!(check("x :: 1u8; y :: 2; x.to_int() + y").is_ok());
+    }
+
+    #[test]
+    fn string_and_char_literals() {
+        assert!(check(r#"s :: "hi"; c : Char = 'h'; (s != "ho") == (c == 'h')"#).is_ok());
+
+        let err = check(r#"n : Int = "1""#).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: STRING_ID
+            }
+        ));
+        let err = check(r#""a" == 'a'"#).unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: STRING_ID,
+                got: CHAR_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn float_arithmetic() {
+        assert!(check("x :: 1.5; y : Float = -x * 2.0 ** 0.5; y < x == (x != 1.0)").is_ok());
+
+        let err = check("1.5 << 2.0").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::OperatorNotImplemented {
+                operator: ShiftLeft,
+                type_id: FLOAT_ID
+            }
+        ));
+        // ints aren't turned into floats
+        let err = check("1.5 + 1").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: FLOAT_ID,
+                got: INT_ID
+            }
+        ));
+    }
+
+    #[test]
+    fn tuples() {
+        assert!(check("p : (u8, Bool) : (255, true); q :: (p.0, (p,)); q.1.0.1 == false").is_ok());
+
+        let err = check("p : (u8, Bool) : (256, true)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::IntLiteralOutOfRange { .. }));
+
+        let err = check("p : (Int, Int) : (1, true)").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::TypeMismatch {
+                expected: INT_ID,
+                got: BOOL_ID
+            }
+        ));
+
+        let err = check("p :: (1, true); p.2").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::FieldNotFound { field, .. } if field == "2"));
+        let err = check("1.0 .0").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::FieldNotFound {
+                type_id: FLOAT_ID,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn structs() {
+        let checked =
+            check("struct P { x: u8, next: P }; f :: fun(p: P) u8 { p.next.x + 1 }").unwrap();
+        assert!(matches!(
+            checked[0].expr.data,
+            CheckedExpressionData::Struct { .. }
+        ));
+
+        // fields are labeled arguments, so they get the same errors
+        let err = check("struct P { x: u8, y: Bool }; P(x: 256, y: true)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::IntLiteralOutOfRange { .. }));
+        let err = check("struct P { x: Int }; P(1)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::MissingArgumentLabel { label } if label == "x"));
+        let err = check("struct P { x: Int, y: Int }; P(x: 1, x: 2)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::DuplicateArgumentLabel { .. }));
+
+        let err = check("struct P { x: Int }; P(x: 1).y").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::FieldNotFound { field, .. } if field == "y"));
+        let err = check("Q(x: 1)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::VariableNotFound { name } if name == "Q"));
+    }
+
+    #[test]
+    fn enums() {
+        let checked = check(
+            "enum E { A(x: u8), B(E) }; f :: fun(e: E) u8 { match e { E.A(x: x) => x, E.B(_) => 0 } }",
+        )
+        .unwrap();
+        assert!(matches!(
+            checked[0].expr.data,
+            CheckedExpressionData::Enum { .. }
+        ));
+
+        // fields are arguments, so they get the same errors
+        let err = check("enum E { A(x: u8) }; E.A(x: 256)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::IntLiteralOutOfRange { .. }));
+        let err = check("enum E { A(x: Int) }; E.A(1)").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::MissingArgumentLabel { label } if label == "x"));
+        let err = check("enum E { A }; E.B").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::VariantNotFound { variant, .. } if variant == "B"));
+
+        let err = check("enum E { A(Int) }; match E.A(1) { E.A(x, y) => 1 }").unwrap_err();
+        assert!(matches!(
+            err.kind,
+            ErrorKind::ArgumentCountMismatch {
+                expected: 1,
+                got: 2
+            }
+        ));
+        let err = check("enum E { A(x: Int) }; match E.A(x: 1) { E.A(y: y) => y }").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::UnknownArgumentLabel { label } if label == "y"));
+        let err = check("match 1 { Int.A => 1 }").unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::VariantNotFound { variant, .. } if variant == "A"));
+    }
+
+    #[test]
+    fn int_literal_boundaries() {
+        let (min, max) = (isize::MIN, isize::MAX);
+        assert!(check(&format!("x :: {max}; y :: -{}", min.unsigned_abs())).is_ok());
+        assert!(check(&format!("x : u64 = {}", u64::MAX)).is_ok());
+        assert!(
+            check(&format!(
+                "match 1 {{ -{} => 1, {max} => 2 }}",
+                min.unsigned_abs()
+            ))
+            .is_ok()
+        );
+
+        for input in [
+            format!("{}", max as u64 + 1),
+            format!("-{}", min.unsigned_abs() + 1),
+            format!("match 1 {{ {} => 1 }}", max as u64 + 1),
+        ] {
+            let err = check(&input).unwrap_err();
+            assert!(matches!(
+                err.kind,
+                ErrorKind::IntLiteralOutOfRange { type_id: INT_ID }
+            ));
+        }
+    }
+
+    #[test]
+    fn unsupported_features() {
+        for (input, feature, span) in [
+            ("x :: 1..3;", "ranges", Span::from_range(5, 9)),
+            (
+                "f :: fun(n: Int) { 0..=n }",
+                "ranges",
+                Span::from_range(19, 24),
+            ),
+            (
+                "f :: fun(n: Int) { for i in 1..n {} }",
+                "for loops",
+                Span::from_range(19, 35),
+            ),
+            (
+                "xs :: (1, 2); y :: xs[0]; z :: y + 1",
+                "indexing",
+                Span::from_range(19, 24),
+            ),
+            (
+                "xs := (1, 2); xs[0] = 3",
+                "assigning to an index",
+                Span::from_range(14, 19),
+            ),
+            (
+                "f :: fun[T: Eq + Show](x: T) T { x };",
+                "trait bounds",
+                Span::from_range(12, 21),
+            ),
+            (
+                "f :: fun(...rest: Int) Int { 1 }; f(1, 2)",
+                "rest parameters",
+                Span::from_range(5, 32),
+            ),
+            (
+                "f :: fun(~a: Int, ~b: Int) Int { a }; t :: (1, 2); f(...t)",
+                "spreading arguments",
+                Span::from_range(56, 57),
+            ),
+            (
+                "t :: (1, 2); x : Option(Int) : some(...t)",
+                "spreading arguments",
+                Span::from_range(39, 40),
+            ),
+        ] {
+            let program = Parser::new(input).parse().unwrap();
+            let mut checker = Checker::new();
+            checker.check_program_recovering(&program);
+            let [err] = checker.errors() else {
+                panic!("expected one error for `{input}`");
+            };
+            assert!(
+                matches!(err.kind, ErrorKind::Unsupported { feature: f } if f == feature),
+                "{input}"
+            );
+            assert_eq!(err.span, span, "{input}");
+        }
+    }
+
+    fn u8_id() -> TypeId {
+        FIRST_SIZED_INT_ID + SIZED_INT_TYPES.iter().position(|t| t.0 == "u8").unwrap()
+    }
+}
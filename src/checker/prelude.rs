@@ -0,0 +1,132 @@
+//! What's in scope before a program declares anything, besides the builtin
+//! types every program has.
+//!
+//! An embedder adds the types and functions it provides, and `lovely check
+//! --prelude` reads them from a file like
+//!
+//! ```text
+//! # the host's drawing functions
+//! type Color
+//! fun rgb(r: Int, g: Int, b: Int) Color
+//! fun fill(Color)
+//! ```
+//!
+//! where a parameter with a name has to be labeled at the call site, and a
+//! function without a return type returns `Unit`.
+
+/// Types and functions for the checker to put in the outermost scope.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Prelude {
+    pub(super) types: Vec<String>,
+    pub(super) functions: Vec<PreludeFunction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PreludeFunction {
+    pub name: String,
+    /// The label each parameter is passed with, if any, and its type.
+    pub parameters: Vec<(Option<String>, String)>,
+    pub return_type: String,
+}
+
+impl Prelude {
+    /// Adds a type that programs can name and pass around, but only the
+    /// prelude's functions can make or take apart.
+    pub fn with_type(mut self, name: &str) -> Self {
+        self.types.push(name.to_string());
+        self
+    }
+
+    /// Adds a function, whose parameters are each a label or `None`, and a
+    /// type name.
+    pub fn with_function(
+        mut self,
+        name: &str,
+        parameters: &[(Option<&str>, &str)],
+        return_type: &str,
+    ) -> Self {
+        self.functions.push(PreludeFunction {
+            name: name.to_string(),
+            parameters: parameters
+                .iter()
+                .map(|(label, ty)| (label.map(str::to_string), ty.to_string()))
+                .collect(),
+            return_type: return_type.to_string(),
+        });
+        self
+    }
+
+    /// Reads a prelude in the format described in the [module docs](self).
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut prelude = Prelude::default();
+        for (number, line) in (1..).zip(source.lines()) {
+            let line = line.split('#').next().unwrap().trim();
+            let err = |what: &str| format!("line {number}: {what}");
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix("type ") {
+                prelude = prelude.with_type(name.trim());
+                continue;
+            }
+            let Some(signature) = line.strip_prefix("fun ") else {
+                return Err(err("expected `type` or `fun`"));
+            };
+            let (name, rest) = signature
+                .split_once('(')
+                .ok_or_else(|| err("expected `(` after the function name"))?;
+            let (parameters, return_type) = rest
+                .split_once(')')
+                .ok_or_else(|| err("expected `)` after the parameters"))?;
+            let parameters = parameters
+                .split(',')
+                .map(str::trim)
+                .filter(|parameter| !parameter.is_empty())
+                .map(|parameter| match parameter.split_once(':') {
+                    Some((label, ty)) => (Some(label.trim()), ty.trim()),
+                    None => (None, parameter),
+                })
+                .collect::<Vec<_>>();
+            let return_type = match return_type.trim() {
+                "" => "Unit",
+                return_type => return_type,
+            };
+            prelude = prelude.with_function(name.trim(), &parameters, return_type);
+        }
+        Ok(prelude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_declarations() {
+        let prelude = Prelude::parse(
+            "# drawing\ntype Color\n\nfun rgb(r: Int, g: Int, b: Int) Color\nfun fill(Color)  # returns unit\n",
+        )
+        .unwrap();
+        assert_eq!(
+            prelude,
+            Prelude::default()
+                .with_type("Color")
+                .with_function(
+                    "rgb",
+                    &[(Some("r"), "Int"), (Some("g"), "Int"), (Some("b"), "Int")],
+                    "Color"
+                )
+                .with_function("fill", &[(None, "Color")], "Unit")
+        );
+
+        assert_eq!(
+            Prelude::parse("type A\nlet x").unwrap_err(),
+            "line 2: expected `type` or `fun`"
+        );
+        assert_eq!(
+            Prelude::parse("fun f(Int").unwrap_err(),
+            "line 1: expected `)` after the parameters"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use super::TypeId;
+use crate::span::Span;
 
 pub type ScopeId = usize;
 
@@ -6,10 +7,27 @@ pub struct Scope {
     pub parent_scope: Option<ScopeId>,
 }
 
+/// The label each parameter of a function has to be passed with, if any.
+pub type ParameterLabels = Vec<Option<String>>;
+
+/// The label of each field of an enum variant, if it has one, and its type.
+pub type VariantFields = Vec<(Option<String>, TypeId)>;
+
 pub struct ScopedVariable {
     pub name: String,
     pub type_id: TypeId,
     pub scope_id: ScopeId,
+    /// Only known when the variable is bound to a function declaration.
+    pub parameter_labels: Option<ParameterLabels>,
+    /// Only variables declared with `:=` can be assigned to.
+    pub mutable: bool,
+    /// The declaration of a variable that isn't a parameter or a binding from
+    /// a pattern, and the `:` or `=` right before its value.
+    pub declaration: Option<(Span, Span)>,
+    /// How many function bodies a function declared ahead of its block was
+    /// in, until its declaration is reached. Before then, it can only be used
+    /// in the bodies of functions in that block.
+    pub hoisted_at: Option<usize>,
 }
 impl ScopedVariable {
     pub fn new(name: &str, scope_id: ScopeId, type_id: TypeId) -> Self {
@@ -17,6 +35,10 @@ impl ScopedVariable {
             name: name.to_string(),
             scope_id,
             type_id,
+            parameter_labels: None,
+            mutable: false,
+            declaration: None,
+            hoisted_at: None,
         }
     }
 }
@@ -25,11 +47,29 @@ pub struct ScopedType {
     pub kind: TypeKind,
     pub scope_id: ScopeId,
 }
-enum TypeKind {
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TypeKind {
     Name(String),
     Function {
-        parameters: Vec<ScopedType>,
-        return_type: Box<ScopedType>,
+        parameters: Vec<TypeId>,
+        return_type: TypeId,
+    },
+    Tuple(Vec<TypeId>),
+    /// `Option(T)`, which is either `some` value of `T` or `none`.
+    Option(TypeId),
+    /// `Result(T, E)`, which is either an `ok` value of `T` or an `err` of `E`.
+    Result {
+        ok: TypeId,
+        err: TypeId,
+    },
+    Struct {
+        name: String,
+        fields: Vec<(String, TypeId)>,
+    },
+    /// The name and fields of each variant.
+    Enum {
+        name: String,
+        variants: Vec<(String, VariantFields)>,
     },
 }
 impl ScopedType {
@@ -39,4 +79,61 @@ impl ScopedType {
             scope_id,
         }
     }
+
+    pub fn function(parameters: Vec<TypeId>, return_type: TypeId, scope_id: ScopeId) -> Self {
+        ScopedType {
+            kind: TypeKind::Function {
+                parameters,
+                return_type,
+            },
+            scope_id,
+        }
+    }
+
+    pub fn tuple(elements: Vec<TypeId>, scope_id: ScopeId) -> Self {
+        ScopedType {
+            kind: TypeKind::Tuple(elements),
+            scope_id,
+        }
+    }
+
+    pub fn option(inner: TypeId, scope_id: ScopeId) -> Self {
+        ScopedType {
+            kind: TypeKind::Option(inner),
+            scope_id,
+        }
+    }
+
+    pub fn result(ok: TypeId, err: TypeId, scope_id: ScopeId) -> Self {
+        ScopedType {
+            kind: TypeKind::Result { ok, err },
+            scope_id,
+        }
+    }
+}
+
+pub struct ScopedMethod {
+    pub name: String,
+    pub receiver_type_id: TypeId,
+    pub type_id: TypeId,
+    pub scope_id: ScopeId,
+    /// The labels of the parameters after `self`.
+    pub parameter_labels: ParameterLabels,
+}
+impl ScopedMethod {
+    pub fn new(
+        name: &str,
+        receiver_type_id: TypeId,
+        scope_id: ScopeId,
+        type_id: TypeId,
+        parameter_labels: ParameterLabels,
+    ) -> Self {
+        ScopedMethod {
+            name: name.to_string(),
+            receiver_type_id,
+            type_id,
+            scope_id,
+            parameter_labels,
+        }
+    }
 }
@@ -0,0 +1,92 @@
+//! Golden-file tests: every `.lv` file in a directory is parsed and checked,
+//! and what happened is compared against the `.expected` file next to it.
+//!
+//! The outcome of a file is its AST as an [s-expression](crate::parser::sexp)
+//! and whether it type checks with any warnings, or the errors it has.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    checker::Checker,
+    parser::{Parser, cfg::Config, sexp::program_to_sexp},
+};
+
+/// A file whose outcome isn't the expected one.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub path: PathBuf,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Runs every `.lv` file in `dir`, in order of their names. With `bless`, the
+/// `.expected` files are updated to the actual outcomes instead.
+pub fn run(dir: &Path, bless: bool) -> io::Result<Vec<Mismatch>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "lv"));
+    paths.sort();
+
+    let mut mismatches = vec![];
+    for path in paths {
+        let actual = outcome(&fs::read_to_string(&path)?);
+        let expected_path = path.with_extension("expected");
+        if bless {
+            fs::write(&expected_path, &actual)?;
+            continue;
+        }
+
+        let expected = match fs::read_to_string(&expected_path) {
+            Ok(expected) => expected,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        if expected != actual {
+            mismatches.push(Mismatch {
+                path,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// What parsing and checking `source` comes to, in the `.expected` format.
+pub fn outcome(source: &str) -> String {
+    let mut program = match Parser::new(source).parse() {
+        Ok(program) => program,
+        Err(err) => return format!("== parse\nerror: {err:?}\n"),
+    };
+    let parsed = program_to_sexp(&program);
+    Config::default().apply(&mut program);
+
+    let mut checker = Checker::new();
+    checker.check_program_recovering(&program);
+    let check = match checker.errors() {
+        [] => checker
+            .warnings()
+            .iter()
+            .fold("ok".to_string(), |out, warning| {
+                format!(
+                    "{out}\nwarning at {}..{}: {:?}",
+                    warning.span.start, warning.span.end, warning.kind
+                )
+            }),
+        errors => errors
+            .iter()
+            .map(|err| {
+                format!(
+                    "error at {}..{}: {:?}",
+                    err.span.start, err.span.end, err.kind
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    format!("== parse\n{parsed}== check\n{check}\n")
+}
@@ -0,0 +1,138 @@
+//! Colors source code for the terminal, based on the tokens the lexer finds.
+
+use crate::lexer::{
+    Lexer,
+    tokens::TokenKind::{self, *},
+};
+
+/// The ANSI SGR parameters each kind of token is written with, like `"1;34"`
+/// for bold blue. An empty string leaves that kind of token uncolored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub keyword: String,
+    /// Ints, bools and `unit`.
+    pub literal: String,
+    /// String and char literals.
+    pub string: String,
+    pub ident: String,
+    /// Identifiers starting with an uppercase letter, which name types by convention.
+    pub ty: String,
+    pub operator: String,
+    pub punctuation: String,
+    pub comment: String,
+    /// Tokens the lexer couldn't make sense of, like an unterminated string.
+    pub error: String,
+}
+
+impl Theme {
+    /// A theme that doesn't color anything.
+    pub fn plain() -> Self {
+        Self {
+            keyword: String::new(),
+            literal: String::new(),
+            string: String::new(),
+            ident: String::new(),
+            ty: String::new(),
+            operator: String::new(),
+            punctuation: String::new(),
+            comment: String::new(),
+            error: String::new(),
+        }
+    }
+
+    fn style(&self, kind: &TokenKind, text: &str) -> &str {
+        match kind {
+            Fun | Impl | Struct | Enum | Operator | SelfValue | Match | While | Loop | For | In
+            | Break | Continue | Return => &self.keyword,
+            Unit | True | False | IntLiteral | FloatLiteral => &self.literal,
+            StringLiteral | CharLiteral => &self.string,
+            Identifier if text.starts_with(|c: char| c.is_ascii_uppercase()) => &self.ty,
+            Identifier => &self.ident,
+            LParen | RParen | LBrace | RBrace | LBracket | RBracket | Colon | Comma | Dot
+            | Ellipsis | DotDot | DotDotEqual | Tilde | Semicolon | FatArrow | At => {
+                &self.punctuation
+            }
+            DocComment => &self.comment,
+            Illegal(_) => &self.error,
+            _ => &self.operator,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            keyword: "35".to_string(),
+            literal: "33".to_string(),
+            string: "32".to_string(),
+            ident: String::new(),
+            ty: "36".to_string(),
+            operator: "1".to_string(),
+            punctuation: String::new(),
+            comment: "2;3".to_string(),
+            error: "1;31".to_string(),
+        }
+    }
+}
+
+pub fn highlight(source: &str, theme: &Theme) -> String {
+    let mut out = String::new();
+    let mut position = 0;
+    for token in Lexer::new(source) {
+        let text = token.span.slice(source);
+        highlight_between_tokens(&source[position..token.span.start], theme, &mut out);
+        paint(text, theme.style(&token.kind, text), &mut out);
+        position = token.span.end;
+    }
+    highlight_between_tokens(&source[position..], theme, &mut out);
+    out
+}
+
+/// The lexer skips over whitespace and comments, so they're picked out here.
+fn highlight_between_tokens(mut text: &str, theme: &Theme, out: &mut String) {
+    while let Some(start) = text.find('#') {
+        out.push_str(&text[..start]);
+        let end = text[start..]
+            .find('\n')
+            .map_or(text.len(), |end| start + end);
+        paint(&text[start..end], &theme.comment, out);
+        text = &text[end..];
+    }
+    out.push_str(text);
+}
+
+fn paint(text: &str, style: &str, out: &mut String) {
+    if style.is_empty() {
+        out.push_str(text);
+    } else {
+        out.push_str(&format!("\x1b[{style}m{text}\x1b[0m"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_theme_keeps_the_source() {
+        let source = "# comment\nx :: fun(~n: Int) Int { n * 2 } # trailing\nx(\"a#b\")\n";
+        assert_eq!(highlight(source, &Theme::plain()), source);
+    }
+
+    #[test]
+    fn colors_tokens_by_kind() {
+        let theme = Theme {
+            keyword: "k".to_string(),
+            literal: "l".to_string(),
+            ty: "t".to_string(),
+            operator: "o".to_string(),
+            comment: "c".to_string(),
+            ..Theme::plain()
+        };
+        assert_eq!(
+            highlight("x: Int = 1 + y # hi", &theme),
+            "x: \x1b[tmInt\x1b[0m \x1b[om=\x1b[0m \x1b[lm1\x1b[0m \x1b[om+\x1b[0m y \x1b[cm# hi\x1b[0m"
+        );
+        assert_eq!(highlight("loop", &theme), "\x1b[kmloop\x1b[0m");
+    }
+}
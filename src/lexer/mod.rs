@@ -3,7 +3,10 @@
 use std::iter::Peekable;
 use std::str::CharIndices;
 
-use tokens::{Token, TokenKind};
+use tokens::{LexError, Token, TokenKind};
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+use crate::span::Span;
 
 pub mod tokens;
 
@@ -31,6 +34,8 @@ impl<'src> Lexer<'src> {
         };
 
         match cur_char {
+            // comments run to the end of the line, which takes care of a
+            // `#!/usr/bin/env lovely` line at the top of a script as well
             '#' => {
                 self.next();
                 while let Some((_, c)) = self.peek() {
@@ -43,11 +48,73 @@ impl<'src> Lexer<'src> {
             }
             '+' => self.make_single_char_token(cur_index, Plus),
             '-' => self.make_single_char_token(cur_index, Minus),
+            // unlike other comments, doc comments are passed on to the parser
+            '/' if self.content[cur_index..].starts_with("///") => {
+                let end = self.content[cur_index..]
+                    .find('\n')
+                    .map_or(self.content.len(), |end| cur_index + end);
+                while self.peek().is_some_and(|(i, _)| i < end) {
+                    self.next();
+                }
+                Token::new(DocComment, cur_index, end - cur_index)
+            }
             '/' => self.make_single_char_token(cur_index, Slash),
-            '*' => self.make_single_char_token(cur_index, Asterisk),
-            '&' => self.make_single_char_token(cur_index, BitAnd),
-            '|' => self.make_single_char_token(cur_index, BitOr),
+            '*' => {
+                self.next();
+                if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '*')
+                    .is_some()
+                {
+                    Token::new(DoubleAsterisk, cur_index, 2)
+                } else {
+                    Token::new(Asterisk, cur_index, 1)
+                }
+            }
+            '&' => {
+                self.next();
+                if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '&')
+                    .is_some()
+                {
+                    Token::new(AmpAmp, cur_index, 2)
+                } else {
+                    Token::new(BitAnd, cur_index, 1)
+                }
+            }
+            '|' => {
+                self.next();
+                if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '|')
+                    .is_some()
+                {
+                    Token::new(PipePipe, cur_index, 2)
+                } else if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '>')
+                    .is_some()
+                {
+                    Token::new(Pipe, cur_index, 2)
+                } else {
+                    Token::new(BitOr, cur_index, 1)
+                }
+            }
             '^' => self.make_single_char_token(cur_index, BitXor),
+            '?' if self.content[cur_index..].starts_with("??") => {
+                for _ in 0..2 {
+                    self.next();
+                }
+                Token::new(QuestionQuestion, cur_index, 2)
+            }
+            '?' if self.content[cur_index..].starts_with("?.") => {
+                for _ in 0..2 {
+                    self.next();
+                }
+                Token::new(QuestionDot, cur_index, 2)
+            }
+            '?' => self.make_single_char_token(cur_index, Question),
             '=' => {
                 self.next();
                 if self
@@ -56,6 +123,12 @@ impl<'src> Lexer<'src> {
                     .is_some()
                 {
                     Token::new(DoubleEqual, cur_index, 2)
+                } else if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '>')
+                    .is_some()
+                {
+                    Token::new(FatArrow, cur_index, 2)
                 } else {
                     Token::new(SingleEqual, cur_index, 1)
                 }
@@ -80,6 +153,12 @@ impl<'src> Lexer<'src> {
                     .is_some()
                 {
                     Token::new(LessThanOrEqual, cur_index, 2)
+                } else if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '<')
+                    .is_some()
+                {
+                    Token::new(ShiftLeft, cur_index, 2)
                 } else {
                     Token::new(LessThan, cur_index, 1)
                 }
@@ -92,6 +171,12 @@ impl<'src> Lexer<'src> {
                     .is_some()
                 {
                     Token::new(GreaterThanOrEqual, cur_index, 2)
+                } else if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '>')
+                    .is_some()
+                {
+                    Token::new(ShiftRight, cur_index, 2)
                 } else {
                     Token::new(GreaterThan, cur_index, 1)
                 }
@@ -100,25 +185,63 @@ impl<'src> Lexer<'src> {
             ')' => self.make_single_char_token(cur_index, RParen),
             '{' => self.make_single_char_token(cur_index, LBrace),
             '}' => self.make_single_char_token(cur_index, RBrace),
-            '~' => self.make_single_char_token(cur_index, Tilde),
+            '[' => self.make_single_char_token(cur_index, LBracket),
+            ']' => self.make_single_char_token(cur_index, RBracket),
+            '~' => {
+                self.next();
+                if self
+                    .chars
+                    .next_if(|(_, next_char)| *next_char == '>')
+                    .is_some()
+                {
+                    Token::new(Compose, cur_index, 2)
+                } else {
+                    Token::new(Tilde, cur_index, 1)
+                }
+            }
             ':' => self.make_single_char_token(cur_index, Colon),
             ',' => self.make_single_char_token(cur_index, Comma),
+            '@' => self.make_single_char_token(cur_index, At),
+            '.' if self.content[cur_index..].starts_with("...") => {
+                for _ in 0..3 {
+                    self.next();
+                }
+                Token::new(Ellipsis, cur_index, 3)
+            }
+            '.' if self.content[cur_index..].starts_with("..=") => {
+                for _ in 0..3 {
+                    self.next();
+                }
+                Token::new(DotDotEqual, cur_index, 3)
+            }
+            '.' if self.content[cur_index..].starts_with("..") => {
+                for _ in 0..2 {
+                    self.next();
+                }
+                Token::new(DotDot, cur_index, 2)
+            }
+            '.' => self.make_single_char_token(cur_index, Dot),
             ';' => self.make_single_char_token(cur_index, Semicolon),
-            'a'..='z' | 'A'..='Z' | '_' => {
+            'r' if self.content[cur_index + 1..].starts_with('"') => self.read_string(cur_index),
+            c if c == '_' || is_xid_start(c) => {
                 let ident = self.read_ident(cur_index);
-                match ident {
-                    "fun" => Token::new(Fun, cur_index, 3),
-                    "unit" => Token::new(Unit, cur_index, 4),
-                    "true" => Token::new(True, cur_index, 4),
-                    "false" => Token::new(False, cur_index, 5),
-                    s => Token::new(Identifier, cur_index, s.len()),
-                }
+                let kind = KEYWORDS
+                    .iter()
+                    .find(|(keyword, _)| *keyword == ident)
+                    .map_or(Identifier, |(_, kind)| kind.clone());
+                Token::new(kind, cur_index, ident.len())
             }
-            '0'..='9' => {
-                let size = self.read_int(cur_index);
-                Token::new(IntLiteral, cur_index, size)
+            '"' => self.read_string(cur_index),
+            '\'' => self.read_char(cur_index),
+            '0'..='9' => self.read_number(cur_index),
+            c => {
+                self.next();
+                Token::new(
+                    Illegal(LexError::UnexpectedCharacter),
+                    cur_index,
+                    c.len_utf8(),
+                )
             }
-            c => panic!("illegal token: {c}"),
         }
     }
 
@@ -136,26 +259,162 @@ impl<'src> Lexer<'src> {
         tok
     }
 
+    /// Reads a name made of the characters Unicode allows in identifiers,
+    /// which can take more than a byte each.
     fn read_ident(&mut self, position: usize) -> &'src str {
-        let mut last = position;
+        self.next();
+        while self.peek().is_some_and(|(_, c)| is_xid_continue(c)) {
+            self.next();
+        }
+        &self.content[position..self.position()]
+    }
+
+    fn read_number(&mut self, position: usize) -> Token {
+        let mut last;
+        if let Some(radix) = radix_prefix(&self.content[position..]) {
+            self.next();
+            (last, _) = self.next().unwrap();
+            let digits_start = last;
+            last = self.read_digits(last, radix);
+            if last == digits_start {
+                return Token::new(TokenKind::Illegal(LexError::MissingDigits), position, 2);
+            }
+        } else {
+            last = self.read_digits(position, 10);
+
+            // `1.5` is a float, but `1.max(2)` calls a method on an int
+            let rest = &self.content[last + 1..];
+            if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+                let (dot, _) = self.next().unwrap();
+                last = self.read_digits(dot, 10);
+                return Token::new(TokenKind::FloatLiteral, position, last + 1 - position);
+            }
+        }
+
+        // a suffix like the `u8` in `255u8` is part of the literal
+        let rest = &self.content[last + 1..];
+        if let Some(suffix) = INT_SUFFIXES.iter().find(|suffix| {
+            rest.strip_prefix(**suffix)
+                .is_some_and(|after| !after.starts_with(is_xid_continue))
+        }) {
+            for _ in 0..suffix.len() {
+                let (l, _) = self.next().unwrap();
+                last = l;
+            }
+        }
+
+        Token::new(TokenKind::IntLiteral, position, last + 1 - position)
+    }
+
+    /// Skips the digits in `radix` coming up, along with the `_`s between
+    /// them, returning where the last one is, or `last` if there aren't any.
+    fn read_digits(&mut self, mut last: usize, radix: u32) -> usize {
+        let mut first = true;
         while self
             .peek()
-            .is_some_and(|(_, c)| c.is_ascii_alphanumeric() || c == '_')
+            .is_some_and(|(_, c)| c.is_digit(radix) || c == '_' && !first)
         {
+            first = false;
             let (l, _) = self.next().unwrap();
             last = l;
         }
-        &self.content[position..last + 1]
+        last
     }
 
-    fn read_int(&mut self, position: usize) -> usize {
-        let mut last = position;
-        while self.peek().is_some_and(|(_, c)| c.is_ascii_digit()) {
-            let (l, _) = self.next().unwrap();
-            last = l;
+    /// Reads a string literal, which is raw if it starts with an `r`, so
+    /// that a backslash in it is just a backslash.
+    fn read_string(&mut self, position: usize) -> Token {
+        let raw = self.content[position..].starts_with('r');
+        if raw {
+            self.next();
+        }
+        let multiline = self.content[self.position()..].starts_with(MULTILINE_QUOTES);
+        for _ in 0..if multiline { 3 } else { 1 } {
+            self.next();
+        }
+
+        let mut invalid_escape = None;
+        loop {
+            match self.next() {
+                None => {
+                    return Token::new(
+                        TokenKind::Illegal(LexError::UnterminatedString),
+                        position,
+                        self.content.len() - position,
+                    );
+                }
+                Some((_, '"')) if !multiline => break,
+                Some((index, '"')) if self.content[index..].starts_with(MULTILINE_QUOTES) => {
+                    self.next();
+                    self.next();
+                    break;
+                }
+                Some((index, '\\')) if !raw => {
+                    if escape_sequence(&mut self.chars).is_none() && invalid_escape.is_none() {
+                        invalid_escape = Some(Span::from_range(index, self.position()));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        match invalid_escape {
+            Some(span) => Token {
+                kind: TokenKind::Illegal(LexError::InvalidEscape),
+                span,
+            },
+            None => Token::new(
+                TokenKind::StringLiteral,
+                position,
+                self.position() - position,
+            ),
+        }
+    }
+
+    fn read_char(&mut self, position: usize) -> Token {
+        self.next();
+
+        let mut kind = TokenKind::CharLiteral;
+        let mut invalid_escape = None;
+        match self.next() {
+            Some((index, '\\')) => {
+                if escape_sequence(&mut self.chars).is_none() {
+                    invalid_escape = Some(Span::from_range(index, self.position()));
+                }
+            }
+            Some((_, '\'' | '\n')) | None => {
+                return Token::new(
+                    TokenKind::Illegal(LexError::InvalidCharLiteral),
+                    position,
+                    self.position() - position,
+                );
+            }
+            Some(_) => {}
+        }
+
+        if self.chars.next_if(|(_, c)| *c == '\'').is_none() {
+            // swallow the rest of the literal so it's reported as a single token
+            while self
+                .chars
+                .next_if(|(_, c)| *c != '\'' && *c != '\n')
+                .is_some()
+            {}
+            self.chars.next_if(|(_, c)| *c == '\'');
+            kind = TokenKind::Illegal(LexError::InvalidCharLiteral);
+        }
+
+        match invalid_escape {
+            Some(span) => Token {
+                kind: TokenKind::Illegal(LexError::InvalidEscape),
+                span,
+            },
+            None => Token::new(kind, position, self.position() - position),
         }
-        let number = &self.content[position..last + 1];
-        number.len()
+    }
+
+    /// The index of the next char, or the end of the source if there isn't one.
+    fn position(&mut self) -> usize {
+        self.peek().map_or(self.content.len(), |(index, _)| index)
     }
 
     fn skip_whitespace(&mut self) {
@@ -165,6 +424,150 @@ impl<'src> Lexer<'src> {
     }
 }
 
+/// Reads the rest of an escape sequence after its `\\`, returning the char it
+/// stands for, or `None` if it isn't a valid escape sequence.
+fn escape_sequence(chars: &mut Peekable<CharIndices>) -> Option<char> {
+    match chars.next()?.1 {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        'u' => {
+            chars.next_if(|(_, c)| *c == '{')?;
+            let mut code = String::new();
+            while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                code.push(c);
+            }
+            chars.next_if(|(_, c)| *c == '}')?;
+            if code.is_empty() || code.len() > 6 {
+                return None;
+            }
+            char::from_u32(u32::from_str_radix(&code, 16).ok()?)
+        }
+        _ => None,
+    }
+}
+
+/// The words that are lexed as keywords rather than identifiers.
+pub const KEYWORDS: [(&str, TokenKind); 17] = [
+    ("fun", TokenKind::Fun),
+    ("unit", TokenKind::Unit),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
+    ("impl", TokenKind::Impl),
+    ("self", TokenKind::SelfValue),
+    ("match", TokenKind::Match),
+    ("while", TokenKind::While),
+    ("loop", TokenKind::Loop),
+    ("for", TokenKind::For),
+    ("in", TokenKind::In),
+    ("break", TokenKind::Break),
+    ("continue", TokenKind::Continue),
+    ("return", TokenKind::Return),
+    ("operator", TokenKind::Operator),
+    ("struct", TokenKind::Struct),
+    ("enum", TokenKind::Enum),
+];
+
+/// The names of the sized int types, which int literals can be suffixed with.
+pub const INT_SUFFIXES: [&str; 8] = ["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64"];
+
+/// The base of an int literal that starts with `0x`, `0o` or `0b`.
+fn radix_prefix(literal: &str) -> Option<u32> {
+    match literal.get(..2)? {
+        "0x" => Some(16),
+        "0o" => Some(8),
+        "0b" => Some(2),
+        _ => None,
+    }
+}
+
+const MULTILINE_QUOTES: &str = "\"\"\"";
+
+/// The value of a string literal the lexer has already accepted, quotes included.
+///
+/// The contents of a `\"\"\"` string start on the line after the opening quotes,
+/// and their common leading indentation is stripped, as is the line holding
+/// the closing quotes if it's only whitespace. Escape sequences are decoded
+/// unless the string is raw, with an `r` before the quotes.
+pub fn string_value(literal: &str) -> String {
+    let (raw, literal) = match literal.strip_prefix('r') {
+        Some(literal) => (true, literal),
+        None => (false, literal),
+    };
+    let contents = match literal
+        .strip_prefix(MULTILINE_QUOTES)
+        .and_then(|rest| rest.strip_suffix(MULTILINE_QUOTES))
+    {
+        Some(contents) => strip_indentation(contents),
+        None => literal[1..literal.len() - 1].to_string(),
+    };
+    if raw { contents } else { unescape(&contents) }
+}
+
+fn strip_indentation(contents: &str) -> String {
+    let contents = contents.strip_prefix('\n').unwrap_or(contents);
+    let mut lines = contents.split('\n').collect::<Vec<_>>();
+    if lines.len() > 1 && lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let indentation = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|l| {
+            if l.trim().is_empty() {
+                ""
+            } else {
+                l.get(indentation..).unwrap_or(l.trim_start())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The value of an int literal the lexer has already accepted, or `None` if
+/// it doesn't fit in a `u64`, and its suffix if it has one.
+pub fn int_value(literal: &str) -> (Option<u64>, Option<&str>) {
+    let (digits, suffix) = match INT_SUFFIXES
+        .iter()
+        .find(|suffix| literal.ends_with(**suffix))
+    {
+        Some(suffix) => literal.split_at(literal.len() - suffix.len()),
+        None => (literal, ""),
+    };
+    let (radix, digits) = match radix_prefix(digits) {
+        Some(radix) => (radix, &digits[2..]),
+        None => (10, digits),
+    };
+    let value = u64::from_str_radix(&digits.replace('_', ""), radix).ok();
+    (value, Some(suffix).filter(|suffix| !suffix.is_empty()))
+}
+
+/// Decodes the escape sequences in the contents of a string or char literal
+/// that the lexer has already accepted.
+pub fn unescape(literal: &str) -> String {
+    let mut chars = literal.char_indices().peekable();
+    let mut res = String::with_capacity(literal.len());
+    while let Some((_, c)) = chars.next() {
+        if c == '\\' {
+            res.extend(escape_sequence(&mut chars));
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
 impl Iterator for Lexer<'_> {
     type Item = Token;
 
@@ -288,4 +691,319 @@ calc(foo, bar)"#
             ],
         );
     }
+
+    #[test]
+    fn string_and_char_literals() {
+        let input = r#"greeting :: "hi\n\"you\""; c :: '\'';"#;
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "greeting"),
+                (Colon, ":"),
+                (Colon, ":"),
+                (StringLiteral, r#""hi\n\"you\"""#),
+                (Semicolon, ";"),
+                (Identifier, "c"),
+                (Colon, ":"),
+                (Colon, ":"),
+                (CharLiteral, r"'\''"),
+                (Semicolon, ";"),
+            ],
+        );
+    }
+
+    #[test]
+    fn invalid_literals() {
+        let input = r#""a\qb" 'ab' \ "open"#;
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Illegal(LexError::InvalidEscape), r"\q"),
+                (Illegal(LexError::InvalidCharLiteral), "'ab'"),
+                (Illegal(LexError::UnexpectedCharacter), r"\"),
+                (Illegal(LexError::UnterminatedString), r#""open"#),
+            ],
+        );
+    }
+
+    #[test]
+    fn unescape_sequences() {
+        assert_eq!(unescape(r#"a\nb\tc\\d\"e\'"#), "a\nb\tc\\d\"e'");
+        assert_eq!(unescape(r"\u{1F600}\0"), "\u{1F600}\0");
+    }
+
+    #[test]
+    fn multiline_strings() {
+        let input = "text :: \"\"\"\n    one \"quoted\"\n\n      two\n    \"\"\";";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "text"),
+                (Colon, ":"),
+                (Colon, ":"),
+                (StringLiteral, &input[8..input.len() - 1]),
+                (Semicolon, ";"),
+            ],
+        );
+
+        assert_eq!(
+            string_value(&input[8..input.len() - 1]),
+            "one \"quoted\"\n\n  two"
+        );
+        assert_eq!(string_value(r#""""a\tb""""#), "a\tb");
+        assert_eq!(string_value("\"\"\"\n  a\n  b\n\"\"\""), "a\nb");
+    }
+
+    #[test]
+    fn raw_strings() {
+        let input = r#"pattern :: r"\d+\"; r"""
+  C:\new
+  """ r x"#;
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "pattern"),
+                (Colon, ":"),
+                (Colon, ":"),
+                (StringLiteral, r#"r"\d+\""#),
+                (Semicolon, ";"),
+                (StringLiteral, "r\"\"\"\n  C:\\new\n  \"\"\""),
+                (Identifier, "r"),
+                (Identifier, "x"),
+            ],
+        );
+
+        assert_eq!(string_value(r#"r"\d+\""#), r"\d+\");
+        assert_eq!(string_value("r\"\"\"\n  C:\\new\n  \"\"\""), r"C:\new");
+    }
+
+    #[test]
+    fn shebang_line() {
+        let input = "#!/usr/bin/env lovely\nx :: 1 # one\n";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "x"),
+                (Colon, ":"),
+                (Colon, ":"),
+                (IntLiteral, "1"),
+            ],
+        );
+    }
+
+    #[test]
+    fn int_suffixes() {
+        let input = "255u8 3i64 4u80";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (IntLiteral, "255u8"),
+                (IntLiteral, "3i64"),
+                (IntLiteral, "4"),
+                (Identifier, "u80"),
+            ],
+        );
+    }
+
+    #[test]
+    fn radixes_and_digit_separators() {
+        let input = "0xFF 0o777u16 0b1010 1_000_000 1_000.000_1 0b12 0x _1 0xg";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (IntLiteral, "0xFF"),
+                (IntLiteral, "0o777u16"),
+                (IntLiteral, "0b1010"),
+                (IntLiteral, "1_000_000"),
+                (FloatLiteral, "1_000.000_1"),
+                (IntLiteral, "0b1"),
+                (IntLiteral, "2"),
+                (Illegal(LexError::MissingDigits), "0x"),
+                (Identifier, "_1"),
+                (Illegal(LexError::MissingDigits), "0x"),
+                (Identifier, "g"),
+            ],
+        );
+        assert_eq!(int_value("0xFF"), (Some(255), None));
+        assert_eq!(int_value("0o7_7u8"), (Some(63), Some("u8")));
+        assert_eq!(int_value("0b1_0"), (Some(2), None));
+        assert_eq!(int_value("1_000i32"), (Some(1000), Some("i32")));
+        assert_eq!(int_value("0x1_0000_0000_0000_0000"), (None, None));
+    }
+
+    #[test]
+    fn float_literals() {
+        let input = "1.5 0.25u8 3.max(2) 4. 5";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (FloatLiteral, "1.5"),
+                (FloatLiteral, "0.25"),
+                (Identifier, "u8"),
+                (IntLiteral, "3"),
+                (Dot, "."),
+                (Identifier, "max"),
+                (LParen, "("),
+                (IntLiteral, "2"),
+                (RParen, ")"),
+                (IntLiteral, "4"),
+                (Dot, "."),
+                (IntLiteral, "5"),
+            ],
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let input = "1..10 0..=n.len ...";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (IntLiteral, "1"),
+                (DotDot, ".."),
+                (IntLiteral, "10"),
+                (IntLiteral, "0"),
+                (DotDotEqual, "..="),
+                (Identifier, "n"),
+                (Dot, "."),
+                (Identifier, "len"),
+                (Ellipsis, "..."),
+            ],
+        );
+    }
+
+    #[test]
+    fn logical_operators() {
+        let input = "a && b || !c & d | e";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "a"),
+                (AmpAmp, "&&"),
+                (Identifier, "b"),
+                (PipePipe, "||"),
+                (ExclamationMark, "!"),
+                (Identifier, "c"),
+                (BitAnd, "&"),
+                (Identifier, "d"),
+                (BitOr, "|"),
+                (Identifier, "e"),
+            ],
+        );
+    }
+
+    #[test]
+    fn pipeline_operator() {
+        let input = "a |> f || b | > c";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "a"),
+                (Pipe, "|>"),
+                (Identifier, "f"),
+                (PipePipe, "||"),
+                (Identifier, "b"),
+                (BitOr, "|"),
+                (GreaterThan, ">"),
+                (Identifier, "c"),
+            ],
+        );
+    }
+
+    #[test]
+    fn question_mark_operators() {
+        let input = "a?.b ?? c? ?.?";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "a"),
+                (QuestionDot, "?."),
+                (Identifier, "b"),
+                (QuestionQuestion, "??"),
+                (Identifier, "c"),
+                (Question, "?"),
+                (QuestionDot, "?."),
+                (Question, "?"),
+            ],
+        );
+    }
+
+    #[test]
+    fn doc_comments() {
+        let input = "/// a point\nx # not a doc\n//// four\n///\ny";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (DocComment, "/// a point"),
+                (Identifier, "x"),
+                (DocComment, "//// four"),
+                (DocComment, "///"),
+                (Identifier, "y"),
+            ],
+        );
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let input = "größe :: 名前 + _x1 € Ωmega\u{301}";
+        let mut lexer = Lexer::new(input);
+        expect_tok(
+            &mut lexer,
+            input,
+            vec![
+                (Identifier, "größe"),
+                (Colon, ":"),
+                (Colon, ":"),
+                (Identifier, "名前"),
+                (Plus, "+"),
+                (Identifier, "_x1"),
+                (Illegal(LexError::UnexpectedCharacter), "€"),
+                (Identifier, "Ωmega\u{301}"),
+            ],
+        );
+    }
+
+    #[test]
+    fn keywords_and_symbols_lex_as_themselves() {
+        let spellings = KEYWORDS
+            .iter()
+            .map(|(keyword, kind)| (kind.clone(), keyword.to_string()))
+            .chain(
+                TokenKind::SYMBOLS
+                    .iter()
+                    .map(|kind| (kind.clone(), kind.to_string())),
+            );
+        for (kind, spelling) in spellings {
+            let tokens = Lexer::new(&spelling)
+                .map(|token| token.kind)
+                .collect::<Vec<_>>();
+            assert_eq!(tokens, [kind], "{spelling}");
+        }
+    }
 }
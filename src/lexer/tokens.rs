@@ -5,21 +5,42 @@ use crate::span::Span;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenKind {
     // keywords:
-    Fun,   // fun
-    Unit,  // unit
-    True,  // true
-    False, // false
+    Fun,       // fun
+    Unit,      // unit
+    True,      // true
+    False,     // false
+    Impl,      // impl
+    SelfValue, // self
+    Match,     // match
+    While,     // while
+    Loop,      // loop
+    For,       // for
+    In,        // in
+    Break,     // break
+    Continue,  // continue
+    Return,    // return
+    Operator,  // operator
+    Struct,    // struct
+    Enum,      // enum
 
     // syntax
     LParen,      // (
     RParen,      // )
     LBrace,      // {
     RBrace,      // }
+    LBracket,    // [
+    RBracket,    // ]
     Colon,       // :
     Comma,       // ,
+    Dot,         // .
+    Ellipsis,    // ...
+    DotDot,      // ..
+    DotDotEqual, // ..=
     Tilde,       // ~
     Semicolon,   // ;
     SingleEqual, // =
+    FatArrow,    // =>
+    At,          // @
 
     // operators:
     ExclamationMark,    // !
@@ -27,24 +48,111 @@ pub enum TokenKind {
     Minus,              // -
     Slash,              // /
     Asterisk,           // *
+    DoubleAsterisk,     // **
     BitAnd,             // &
     BitOr,              // |
     BitXor,             // ^
+    AmpAmp,             // &&
+    PipePipe,           // ||
     DoubleEqual,        // ==
     NotEqual,           // !=
     LessThan,           // <
     GreaterThan,        // >
     LessThanOrEqual,    // <=
     GreaterThanOrEqual, // >=
+    ShiftLeft,          // <<
+    ShiftRight,         // >>
+    Compose,            // ~>
+    Pipe,               // |>
+    Question,           // ?
+    QuestionQuestion,   // ??
+    QuestionDot,        // ?.
 
     // idents
     Identifier, // variable/type names
 
     IntLiteral,
+    FloatLiteral,
+    StringLiteral,
+    CharLiteral,
+
+    /// `///` and the rest of its line, which documents the declaration after it.
+    DocComment,
+
+    Illegal(LexError),
 
     Eof,
 }
 
+impl TokenKind {
+    /// The tokens that are always spelled the way they're displayed.
+    pub const SYMBOLS: [TokenKind; 41] = [
+        TokenKind::LParen,
+        TokenKind::RParen,
+        TokenKind::LBrace,
+        TokenKind::RBrace,
+        TokenKind::LBracket,
+        TokenKind::RBracket,
+        TokenKind::Colon,
+        TokenKind::Comma,
+        TokenKind::Dot,
+        TokenKind::Ellipsis,
+        TokenKind::DotDot,
+        TokenKind::DotDotEqual,
+        TokenKind::Tilde,
+        TokenKind::Semicolon,
+        TokenKind::SingleEqual,
+        TokenKind::FatArrow,
+        TokenKind::At,
+        TokenKind::ExclamationMark,
+        TokenKind::Plus,
+        TokenKind::Minus,
+        TokenKind::Slash,
+        TokenKind::Asterisk,
+        TokenKind::DoubleAsterisk,
+        TokenKind::BitAnd,
+        TokenKind::BitOr,
+        TokenKind::BitXor,
+        TokenKind::AmpAmp,
+        TokenKind::PipePipe,
+        TokenKind::DoubleEqual,
+        TokenKind::NotEqual,
+        TokenKind::LessThan,
+        TokenKind::GreaterThan,
+        TokenKind::LessThanOrEqual,
+        TokenKind::GreaterThanOrEqual,
+        TokenKind::ShiftLeft,
+        TokenKind::ShiftRight,
+        TokenKind::Compose,
+        TokenKind::Pipe,
+        TokenKind::Question,
+        TokenKind::QuestionQuestion,
+        TokenKind::QuestionDot,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexError {
+    InvalidEscape,
+    UnterminatedString,
+    InvalidCharLiteral,
+    UnexpectedCharacter,
+    MissingDigits,
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            LexError::InvalidEscape => "invalid escape sequence",
+            LexError::UnterminatedString => "unterminated string literal",
+            LexError::InvalidCharLiteral => "invalid char literal",
+            LexError::UnexpectedCharacter => "unexpected character",
+            LexError::MissingDigits => "missing digits after the base prefix",
+        };
+        f.write_str(str)
+    }
+}
+
 impl Display for TokenKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
@@ -52,31 +160,67 @@ impl Display for TokenKind {
             TokenKind::Unit => "unit",
             TokenKind::True => "true",
             TokenKind::False => "false",
+            TokenKind::Impl => "impl",
+            TokenKind::SelfValue => "self",
+            TokenKind::Match => "match",
+            TokenKind::While => "while",
+            TokenKind::For => "for",
+            TokenKind::In => "in",
+            TokenKind::Loop => "loop",
+            TokenKind::Break => "break",
+            TokenKind::Continue => "continue",
+            TokenKind::Return => "return",
+            TokenKind::Operator => "operator",
+            TokenKind::Struct => "struct",
+            TokenKind::Enum => "enum",
             TokenKind::LParen => "(",
             TokenKind::RParen => ")",
             TokenKind::LBrace => "{",
             TokenKind::RBrace => "}",
+            TokenKind::LBracket => "[",
+            TokenKind::RBracket => "]",
             TokenKind::Colon => ":",
             TokenKind::Comma => ",",
+            TokenKind::Dot => ".",
+            TokenKind::Ellipsis => "...",
+            TokenKind::DotDot => "..",
+            TokenKind::DotDotEqual => "..=",
             TokenKind::Tilde => "~",
             TokenKind::Semicolon => ";",
             TokenKind::SingleEqual => "=",
+            TokenKind::FatArrow => "=>",
+            TokenKind::At => "@",
             TokenKind::ExclamationMark => "!",
             TokenKind::Plus => "+",
             TokenKind::Minus => "-",
             TokenKind::Slash => "/",
             TokenKind::Asterisk => "*",
+            TokenKind::DoubleAsterisk => "**",
             TokenKind::BitAnd => "&",
             TokenKind::BitOr => "|",
             TokenKind::BitXor => "^",
-            TokenKind::DoubleEqual => "=",
+            TokenKind::AmpAmp => "&&",
+            TokenKind::PipePipe => "||",
+            TokenKind::DoubleEqual => "==",
             TokenKind::NotEqual => "!=",
             TokenKind::LessThan => "<",
             TokenKind::GreaterThan => ">",
             TokenKind::LessThanOrEqual => "<=",
             TokenKind::GreaterThanOrEqual => ">=",
+            TokenKind::ShiftLeft => "<<",
+            TokenKind::ShiftRight => ">>",
+            TokenKind::Compose => "~>",
+            TokenKind::Pipe => "|>",
+            TokenKind::Question => "?",
+            TokenKind::QuestionQuestion => "??",
+            TokenKind::QuestionDot => "?.",
             TokenKind::Identifier => "identifier",
             TokenKind::IntLiteral => "integer literal",
+            TokenKind::FloatLiteral => "float literal",
+            TokenKind::StringLiteral => "string literal",
+            TokenKind::CharLiteral => "char literal",
+            TokenKind::DocComment => "doc comment",
+            TokenKind::Illegal(error) => return error.fmt(f),
             TokenKind::Eof => "EOF",
         };
         f.write_str(str)
@@ -1,9 +1,13 @@
 #![allow(dead_code)]
 
-mod checker;
-mod lexer;
-mod parser;
+pub mod cache;
+pub mod checker;
+pub mod conformance;
+pub mod highlight;
+pub mod lexer;
+pub mod parser;
 mod span;
+pub mod textmate;
 
 #[cfg(test)]
 mod tests;
@@ -1,3 +1,424 @@
-fn main() {
-    println!("Hello, world!");
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Read},
+    panic,
+    path::Path,
+    process::ExitCode,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tracing::{Subscriber, span::Id};
+use tracing_subscriber::{
+    Layer,
+    layer::{Context, SubscriberExt},
+    registry::{LookupSpan, Registry},
+};
+
+#[cfg(feature = "arbitrary")]
+use lovely::parser::arbitrary;
+use lovely::{
+    cache::{self, Cache},
+    checker::{Checker, Prelude},
+    conformance,
+    highlight::{Theme, highlight},
+    parser::{Parser, ast::Program, cfg::Config, dot, include, printer},
+    textmate,
+};
+
+const USAGE: &str = "usage: lovely check [--timings] [--no-cache] [--cfg key=value]...
+                    [--prelude <file>] <file>...
+       lovely build [--timings] [--no-cache] [--cfg key=value]...
+                    [--prelude <file>] <file>...
+       lovely -e <expression>
+       lovely conformance [--bless] <dir>
+       lovely emit --ast-dot <file>
+       lovely emit --textmate
+       lovely fmt --minify <file>
+       lovely highlight <file>
+       lovely fuzz-parse [--seed N] [--count N] [--depth N] [--width N]
+                         (with the `arbitrary` feature)
+
+a <file> of `-` is read from stdin, and `build` only checks until there's
+code generation";
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["check" | "build", ref args @ ..] => check(args),
+        ["-e", source] => check_expression(source),
+        ["conformance", dir] => run_conformance(dir, false),
+        ["conformance", "--bless", dir] => run_conformance(dir, true),
+        ["emit", "--ast-dot", path] => emit_ast_dot(path),
+        ["emit", "--textmate"] => {
+            print!("{}", textmate::grammar());
+            ExitCode::SUCCESS
+        }
+        ["fmt", "--minify", path] => minify(path),
+        ["highlight", path] => print_highlighted(path),
+        #[cfg(feature = "arbitrary")]
+        ["fuzz-parse", ref args @ ..] => fuzz_parse(args),
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Type checks each file that changed since it last checked cleanly,
+/// optionally printing how long each phase took, from its tracing span. Files don't depend on each
+/// other, so they're checked in parallel, but reported in the order given.
+fn check(args: &[&str]) -> ExitCode {
+    let timings = args.contains(&"--timings");
+    if timings
+        && let Err(err) =
+            tracing::subscriber::set_global_default(Registry::default().with(PhaseTimer))
+    {
+        eprintln!("couldn't time the phases: {err}");
+    }
+    let cache = (!args.contains(&"--no-cache")).then(|| Cache::new(cache::DEFAULT_DIR));
+    let mut config = Config::default();
+    let mut prelude = Prelude::default();
+    let mut paths = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--cfg" => match args.next().and_then(|option| option.split_once('=')) {
+                Some((key, value)) => config = config.with(key, value),
+                None => {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--prelude" => {
+                let Some(path) = args.next() else {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                };
+                let Some(source) = read_file(path) else {
+                    return ExitCode::FAILURE;
+                };
+                prelude = match Prelude::parse(&source) {
+                    Ok(prelude) => prelude,
+                    Err(err) => {
+                        eprintln!("{path}: {err}");
+                        return ExitCode::FAILURE;
+                    }
+                };
+            }
+            "--timings" | "--no-cache" => {}
+            arg if arg.starts_with('-') && arg != "-" => {
+                eprintln!("unknown option {arg}\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            path => paths.push(path),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    // a worker per core takes the next file until there are none left, and
+    // the results are put back in the order the files were given
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let handles = (0..workers.min(paths.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else {
+                            break results;
+                        };
+                        results.push((i, check_file(path, &config, &prelude, cache.as_ref())));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    let results = results.into_iter().map(|(_, result)| result);
+
+    let mut total = Duration::ZERO;
+    let mut failed = false;
+    for (path, result) in paths.iter().zip(results) {
+        match result {
+            Err(err) => {
+                for line in err.lines() {
+                    eprintln!("{path}: {line}");
+                }
+                failed = true;
+            }
+            Ok(None) if timings => println!("{path}\n  cached"),
+            Ok(None) => {}
+            Ok(Some(phases)) => {
+                for warning in &phases.warnings {
+                    eprintln!("{path}: {warning}");
+                }
+                if timings {
+                    println!("{path}");
+                    for (phase, duration) in &phases.durations {
+                        println!("  {phase:<7}{duration:>10.2?}");
+                    }
+                }
+                total += phases.durations.iter().map(|(_, duration)| *duration).sum();
+            }
+        }
+    }
+
+    if timings && paths.len() > 1 {
+        println!("total    {total:>10.2?}");
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+struct PhaseTimings {
+    /// How long each phase took, in the order they ran, if they were timed.
+    durations: Vec<(&'static str, Duration)>,
+    warnings: Vec<String>,
+}
+
+thread_local! {
+    /// The phases [`PhaseTimer`] timed on this thread since they were last taken.
+    static PHASES: RefCell<Vec<(&'static str, Duration)>> = const { RefCell::new(vec![]) };
+}
+
+/// Times the spans that aren't inside another one, which are the phases of
+/// checking a file rather than, say, parsing a file it includes. A file is
+/// checked on one thread, so the phases are kept for each thread.
+struct PhaseTimer;
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for PhaseTimer {
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && span.parent().is_none()
+        {
+            span.extensions_mut().insert(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let Some(start) = span.extensions_mut().remove::<Instant>() else {
+            return;
+        };
+        PHASES.with_borrow_mut(|phases| phases.push((span.name(), start.elapsed())));
+    }
+}
+
+/// Checks a file unless it's cached, returning how long each phase took, or
+/// what went wrong, a line for each error.
+fn check_file(
+    path: &str,
+    config: &Config,
+    prelude: &Prelude,
+    cache: Option<&Cache>,
+) -> Result<Option<PhaseTimings>, String> {
+    let source = read_source(path).map_err(|err| format!("couldn't read it: {err}"))?;
+    PHASES.with_borrow_mut(Vec::clear);
+
+    let mut program = Parser::new(&source)
+        .parse()
+        .map_err(|err| err.to_string())?;
+    let files =
+        include::resolve(&mut program, Path::new(path), config).map_err(|err| err.to_string())?;
+
+    // what gets checked depends on the configuration, the prelude and the
+    // files it pulls in as well
+    let mut cache_key = format!("{config}\n{prelude:?}\n{source}");
+    for (_, contents) in &files {
+        cache_key.push('\0');
+        cache_key.push_str(contents);
+    }
+    if cache.is_some_and(|cache| cache.contains(&cache_key)) {
+        return Ok(None);
+    }
+
+    // the prelude only fails to load over a type that isn't there, which
+    // doesn't take its checker to name
+    let mut checker = Checker::with_prelude(prelude)
+        .map_err(|err| format!("in the prelude: {}", err.display(&Checker::new())))?;
+    checker.check_program_recovering(&program);
+    if !checker.errors().is_empty() {
+        let errors = checker
+            .errors()
+            .iter()
+            .map(|err| err.display(&checker).to_string())
+            .collect::<Vec<_>>();
+        return Err(errors.join("\n"));
+    }
+    let warnings = checker
+        .warnings()
+        .iter()
+        .map(|warning| warning.display(&checker).to_string())
+        .collect::<Vec<_>>();
+
+    // files with warnings aren't cached, so the warnings show up every time
+    if warnings.is_empty()
+        && let Some(Err(err)) = cache.map(|cache| cache.insert(&cache_key))
+    {
+        eprintln!("couldn't cache {path}: {err}");
+    }
+    Ok(Some(PhaseTimings {
+        durations: PHASES.take(),
+        warnings,
+    }))
+}
+
+/// Checks the source of a program given on the command line and prints the
+/// type of its value, which is all there is to show of it until there's an
+/// evaluator.
+fn check_expression(source: &str) -> ExitCode {
+    let mut program = match Parser::new(source).parse() {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    Config::default().apply(&mut program);
+    let mut checker = Checker::new();
+    let checked = checker.check_program_recovering(&program);
+    for err in checker.errors() {
+        eprintln!("{}", err.display(&checker));
+    }
+    if !checker.errors().is_empty() {
+        return ExitCode::FAILURE;
+    }
+    for warning in checker.warnings() {
+        eprintln!("{}", warning.display(&checker));
+    }
+    println!("{}", checker.type_name(checked.type_id()));
+    ExitCode::SUCCESS
+}
+
+/// Runs the golden-file tests in `dir`, or updates their expected outcomes.
+fn run_conformance(dir: &str, bless: bool) -> ExitCode {
+    let mismatches = match conformance::run(Path::new(dir), bless) {
+        Ok(mismatches) => mismatches,
+        Err(err) => {
+            eprintln!("couldn't run the cases in {dir}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    for mismatch in &mismatches {
+        println!("{} doesn't match", mismatch.path.display());
+        println!(
+            "--- expected\n{}--- actual\n{}",
+            mismatch.expected, mismatch.actual
+        );
+    }
+    if mismatches.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn emit_ast_dot(path: &str) -> ExitCode {
+    let Some(program) = parse_file(path) else {
+        return ExitCode::FAILURE;
+    };
+    print!("{}", dot::program_to_dot(&program));
+    ExitCode::SUCCESS
+}
+
+fn minify(path: &str) -> ExitCode {
+    let Some(program) = parse_file(path) else {
+        return ExitCode::FAILURE;
+    };
+    println!("{}", printer::program_to_minified_source(&program));
+    ExitCode::SUCCESS
+}
+
+fn print_highlighted(path: &str) -> ExitCode {
+    let Some(source) = read_file(path) else {
+        return ExitCode::FAILURE;
+    };
+    print!("{}", highlight(&source, &Theme::default()));
+    ExitCode::SUCCESS
+}
+
+/// Parses generated programs until the parser panics or rejects one.
+#[cfg(feature = "arbitrary")]
+fn fuzz_parse(args: &[&str]) -> ExitCode {
+    let mut options = [
+        ("--seed", 0),
+        ("--count", 1000),
+        ("--depth", 5),
+        ("--width", 3),
+    ];
+    for pair in args.chunks(2) {
+        let option = options.iter_mut().find(|(name, _)| *name == pair[0]);
+        match (option, pair.get(1).and_then(|value| value.parse().ok())) {
+            (Some((_, option)), Some(value)) => *option = value,
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let [(_, seed), (_, count), (_, depth), (_, width)] = options;
+
+    // panics are reported with the source that caused them instead
+    std::panic::set_hook(Box::new(|_| {}));
+    for seed in seed..seed + count {
+        if let Err(failure) = arbitrary::fuzz_parse(seed, depth as usize, width as usize) {
+            eprintln!("seed {seed}: {}", failure.problem);
+            eprintln!("{}", failure.source);
+            return ExitCode::FAILURE;
+        }
+    }
+    println!("parsed {count} programs");
+    ExitCode::SUCCESS
+}
+
+/// Reads the file at `path`, or stdin if it's `-`.
+fn read_source(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        return Ok(source);
+    }
+    fs::read_to_string(path)
+}
+
+fn read_file(path: &str) -> Option<String> {
+    match read_source(path) {
+        Ok(source) => Some(source),
+        Err(err) => {
+            eprintln!("couldn't read {path}: {err}");
+            None
+        }
+    }
+}
+
+fn parse_file(path: &str) -> Option<Program> {
+    let source = read_file(path)?;
+    match Parser::new(&source).parse() {
+        Ok(program) => Some(program),
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            None
+        }
+    }
 }
@@ -0,0 +1,667 @@
+//! Random programs for property tests and fuzzing, behind the `arbitrary`
+//! feature.
+//!
+//! Generated programs always parse, but they aren't type checked, so they're
+//! mostly nonsense to the checker. Every span is empty, like in programs read
+//! back from [`sexp`](super::sexp).
+
+use super::ast::{
+    Attribute, EnumVariant, Expression, ExpressionKind, ExpressionStatement, FunctionArgument,
+    FunctionParameter, InfixOperator, MatchArm, Method, Pattern, PatternKind, PrefixOperator,
+    Program, StructField, Type, TypeParameter, VariantField, VariantFieldPattern,
+};
+use super::{
+    Error, Parser,
+    printer::{self, program_to_minified_source},
+};
+use crate::{lexer::INT_SUFFIXES, span::Span};
+use std::panic;
+
+const NAMES: [&str; 6] = ["a", "b", "c", "r", "x", "y"];
+// kept apart from the names, since an identifier after `break` is read as a
+// label if it names an enclosing loop
+const LABELS: [&str; 2] = ["outer", "inner"];
+const TYPES: [&str; 4] = ["Int", "Bool", "Unit", "T"];
+const ATTRIBUTES: [&str; 3] = ["inline", "test", "deprecated"];
+const CHARS: [char; 8] = ['a', 'Z', '0', ' ', '"', '\'', '\\', '\n'];
+
+const INFIX_OPERATORS: [InfixOperator; 18] = [
+    InfixOperator::Plus,
+    InfixOperator::Minus,
+    InfixOperator::Divide,
+    InfixOperator::Multiply,
+    InfixOperator::Power,
+    InfixOperator::Equal,
+    InfixOperator::NotEqual,
+    InfixOperator::LessThan,
+    InfixOperator::GreaterThan,
+    InfixOperator::LessThanOrEqual,
+    InfixOperator::GreaterThanOrEqual,
+    InfixOperator::ShiftLeft,
+    InfixOperator::ShiftRight,
+    InfixOperator::And,
+    InfixOperator::Or,
+    InfixOperator::Compose,
+    InfixOperator::Pipe,
+    InfixOperator::Coalesce,
+];
+const COMPARISON_OPERATORS: [InfixOperator; 4] = [
+    InfixOperator::LessThan,
+    InfixOperator::GreaterThan,
+    InfixOperator::LessThanOrEqual,
+    InfixOperator::GreaterThanOrEqual,
+];
+
+/// Generates programs from a seed, so a failing case can be reproduced.
+pub struct Generator {
+    state: u64,
+    /// How many more levels of nesting are allowed.
+    depth: usize,
+    /// The most statements, arguments, arms and so on in one place.
+    width: usize,
+    /// The loops around the expression being generated, innermost last.
+    loops: Vec<Option<String>>,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            depth: 0,
+            width: 3,
+            loops: vec![],
+        }
+    }
+
+    /// A program whose expressions are nested at most `depth` levels deep,
+    /// with at most `width` statements in a block, arguments in a call, etc.
+    pub fn program(&mut self, depth: usize, width: usize) -> Program {
+        self.depth = depth;
+        self.width = width;
+        Program(self.statements())
+    }
+
+    pub fn expression(&mut self) -> Expression {
+        if self.depth == 0 || self.below(4) == 0 {
+            return self.leaf();
+        }
+        self.depth -= 1;
+        let kind = self.compound();
+        self.depth += 1;
+        Expression::new(kind, no_span())
+    }
+
+    fn compound(&mut self) -> ExpressionKind {
+        match self.below(24) {
+            0 => ExpressionKind::Prefix {
+                operator: self.pick(&[PrefixOperator::LogicalNot, PrefixOperator::Negative]),
+                expression: Box::new(self.expression()),
+            },
+            1 => ExpressionKind::Infix {
+                left: Box::new(self.expression()),
+                operator: self.pick(&INFIX_OPERATORS),
+                right: Box::new(self.expression()),
+            },
+            2 => {
+                let operators = (0..2 + self.below(2))
+                    .map(|_| self.pick(&COMPARISON_OPERATORS))
+                    .collect::<Vec<_>>();
+                ExpressionKind::ChainedComparison {
+                    operands: (0..=operators.len()).map(|_| self.expression()).collect(),
+                    operators,
+                }
+            }
+            3 => ExpressionKind::VariableDecl {
+                name: self.name(),
+                value: Box::new(self.expression()),
+                mutable: self.below(2) == 0,
+                ty: self.maybe(|g| (g.ty(), no_span())),
+                separator: no_span(),
+                // minified source leaves out doc comments
+                doc: None,
+                attributes: vec![],
+            },
+            4 => self.function(),
+            5 => ExpressionKind::FunctionCall {
+                name: self.name(),
+                arguments: self.arguments(),
+            },
+            6 => match self.below(3) {
+                0 => ExpressionKind::SafeFieldAccess {
+                    target: Box::new(self.expression()),
+                    field: self.name(),
+                },
+                _ => ExpressionKind::FieldAccess {
+                    target: Box::new(self.expression()),
+                    field: match self.below(2) {
+                        0 => self.name(),
+                        _ => self.below(3).to_string(),
+                    },
+                },
+            },
+            7 => ExpressionKind::MethodCall {
+                receiver: Box::new(self.expression()),
+                method: self.name(),
+                arguments: self.arguments(),
+            },
+            8 => match self.below(3) {
+                0 => ExpressionKind::Try(Box::new(self.expression())),
+                _ => ExpressionKind::Index {
+                    target: Box::new(self.expression()),
+                    index: Box::new(self.expression()),
+                },
+            },
+            9 => ExpressionKind::Impl {
+                ty: self.ty(),
+                methods: self.some(|generator| Method {
+                    name: generator.name(),
+                    function: Expression::new(generator.function(), no_span()),
+                    doc: None,
+                    attributes: generator.attributes(),
+                }),
+            },
+            10 => ExpressionKind::Match {
+                value: Box::new(self.expression()),
+                arms: self.some(|generator| MatchArm {
+                    pattern: generator.pattern(2),
+                    body: generator.expression(),
+                }),
+            },
+            11 => {
+                let label = self.maybe(|generator| generator.pick(&LABELS).to_string());
+                let condition = Box::new(self.expression());
+                let body = self.loop_body(label.clone());
+                ExpressionKind::While {
+                    label,
+                    condition,
+                    body,
+                }
+            }
+            12 => {
+                let label = self.maybe(|generator| generator.pick(&LABELS).to_string());
+                let body = self.loop_body(label.clone());
+                ExpressionKind::Loop { label, body }
+            }
+            13 => ExpressionKind::Call {
+                callee: Box::new(self.expression()),
+                arguments: self.arguments(),
+            },
+            14 => {
+                let label = self.maybe(|generator| generator.pick(&LABELS).to_string());
+                let pattern = Box::new(self.pattern(2));
+                let iterable = Box::new(self.expression());
+                let body = self.loop_body(label.clone());
+                ExpressionKind::For {
+                    label,
+                    pattern,
+                    iterable,
+                    body,
+                }
+            }
+            15 => ExpressionKind::Range {
+                start: Box::new(self.expression()),
+                end: Box::new(self.expression()),
+                inclusive: self.below(2) == 0,
+            },
+            16 => ExpressionKind::Tuple(
+                (0..1 + self.below(self.width))
+                    .map(|_| self.expression())
+                    .collect(),
+            ),
+            17 => {
+                let mut fields = self.some(|generator| StructField {
+                    name: generator.name(),
+                    ty: generator.ty(),
+                    span: no_span(),
+                });
+                let mut names = vec![];
+                fields.retain(|field| {
+                    let unique = !names.contains(&field.name);
+                    names.push(field.name.clone());
+                    unique
+                });
+                ExpressionKind::Struct {
+                    name: self.pick(&TYPES).to_string(),
+                    fields,
+                    doc: None,
+                    attributes: vec![],
+                }
+            }
+            18 => {
+                let mut variants = self.some(|generator| EnumVariant {
+                    name: generator.name(),
+                    fields: generator.some(|generator| VariantField {
+                        label: generator.maybe(Generator::name),
+                        ty: generator.ty(),
+                    }),
+                    span: no_span(),
+                });
+                let mut names = vec![];
+                variants.retain(|variant| {
+                    let unique = !names.contains(&variant.name);
+                    names.push(variant.name.clone());
+                    unique
+                });
+                for variant in &mut variants {
+                    let mut labels = vec![];
+                    for field in &mut variant.fields {
+                        if labels.contains(&field.label) {
+                            field.label = None;
+                        }
+                        labels.push(field.label.clone());
+                    }
+                }
+                ExpressionKind::Enum {
+                    name: self.pick(&TYPES).to_string(),
+                    variants,
+                    doc: None,
+                    attributes: vec![],
+                }
+            }
+            19 => {
+                // only variables and fields can be assigned to
+                let target = match self.below(2) {
+                    0 => ExpressionKind::Ident(self.name()),
+                    _ => ExpressionKind::FieldAccess {
+                        target: Box::new(self.expression()),
+                        field: self.name(),
+                    },
+                };
+                ExpressionKind::Assign {
+                    target: Box::new(Expression::new(target, no_span())),
+                    value: Box::new(self.expression()),
+                }
+            }
+            20 => ExpressionKind::Block(self.statements()),
+            21 => ExpressionKind::Return(self.maybe(|generator| Box::new(generator.expression()))),
+            _ if self.loops.is_empty() => self.leaf().kind,
+            _ => {
+                let labels = self.loops.iter().flatten().cloned().collect::<Vec<_>>();
+                let label = if labels.is_empty() {
+                    None
+                } else {
+                    self.maybe(|generator| generator.pick(&labels))
+                };
+                if self.below(2) == 0 {
+                    ExpressionKind::Continue { label }
+                } else {
+                    ExpressionKind::Break {
+                        label,
+                        value: self.maybe(|generator| Box::new(generator.expression())),
+                    }
+                }
+            }
+        }
+    }
+
+    fn leaf(&mut self) -> Expression {
+        let kind = match self.below(11) {
+            0 => ExpressionKind::Unit,
+            1..=6 => return self.literal(),
+            7 => ExpressionKind::Ident("self".to_string()),
+            8 => ExpressionKind::EmbedText(self.file_name()),
+            _ => ExpressionKind::Ident(self.name()),
+        };
+        Expression::new(kind, no_span())
+    }
+
+    fn literal(&mut self) -> Expression {
+        let kind = match self.below(6) {
+            0 => ExpressionKind::BoolLiteral(self.below(2) == 0),
+            1 => ExpressionKind::IntLiteral(self.int()),
+            2 => ExpressionKind::SuffixedIntLiteral {
+                value: self.int(),
+                suffix: self.pick(&INT_SUFFIXES).to_string(),
+            },
+            3 => ExpressionKind::StringLiteral(
+                (0..self.below(4)).map(|_| self.pick(&CHARS)).collect(),
+            ),
+            4 => ExpressionKind::CharLiteral(self.pick(&CHARS)),
+            _ => ExpressionKind::FloatLiteral(self.int() as f64 / 8.0),
+        };
+        Expression::new(kind, no_span())
+    }
+
+    fn attributes(&mut self) -> Vec<Attribute> {
+        self.some(|generator| Attribute {
+            name: generator.pick(&ATTRIBUTES).to_string(),
+            arguments: generator.some(Generator::literal),
+            span: no_span(),
+        })
+    }
+
+    fn statements(&mut self) -> Vec<ExpressionStatement> {
+        let mut stmts = self.some(|generator| {
+            let mut expr = match generator.below(16) {
+                0 => Expression::new(ExpressionKind::Include(generator.file_name()), no_span()),
+                _ => generator.expression(),
+            };
+            // attributes only go in front of declarations of their own
+            if let ExpressionKind::VariableDecl { attributes, .. }
+            | ExpressionKind::Struct { attributes, .. }
+            | ExpressionKind::Enum { attributes, .. } = &mut expr.kind
+            {
+                *attributes = generator.attributes();
+            }
+            // now and then behind a `@cfg` attribute
+            if generator.below(8) == 0 {
+                let options = (0..generator.below(3))
+                    .map(|_| {
+                        let key = generator.name();
+                        let value = (0..generator.below(4)).map(|_| generator.pick(&CHARS));
+                        (key, value.collect())
+                    })
+                    .collect();
+                expr = Expression::new(
+                    ExpressionKind::Cfg {
+                        options,
+                        expr: Box::new(expr),
+                    },
+                    no_span(),
+                );
+            }
+            ExpressionStatement {
+                expr,
+                discarded: generator.below(2) == 0,
+            }
+        });
+        // the parser only keeps a statement that isn't discarded apart from
+        // the next one if it doesn't run into it
+        let sources = stmts
+            .iter()
+            .map(|stmt| printer::source(&stmt.expr))
+            .collect::<Vec<_>>();
+        for (i, pair) in sources.windows(2).enumerate() {
+            if printer::runs_into(&stmts[i].expr, &pair[0], &pair[1]) {
+                stmts[i].discarded = true;
+            }
+        }
+        stmts
+    }
+
+    fn loop_body(&mut self, label: Option<String>) -> Vec<ExpressionStatement> {
+        self.loops.push(label);
+        let body = self.statements();
+        self.loops.pop();
+        body
+    }
+
+    fn function(&mut self) -> ExpressionKind {
+        // `break` and `continue` can't reach loops outside the function
+        let loops = std::mem::take(&mut self.loops);
+        let function = ExpressionKind::Function {
+            type_parameters: self.some(|generator| TypeParameter {
+                name: generator.pick(&TYPES).to_string(),
+                bounds: generator.some(|generator| (generator.pick(&TYPES).to_string(), no_span())),
+                span: no_span(),
+            }),
+            parameters: self.parameters(),
+            return_type: self.maybe(Generator::ty),
+            body: self.statements(),
+        };
+        self.loops = loops;
+        function
+    }
+
+    /// Parameters with different names, which the parser insists on.
+    fn parameters(&mut self) -> Vec<FunctionParameter> {
+        let mut parameters = self.some(Generator::parameter);
+        if self.below(4) == 0 {
+            parameters.push(FunctionParameter::Rest {
+                name: self.name(),
+                ty: self.maybe(Generator::ty),
+            });
+        }
+        let mut names = vec![];
+        let mut labels = vec![];
+        parameters.retain(|parameter| match parameter {
+            FunctionParameter::LabeledAtCallsite {
+                internal_name,
+                external_name,
+                ..
+            } => {
+                let label = external_name.as_ref().unwrap_or(internal_name);
+                if names.contains(internal_name) || labels.contains(label) {
+                    return false;
+                }
+                names.push(internal_name.clone());
+                labels.push(label.clone());
+                true
+            }
+            FunctionParameter::UnlabeledAtCallsite { name, .. }
+            | FunctionParameter::Rest { name, .. } => {
+                if names.contains(name) {
+                    return false;
+                }
+                names.push(name.clone());
+                true
+            }
+            FunctionParameter::Pattern { pattern, .. } => {
+                let bindings = pattern.bindings();
+                let duplicate = bindings.iter().enumerate().any(|(i, (name, _))| {
+                    names.iter().any(|other| other == name)
+                        || bindings[..i].iter().any(|(other, _)| other == name)
+                });
+                if duplicate {
+                    return false;
+                }
+                names.extend(bindings.iter().map(|(name, _)| name.to_string()));
+                true
+            }
+            FunctionParameter::SelfParameter => true,
+        });
+        parameters
+    }
+
+    fn parameter(&mut self) -> FunctionParameter {
+        match self.below(4) {
+            0 => FunctionParameter::SelfParameter,
+            1 => FunctionParameter::UnlabeledAtCallsite {
+                name: self.name(),
+                ty: self.maybe(Generator::ty),
+            },
+            2 => FunctionParameter::Pattern {
+                pattern: self.pattern(1),
+                ty: self.maybe(Generator::ty),
+            },
+            _ => FunctionParameter::LabeledAtCallsite {
+                internal_name: self.name(),
+                external_name: self.maybe(Generator::name),
+                ty: self.maybe(Generator::ty),
+                label_span: no_span(),
+            },
+        }
+    }
+
+    fn arguments(&mut self) -> Vec<FunctionArgument> {
+        self.some(|generator| {
+            let spread = generator.below(4) == 0;
+            FunctionArgument {
+                label: if spread {
+                    None
+                } else {
+                    generator.maybe(Generator::name)
+                },
+                value: generator.expression(),
+                spread,
+            }
+        })
+    }
+
+    fn pattern(&mut self, depth: usize) -> Pattern {
+        let kind = match self.below(if depth == 0 { 5 } else { 7 }) {
+            0 => PatternKind::Wildcard,
+            1 => PatternKind::Binding(self.name()),
+            2 => PatternKind::Unit,
+            3 => PatternKind::BoolLiteral(self.below(2) == 0),
+            4 => PatternKind::IntLiteral(i128::from(self.int()) - 50),
+            5 => PatternKind::Tuple(
+                (0..1 + self.below(self.width))
+                    .map(|_| self.pattern(depth - 1))
+                    .collect(),
+            ),
+            _ => PatternKind::Variant {
+                ty: self.pick(&TYPES).to_string(),
+                variant: self.pick(&TYPES).to_string(),
+                fields: self.some(|generator| VariantFieldPattern {
+                    label: generator.maybe(Generator::name),
+                    pattern: generator.pattern(depth - 1),
+                }),
+            },
+        };
+        Pattern::new(kind, no_span())
+    }
+
+    fn ty(&mut self) -> Type {
+        if self.below(8) == 0 {
+            return Type::Function {
+                parameters: self.some(Generator::ty).into(),
+                return_type: self.maybe(|generator| Box::new(generator.ty())),
+            };
+        }
+        if self.below(8) == 0 {
+            return Type::Applied {
+                name: self.pick(&TYPES).to_string(),
+                arguments: (0..1 + self.below(self.width)).map(|_| self.ty()).collect(),
+            };
+        }
+        if self.below(4) == 0 {
+            let elements = 1 + self.below(self.width);
+            return Type::Tuple(
+                (0..elements)
+                    .map(|_| Type::Ident(self.pick(&TYPES).to_string()))
+                    .collect(),
+            );
+        }
+        Type::Ident(self.pick(&TYPES).to_string())
+    }
+
+    fn name(&mut self) -> String {
+        self.pick(&NAMES).to_string()
+    }
+
+    fn file_name(&mut self) -> String {
+        format!("{}.lv", self.name())
+    }
+
+    fn int(&mut self) -> u64 {
+        self.below(100) as u64
+    }
+
+    /// Up to `width` things.
+    fn some<T>(&mut self, mut item: impl FnMut(&mut Self) -> T) -> Vec<T> {
+        (0..self.below(self.width + 1))
+            .map(|_| item(self))
+            .collect()
+    }
+
+    fn maybe<T>(&mut self, item: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        (self.below(2) == 0).then(|| item(self))
+    }
+
+    fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())].clone()
+    }
+
+    /// A number in `0..n`, from splitmix64.
+    fn below(&mut self, n: usize) -> usize {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        ((z ^ (z >> 31)) % n as u64) as usize
+    }
+}
+
+/// Source the parser panicked on, or rejected even though it should parse.
+#[derive(Debug)]
+pub struct FuzzFailure {
+    pub source: String,
+    pub problem: String,
+}
+
+/// Parses the minified source of a generated program, then a few broken
+/// versions of it, which the parser has to reject without panicking.
+pub fn fuzz_parse(seed: u64, depth: usize, width: usize) -> Result<(), FuzzFailure> {
+    let mut generator = Generator::new(seed);
+    let source = program_to_minified_source(&generator.program(depth, width));
+    if let Err(err) = try_parse(&source)? {
+        return Err(FuzzFailure {
+            problem: format!("didn't parse: {err:?}"),
+            source,
+        });
+    }
+
+    let boundaries = source
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([source.len()])
+        .collect::<Vec<_>>();
+    for _ in 0..8 {
+        let i = boundaries[generator.below(boundaries.len())];
+        let broken = if generator.below(2) == 0 || i == source.len() {
+            source[..i].to_string()
+        } else {
+            let next = boundaries.iter().find(|&&j| j > i).unwrap();
+            format!("{}{}", &source[..i], &source[*next..])
+        };
+        // broken source is allowed to fail, just not to panic
+        let _ = try_parse(&broken)?;
+    }
+    Ok(())
+}
+
+fn try_parse(source: &str) -> Result<Result<Program, Error>, FuzzFailure> {
+    panic::catch_unwind(|| Parser::new(source).parse()).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        FuzzFailure {
+            source: source.to_string(),
+            problem: format!("panicked: {message}"),
+        }
+    })
+}
+
+fn no_span() -> Span {
+    Span::from_range(0, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::sexp::program_to_sexp;
+
+    /// The dump on one line.
+    fn flat_sexp(program: &Program) -> String {
+        let sexp = program_to_sexp(program);
+        sexp.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn minified_programs_parse_back() {
+        for seed in 0..500 {
+            let program = Generator::new(seed).program(4, 3);
+            let source = program_to_minified_source(&program);
+            let reparsed = Parser::new(&source)
+                .parse()
+                .unwrap_or_else(|err| panic!("seed {seed}: {err:?} in {source}"));
+
+            assert_eq!(program_to_minified_source(&reparsed), source, "seed {seed}");
+            assert_eq!(
+                flat_sexp(&reparsed),
+                flat_sexp(&program),
+                "seed {seed}: {source}"
+            );
+        }
+    }
+
+    #[test]
+    fn fuzzing_the_parser() {
+        for seed in 0..200 {
+            fuzz_parse(seed, 4, 3).unwrap();
+        }
+    }
+}
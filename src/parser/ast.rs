@@ -1,15 +1,17 @@
+use std::fmt::Display;
+
 use crate::span::Span;
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Debug)]
 pub struct Program(pub Vec<ExpressionStatement>);
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Debug)]
 pub struct ExpressionStatement {
     pub expr: Expression,
     pub discarded: bool,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Debug)]
 pub struct Expression {
     pub span: Span,
     pub kind: ExpressionKind,
@@ -20,11 +22,20 @@ impl Expression {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Debug)]
 pub enum ExpressionKind {
     Unit,
     BoolLiteral(bool),
-    IntLiteral(isize),
+    /// Whether the value fits is up to the type the checker gives it.
+    IntLiteral(u64),
+    /// `255u8`, where the suffix is the name of a sized int type.
+    SuffixedIntLiteral {
+        value: u64,
+        suffix: String,
+    },
+    FloatLiteral(f64),
+    StringLiteral(String),
+    CharLiteral(char),
     Ident(String),
 
     Prefix {
@@ -36,15 +47,50 @@ pub enum ExpressionKind {
         operator: InfixOperator,
         right: Box<Expression>,
     },
+    /// `a <+> b`, with an operator declared earlier in the program.
+    CustomInfix {
+        left: Box<Expression>,
+        operator: CustomOperator,
+        right: Box<Expression>,
+    },
+    /// `a < b <= c`, which means `a < b && b <= c` with `b` evaluated once.
+    ChainedComparison {
+        operands: Vec<Expression>,
+        operators: Vec<InfixOperator>,
+    },
+    /// `(a, b)`, or `(a,)` with one element.
+    Tuple(Vec<Expression>),
+    /// `start..end`, or `start..=end` to include `end`.
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+        inclusive: bool,
+    },
 
     VariableDecl {
         name: String,
         value: Box<Expression>,
         mutable: bool,
-        ty: Option<Type>,
+        /// The type the variable is annotated with, if any, and its span.
+        ty: Option<(Type, Span)>,
+        /// The span of the `:` or `=` right before the value, which makes the
+        /// variable mutable when it's `=`.
+        separator: Span,
+        /// The `///` comment right before the declaration, if there is one.
+        doc: Option<String>,
+        attributes: Vec<Attribute>,
+    },
+    /// `{ a; b }`, whose value is that of its last statement, or `unit` if
+    /// that one's discarded.
+    Block(Vec<ExpressionStatement>),
+    /// `target = value`, where the target is a variable, a field or an index.
+    Assign {
+        target: Box<Expression>,
+        value: Box<Expression>,
     },
 
     Function {
+        type_parameters: Vec<TypeParameter>,
         parameters: Vec<FunctionParameter>,
         return_type: Option<Type>,
         body: Vec<ExpressionStatement>,
@@ -54,6 +100,101 @@ pub enum ExpressionKind {
         name: String,
         arguments: Vec<FunctionArgument>,
     },
+    /// A call of anything but a name, like `f()(x)` or `fs[i](x)`.
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<FunctionArgument>,
+    },
+    FieldAccess {
+        target: Box<Expression>,
+        field: String,
+    },
+    /// `target?.field`, which is `none` when `target` is.
+    SafeFieldAccess {
+        target: Box<Expression>,
+        field: String,
+    },
+    MethodCall {
+        receiver: Box<Expression>,
+        method: String,
+        arguments: Vec<FunctionArgument>,
+    },
+    /// `target[index]`, which is a slice when `index` is a range.
+    Index {
+        target: Box<Expression>,
+        index: Box<Expression>,
+    },
+    /// `value?`, which returns the error from the function when `value` is one.
+    Try(Box<Expression>),
+
+    Impl {
+        ty: Type,
+        methods: Vec<Method>,
+    },
+    /// `struct Point { x: Int, y: Int }`, which declares a type that's made
+    /// like a function call with a labeled argument for each field:
+    /// `Point(x: 1, y: 2)`.
+    Struct {
+        name: String,
+        fields: Vec<StructField>,
+        doc: Option<String>,
+        attributes: Vec<Attribute>,
+    },
+    /// `enum Shape { Circle(radius: Float), Empty }`, whose values are made
+    /// like `Shape.Circle(radius: 1.0)` and `Shape.Empty`.
+    Enum {
+        name: String,
+        variants: Vec<EnumVariant>,
+        doc: Option<String>,
+        attributes: Vec<Attribute>,
+    },
+    /// `operator <+> (precedence: 6, assoc: left, method: combine)`
+    OperatorDecl(CustomOperator),
+
+    Match {
+        value: Box<Expression>,
+        arms: Vec<MatchArm>,
+    },
+
+    While {
+        label: Option<String>,
+        condition: Box<Expression>,
+        body: Vec<ExpressionStatement>,
+    },
+    Loop {
+        label: Option<String>,
+        body: Vec<ExpressionStatement>,
+    },
+    /// `for pattern in iterable { body }`, running the body with the pattern
+    /// matched against each element.
+    For {
+        label: Option<String>,
+        pattern: Box<Pattern>,
+        iterable: Box<Expression>,
+        body: Vec<ExpressionStatement>,
+    },
+    Break {
+        label: Option<String>,
+        value: Option<Box<Expression>>,
+    },
+    Continue {
+        label: Option<String>,
+    },
+    /// `return value`, or just `return` to return `unit`.
+    Return(Option<Box<Expression>>),
+
+    /// `@cfg(target: "wasm") expr`, a statement that's only kept when the
+    /// configuration has every one of the options. Only ever a statement.
+    Cfg {
+        options: Vec<(String, String)>,
+        expr: Box<Expression>,
+    },
+    /// `include "other.lv"`, a statement that's replaced by the statements of
+    /// the other file before checking.
+    Include(String),
+    /// `embed_text "data.txt"`, which is replaced by the contents of the file
+    /// as a string literal before checking.
+    EmbedText(String),
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
@@ -68,18 +209,123 @@ pub enum InfixOperator {
     Minus,
     Divide,
     Multiply,
+    Power,
     Equal,
     NotEqual,
     LessThan,
     GreaterThan,
     LessThanOrEqual,
     GreaterThanOrEqual,
+    ShiftLeft,
+    ShiftRight,
+    And,
+    Or,
+    Compose,
+    Pipe,
+    Coalesce,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+impl InfixOperator {
+    pub fn precedence(self) -> Precedence {
+        match self {
+            InfixOperator::Pipe => Precedence::Pipeline,
+            InfixOperator::Compose => Precedence::Composition,
+            InfixOperator::Coalesce => Precedence::Coalesce,
+            InfixOperator::Or => Precedence::Or,
+            InfixOperator::And => Precedence::And,
+            InfixOperator::Equal | InfixOperator::NotEqual => Precedence::Equality,
+            InfixOperator::LessThan
+            | InfixOperator::GreaterThan
+            | InfixOperator::LessThanOrEqual
+            | InfixOperator::GreaterThanOrEqual => Precedence::Comparison,
+            InfixOperator::ShiftLeft | InfixOperator::ShiftRight => Precedence::Shift,
+            InfixOperator::Plus | InfixOperator::Minus => Precedence::Sum,
+            InfixOperator::Multiply | InfixOperator::Divide => Precedence::Product,
+            InfixOperator::Power => Precedence::Power,
+        }
+    }
+
+    pub fn associativity(self) -> Associativity {
+        match self {
+            InfixOperator::Power | InfixOperator::Coalesce => Associativity::Right,
+            _ => Associativity::Left,
+        }
+    }
+}
+
+impl Display for PrefixOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            PrefixOperator::LogicalNot => "!",
+            PrefixOperator::Negative => "-",
+        };
+        f.write_str(str)
+    }
+}
+
+impl Display for InfixOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            InfixOperator::Plus => "+",
+            InfixOperator::Minus => "-",
+            InfixOperator::Divide => "/",
+            InfixOperator::Multiply => "*",
+            InfixOperator::Power => "**",
+            InfixOperator::Equal => "==",
+            InfixOperator::NotEqual => "!=",
+            InfixOperator::LessThan => "<",
+            InfixOperator::GreaterThan => ">",
+            InfixOperator::LessThanOrEqual => "<=",
+            InfixOperator::GreaterThanOrEqual => ">=",
+            InfixOperator::ShiftLeft => "<<",
+            InfixOperator::ShiftRight => ">>",
+            InfixOperator::And => "&&",
+            InfixOperator::Or => "||",
+            InfixOperator::Compose => "~>",
+            InfixOperator::Pipe => "|>",
+            InfixOperator::Coalesce => "??",
+        };
+        f.write_str(str)
+    }
+}
+
+/// An infix operator declared in the program, which applies a method of its left operand.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CustomOperator {
+    pub symbol: String,
+    /// From 1 for binding as loosely as `~>` to 6 for binding as tightly as `*`.
+    pub precedence: u8,
+    pub associativity: Associativity,
+    pub method: Option<String>,
+}
+
+impl CustomOperator {
+    pub fn precedence(&self) -> Precedence {
+        Precedence::from_level(self.precedence)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+impl Display for Associativity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Associativity::Left => "left",
+            Associativity::Right => "right",
+        })
+    }
+}
+
+#[derive(PartialEq, Debug)]
 pub struct FunctionArgument {
     pub label: Option<String>,
     pub value: Expression,
+    /// `...value`, passing each element of `value` as its own argument.
+    pub spread: bool,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -87,15 +333,183 @@ pub enum FunctionParameter {
     LabeledAtCallsite {
         internal_name: String,
         external_name: Option<String>,
-        ty: Type,
+        ty: Option<Type>,
+        /// The span of the label, which is the name unless there's an
+        /// external one.
+        label_span: Span,
     },
     UnlabeledAtCallsite {
         name: String,
-        ty: Type,
+        ty: Option<Type>,
+    },
+    /// `...rest`, the last parameter, which takes the unlabeled arguments
+    /// left over after the others.
+    Rest {
+        name: String,
+        ty: Option<Type>,
+    },
+    /// `(x, y)`, which takes an unlabeled argument apart instead of naming it.
+    Pattern {
+        pattern: Pattern,
+        ty: Option<Type>,
+    },
+    SelfParameter,
+}
+
+/// `@inline` or `@deprecated("use g")` in front of a declaration, which the
+/// parser keeps for later passes to look for. Its arguments are literals.
+#[derive(PartialEq, Debug)]
+pub struct Attribute {
+    pub name: String,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
+}
+
+/// A field of a struct declaration, whose span is the span of its name.
+#[derive(PartialEq, Eq, Debug)]
+pub struct StructField {
+    pub name: String,
+    pub ty: Type,
+    pub span: Span,
+}
+
+/// A variant of an enum declaration, whose span is the span of its name.
+#[derive(PartialEq, Eq, Debug)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<VariantField>,
+    pub span: Span,
+}
+
+/// A field of an enum variant. A labeled one has to be passed with its label
+/// when the variant is made, like a labeled parameter.
+#[derive(PartialEq, Eq, Debug)]
+pub struct VariantField {
+    pub label: Option<String>,
+    pub ty: Type,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct TypeParameter {
+    pub name: String,
+    /// The names of the bounds, like `Comparable` in `T: Comparable`, with
+    /// their spans.
+    pub bounds: Vec<(String, Span)>,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Method {
+    pub name: String,
+    pub function: Expression,
+    pub doc: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expression,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Pattern {
+    pub span: Span,
+    pub kind: PatternKind,
+}
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self { span, kind }
+    }
+
+    /// The names the pattern binds, along with the spans of the bindings.
+    pub fn bindings(&self) -> Vec<(&str, Span)> {
+        match &self.kind {
+            PatternKind::Binding(name) => vec![(name, self.span)],
+            PatternKind::Wildcard
+            | PatternKind::Unit
+            | PatternKind::BoolLiteral(_)
+            | PatternKind::IntLiteral(_) => vec![],
+            PatternKind::Variant { fields, .. } => fields
+                .iter()
+                .flat_map(|field| field.pattern.bindings())
+                .collect(),
+            PatternKind::Tuple(elements) => elements.iter().flat_map(Pattern::bindings).collect(),
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            PatternKind::Wildcard => f.write_str("_"),
+            PatternKind::Binding(name) => f.write_str(name),
+            PatternKind::Unit => f.write_str("unit"),
+            PatternKind::BoolLiteral(value) => write!(f, "{value}"),
+            PatternKind::IntLiteral(value) => write!(f, "{value}"),
+            PatternKind::Variant {
+                ty,
+                variant,
+                fields,
+            } => {
+                write!(f, "{ty}.{variant}")?;
+                if !fields.is_empty() {
+                    write!(f, "(")?;
+                    for (i, field) in fields.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        if let Some(label) = &field.label {
+                            write!(f, "{label}: ")?;
+                        }
+                        write!(f, "{}", field.pattern)?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            PatternKind::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum PatternKind {
+    Wildcard,
+    Binding(String),
+    Unit,
+    BoolLiteral(bool),
+    /// Can be negative, unlike int literal expressions, which are negated
+    /// with a prefix operator.
+    IntLiteral(i128),
+    Variant {
+        ty: String,
+        variant: String,
+        fields: Vec<VariantFieldPattern>,
     },
+    /// `(a, b)`, or `(a,)` with one element.
+    Tuple(Vec<Pattern>),
 }
 
 #[derive(PartialEq, Eq, Debug)]
+pub struct VariantFieldPattern {
+    pub label: Option<String>,
+    pub pattern: Pattern,
+}
+
+#[derive(PartialEq, Debug)]
 pub struct VariableDecl {
     pub name: String,
     pub value: Expression,
@@ -106,15 +520,123 @@ pub struct VariableDecl {
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Type {
     Ident(String),
+    /// A boxed slice rather than a `Vec`, which keeps a type as small as a
+    /// name and so doesn't grow every expression that has one.
+    Tuple(Box<[Type]>),
+    /// A type constructor applied to type arguments, like `List(Int)` or
+    /// `Map(String, Int)`.
+    Applied {
+        name: String,
+        arguments: Box<[Type]>,
+    },
+    /// `fun(Int, Int) Int`, which returns `Unit` without a return type.
+    Function {
+        parameters: Box<[Type]>,
+        return_type: Option<Box<Type>>,
+    },
 }
 
-#[derive(Ord, Eq, PartialEq, PartialOrd, Debug)]
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Ident(name) => f.write_str(name),
+            Type::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Type::Applied { name, arguments } => {
+                write!(f, "{name}(")?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{argument}")?;
+                }
+                write!(f, ")")
+            }
+            Type::Function {
+                parameters,
+                return_type,
+            } => {
+                write!(f, "fun(")?;
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{parameter}")?;
+                }
+                write!(f, ")")?;
+                if let Some(return_type) = return_type {
+                    write!(f, " {return_type}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Ord, Eq, PartialEq, PartialOrd, Debug, Clone, Copy)]
 pub enum Precedence {
     Lowest,
-    Equality,   // == or !=
-    Comparison, // <, <=, >, >=
-    Sum,        // + or -
-    Product,    // * or /
-    Group,      // ( )
-    Prefix,     // -X or !X
+    Assignment,  // =
+    Pipeline,    // |>
+    Composition, // ~>
+    Or,          // ||
+    And,         // &&
+    Equality,    // == or !=
+    Comparison,  // <, <=, >, >=
+    Coalesce,    // ??
+    Range,       // .. or ..=
+    Shift,       // << or >>
+    Sum,         // + or -
+    Product,     // * or /
+    Power,       // **
+    Prefix,      // -X or !X
+    Access,      // x.y, x[y] or x(y)
+}
+
+impl Precedence {
+    /// The level of the binary operators that declared operators can share,
+    /// from 0 for [`Lowest`](Self::Lowest) to 6 for [`Product`](Self::Product).
+    pub fn from_level(level: u8) -> Self {
+        match level {
+            0 => Precedence::Lowest,
+            1 => Precedence::Composition,
+            2 => Precedence::Equality,
+            3 => Precedence::Comparison,
+            4 => Precedence::Shift,
+            5 => Precedence::Sum,
+            _ => Precedence::Product,
+        }
+    }
+
+    /// The precedence right below this one.
+    pub fn below(self) -> Self {
+        match self {
+            Precedence::Lowest | Precedence::Assignment => Precedence::Lowest,
+            Precedence::Pipeline => Precedence::Assignment,
+            Precedence::Composition => Precedence::Pipeline,
+            Precedence::Or => Precedence::Composition,
+            Precedence::And => Precedence::Or,
+            Precedence::Equality => Precedence::And,
+            Precedence::Comparison => Precedence::Equality,
+            Precedence::Coalesce => Precedence::Comparison,
+            Precedence::Range => Precedence::Coalesce,
+            Precedence::Shift => Precedence::Range,
+            Precedence::Sum => Precedence::Shift,
+            Precedence::Product => Precedence::Sum,
+            Precedence::Power => Precedence::Product,
+            Precedence::Prefix => Precedence::Power,
+            Precedence::Access => Precedence::Prefix,
+        }
+    }
 }
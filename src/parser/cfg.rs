@@ -0,0 +1,135 @@
+//! Conditional compilation. A statement with `@cfg(target: "wasm")` in front
+//! of it only stays in the program when the configuration it's built with
+//! has `target` set to `"wasm"`.
+//!
+//! The parser keeps every statement along with its attributes, so that
+//! tools like the formatter see the whole program. [`Config::apply`] strips
+//! the ones that don't apply before the program is checked.
+
+use std::fmt::{self, Display};
+
+use super::{
+    ast::{ExpressionKind, ExpressionStatement, Program},
+    visit::{VisitMut, walk_statements},
+};
+
+/// The options `@cfg` attributes are compared against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    options: Vec<(String, String)>,
+}
+
+/// Builds for the interpreter.
+impl Default for Config {
+    fn default() -> Self {
+        Self { options: vec![] }.with("target", "interpreter")
+    }
+}
+
+impl Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.options.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}: {value:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Sets an option, replacing the value it had.
+    pub fn with(mut self, key: &str, value: &str) -> Self {
+        match self.options.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => {
+                self.options.push((key.to_string(), value.to_string()));
+                self.options.sort();
+            }
+        }
+        self
+    }
+
+    /// Whether every one of the attribute's options is set to the same value here.
+    pub fn enables(&self, options: &[(String, String)]) -> bool {
+        options
+            .iter()
+            .all(|option| self.options.iter().any(|set| set == option))
+    }
+
+    /// Removes the statements this doesn't enable, and the attributes of the
+    /// ones it does.
+    pub fn apply(&self, program: &mut Program) {
+        Strip(self).visit_statements(&mut program.0);
+    }
+}
+
+struct Strip<'a>(&'a Config);
+
+impl VisitMut for Strip<'_> {
+    fn visit_statements(&mut self, stmts: &mut Vec<ExpressionStatement>) {
+        let config = self.0;
+        stmts.retain_mut(|stmt| {
+            while let ExpressionKind::Cfg { options, .. } = &stmt.expr.kind {
+                if !config.enables(options) {
+                    return false;
+                }
+                let ExpressionKind::Cfg { expr, .. } =
+                    std::mem::replace(&mut stmt.expr.kind, ExpressionKind::Unit)
+                else {
+                    unreachable!()
+                };
+                stmt.expr = *expr;
+            }
+            true
+        });
+        walk_statements(self, stmts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, printer::program_to_minified_source};
+
+    fn configured(config: &Config, input: &str) -> String {
+        let mut program = Parser::new(input).parse().unwrap();
+        config.apply(&mut program);
+        program_to_minified_source(&program)
+    }
+
+    #[test]
+    fn strips_statements_that_dont_apply() {
+        let input = r#"
+            @cfg(target: "wasm") x :: 1;
+            @cfg(target: "native") x :: 2;
+            f :: fun() {
+                @cfg(target: "wasm") @cfg(os: "linux") g();
+                h();
+            };
+        "#;
+        assert_eq!(configured(&Config::default(), input), "f::fun(){h();};");
+        assert_eq!(
+            configured(&Config::default().with("target", "wasm"), input),
+            "x::1;f::fun(){h();};"
+        );
+        assert_eq!(
+            configured(
+                &Config::default().with("target", "wasm").with("os", "linux"),
+                input
+            ),
+            "x::1;f::fun(){g();h();};"
+        );
+    }
+
+    #[test]
+    fn attributes_round_trip() {
+        let input = r#"@cfg(target: "wasm", os: "linux") x :: 1; @cfg() y"#;
+        let program = Parser::new(input).parse().unwrap();
+        let minified = program_to_minified_source(&program);
+        assert_eq!(minified, r#"@cfg(target:"wasm",os:"linux")x::1;@cfg()y"#);
+        let reparsed = Parser::new(&minified).parse().unwrap();
+        assert_eq!(program_to_minified_source(&reparsed), minified);
+    }
+}
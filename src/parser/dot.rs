@@ -0,0 +1,455 @@
+//! Renders a parsed program as a Graphviz DOT graph, with a node for every
+//! expression and pattern labeled with its kind and span.
+
+use std::fmt::Write;
+
+use super::ast::{
+    EnumVariant, Expression, ExpressionKind, ExpressionStatement, FunctionArgument,
+    FunctionParameter, Pattern, PatternKind, Program,
+};
+use crate::span::Span;
+
+pub fn program_to_dot(program: &Program) -> String {
+    let mut graph = DotGraph {
+        out: String::from("digraph ast {\n  node [shape=box, fontname=monospace];\n"),
+        node_count: 0,
+    };
+    let root = graph.node("Program", None);
+    graph.statements(root, "", &program.0);
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+struct DotGraph {
+    out: String,
+    node_count: usize,
+}
+
+impl DotGraph {
+    fn node(&mut self, label: &str, span: Option<Span>) -> usize {
+        let id = self.node_count;
+        self.node_count += 1;
+        let label = match span {
+            Some(span) => format!("{label}\n{}..{}", span.start, span.end),
+            None => label.to_string(),
+        };
+        writeln!(self.out, "  n{id} [label=\"{}\"];", escape(&label)).unwrap();
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &str) {
+        if label.is_empty() {
+            writeln!(self.out, "  n{from} -> n{to};").unwrap();
+        } else {
+            writeln!(
+                self.out,
+                "  n{from} -> n{to} [label=\"{}\"];",
+                escape(label)
+            )
+            .unwrap();
+        }
+    }
+
+    fn child(&mut self, parent: usize, label: &str, expr: &Expression) {
+        let child = self.expression(expr);
+        self.edge(parent, child, label);
+    }
+
+    fn statements(&mut self, parent: usize, label: &str, stmts: &[ExpressionStatement]) {
+        for stmt in stmts {
+            let child = self.expression(&stmt.expr);
+            let label = if stmt.discarded {
+                format!("{label};")
+            } else {
+                label.to_string()
+            };
+            self.edge(parent, child, &label);
+        }
+    }
+
+    fn arguments(&mut self, parent: usize, arguments: &[FunctionArgument]) {
+        for argument in arguments {
+            let label = match (&argument.label, argument.spread) {
+                (_, true) => "...".to_string(),
+                (Some(label), false) => format!("{label}:"),
+                (None, false) => String::new(),
+            };
+            self.child(parent, &label, &argument.value);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) -> usize {
+        let span = Some(expr.span);
+        match &expr.kind {
+            ExpressionKind::Unit => self.node("Unit", span),
+            ExpressionKind::BoolLiteral(value) => self.node(&format!("Bool {value}"), span),
+            ExpressionKind::IntLiteral(value) => self.node(&format!("Int {value}"), span),
+            ExpressionKind::SuffixedIntLiteral { value, suffix } => {
+                self.node(&format!("Int {value}{suffix}"), span)
+            }
+            ExpressionKind::FloatLiteral(value) => self.node(&format!("Float {value}"), span),
+            ExpressionKind::StringLiteral(value) => self.node(&format!("String {value:?}"), span),
+            ExpressionKind::CharLiteral(value) => self.node(&format!("Char {value:?}"), span),
+            ExpressionKind::Ident(name) => self.node(&format!("Ident {name}"), span),
+            ExpressionKind::Prefix {
+                operator,
+                expression,
+            } => {
+                let id = self.node(&format!("Prefix {operator}"), span);
+                self.child(id, "", expression);
+                id
+            }
+            ExpressionKind::Infix {
+                left,
+                operator,
+                right,
+            } => {
+                let id = self.node(&format!("Infix {operator}"), span);
+                self.child(id, "left", left);
+                self.child(id, "right", right);
+                id
+            }
+            ExpressionKind::CustomInfix {
+                left,
+                operator,
+                right,
+            } => {
+                let id = self.node(&format!("CustomInfix {}", operator.symbol), span);
+                self.child(id, "left", left);
+                self.child(id, "right", right);
+                id
+            }
+            ExpressionKind::ChainedComparison {
+                operands,
+                operators,
+            } => {
+                let operators = operators.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+                let id = self.node(&format!("ChainedComparison {}", operators.join(" ")), span);
+                for operand in operands {
+                    self.child(id, "", operand);
+                }
+                id
+            }
+            ExpressionKind::Tuple(elements) => {
+                let id = self.node("Tuple", span);
+                for element in elements {
+                    self.child(id, "", element);
+                }
+                id
+            }
+            ExpressionKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let id = self.node(if *inclusive { "Range ..=" } else { "Range .." }, span);
+                self.child(id, "start", start);
+                self.child(id, "end", end);
+                id
+            }
+            ExpressionKind::Block(body) => {
+                let id = self.node("Block", span);
+                self.statements(id, "body", body);
+                id
+            }
+            ExpressionKind::Assign { target, value } => {
+                let id = self.node("Assign", span);
+                self.child(id, "target", target);
+                self.child(id, "value", value);
+                id
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                mutable,
+                ty,
+                ..
+            } => {
+                let mut label = format!("VariableDecl {name}");
+                if let Some((ty, _)) = ty {
+                    write!(label, ": {ty}").unwrap();
+                }
+                if *mutable {
+                    label.push_str(" (mutable)");
+                }
+                let id = self.node(&label, span);
+                self.child(id, "value", value);
+                id
+            }
+            ExpressionKind::Function {
+                type_parameters,
+                parameters,
+                return_type,
+                body,
+            } => {
+                let mut label = String::from("Function");
+                if !type_parameters.is_empty() {
+                    let names = type_parameters.iter().map(|t| t.name.as_str());
+                    write!(label, "[{}]", names.collect::<Vec<_>>().join(", ")).unwrap();
+                }
+                let parameters = parameters.iter().map(parameter_label).collect::<Vec<_>>();
+                write!(label, "({})", parameters.join(", ")).unwrap();
+                if let Some(ty) = return_type {
+                    write!(label, " {ty}").unwrap();
+                }
+                let id = self.node(&label, span);
+                self.statements(id, "body", body);
+                id
+            }
+            ExpressionKind::FunctionCall { name, arguments } => {
+                let id = self.node(&format!("FunctionCall {name}"), span);
+                self.arguments(id, arguments);
+                id
+            }
+            ExpressionKind::Call { callee, arguments } => {
+                let id = self.node("Call", span);
+                self.child(id, "callee", callee);
+                self.arguments(id, arguments);
+                id
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                let id = self.node(&format!("FieldAccess .{field}"), span);
+                self.child(id, "target", target);
+                id
+            }
+            ExpressionKind::SafeFieldAccess { target, field } => {
+                let id = self.node(&format!("SafeFieldAccess ?.{field}"), span);
+                self.child(id, "target", target);
+                id
+            }
+            ExpressionKind::Try(value) => {
+                let id = self.node("Try", span);
+                self.child(id, "value", value);
+                id
+            }
+            ExpressionKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => {
+                let id = self.node(&format!("MethodCall .{method}"), span);
+                self.child(id, "receiver", receiver);
+                self.arguments(id, arguments);
+                id
+            }
+            ExpressionKind::Index { target, index } => {
+                let id = self.node("Index", span);
+                self.child(id, "target", target);
+                self.child(id, "index", index);
+                id
+            }
+            ExpressionKind::Impl { ty, methods } => {
+                let id = self.node(&format!("Impl {ty}"), span);
+                for method in methods {
+                    self.child(id, &method.name, &method.function);
+                }
+                id
+            }
+            ExpressionKind::Struct { name, fields, .. } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name, field.ty))
+                    .collect::<Vec<_>>();
+                self.node(&format!("Struct {name} {{ {} }}", fields.join(", ")), span)
+            }
+            ExpressionKind::Enum { name, variants, .. } => {
+                let variants = variants.iter().map(variant_label).collect::<Vec<_>>();
+                self.node(&format!("Enum {name} {{ {} }}", variants.join(", ")), span)
+            }
+            ExpressionKind::OperatorDecl(operator) => {
+                let mut label = format!(
+                    "OperatorDecl {} (precedence {}, {})",
+                    operator.symbol, operator.precedence, operator.associativity
+                );
+                if let Some(method) = &operator.method {
+                    write!(label, " .{method}").unwrap();
+                }
+                self.node(&label, span)
+            }
+            ExpressionKind::Match { value, arms } => {
+                let id = self.node("Match", span);
+                self.child(id, "value", value);
+                for arm in arms {
+                    let arm_id = self.node("MatchArm", None);
+                    self.edge(id, arm_id, "");
+                    let pattern = self.pattern(&arm.pattern);
+                    self.edge(arm_id, pattern, "pattern");
+                    self.child(arm_id, "body", &arm.body);
+                }
+                id
+            }
+            ExpressionKind::While {
+                label,
+                condition,
+                body,
+            } => {
+                let id = self.node(&labeled("While", label), span);
+                self.child(id, "condition", condition);
+                self.statements(id, "body", body);
+                id
+            }
+            ExpressionKind::Loop { label, body } => {
+                let id = self.node(&labeled("Loop", label), span);
+                self.statements(id, "body", body);
+                id
+            }
+            ExpressionKind::For {
+                label,
+                pattern,
+                iterable,
+                body,
+            } => {
+                let id = self.node(&labeled("For", label), span);
+                let pattern = self.pattern(pattern);
+                self.edge(id, pattern, "pattern");
+                self.child(id, "iterable", iterable);
+                self.statements(id, "body", body);
+                id
+            }
+            ExpressionKind::Break { label, value } => {
+                let id = self.node(&labeled("Break", label), span);
+                if let Some(value) = value {
+                    self.child(id, "value", value);
+                }
+                id
+            }
+            ExpressionKind::Continue { label } => self.node(&labeled("Continue", label), span),
+            ExpressionKind::Return(value) => {
+                let id = self.node("Return", span);
+                if let Some(value) = value {
+                    self.child(id, "value", value);
+                }
+                id
+            }
+            ExpressionKind::Cfg { options, expr } => {
+                let options = options
+                    .iter()
+                    .map(|(key, value)| format!("{key}: {value:?}"))
+                    .collect::<Vec<_>>();
+                let id = self.node(&format!("Cfg({})", options.join(", ")), span);
+                self.child(id, "", expr);
+                id
+            }
+            ExpressionKind::Include(path) => self.node(&format!("Include {path:?}"), span),
+            ExpressionKind::EmbedText(path) => self.node(&format!("EmbedText {path:?}"), span),
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) -> usize {
+        let span = Some(pattern.span);
+        match &pattern.kind {
+            PatternKind::Wildcard => self.node("Wildcard", span),
+            PatternKind::Binding(name) => self.node(&format!("Binding {name}"), span),
+            PatternKind::Unit => self.node("Unit", span),
+            PatternKind::BoolLiteral(value) => self.node(&format!("Bool {value}"), span),
+            PatternKind::IntLiteral(value) => self.node(&format!("Int {value}"), span),
+            PatternKind::Variant {
+                ty,
+                variant,
+                fields,
+            } => {
+                let id = self.node(&format!("Variant {ty}.{variant}"), span);
+                for field in fields {
+                    let child = self.pattern(&field.pattern);
+                    let label = field
+                        .label
+                        .as_ref()
+                        .map_or(String::new(), |l| format!("{l}:"));
+                    self.edge(id, child, &label);
+                }
+                id
+            }
+            PatternKind::Tuple(elements) => {
+                let id = self.node("Tuple", span);
+                for element in elements {
+                    let child = self.pattern(element);
+                    self.edge(id, child, "");
+                }
+                id
+            }
+        }
+    }
+}
+
+fn parameter_label(parameter: &FunctionParameter) -> String {
+    let (name, ty) = match parameter {
+        FunctionParameter::LabeledAtCallsite {
+            internal_name,
+            external_name: Some(external_name),
+            ty,
+            ..
+        } => (format!("{external_name} {internal_name}"), ty),
+        FunctionParameter::LabeledAtCallsite {
+            internal_name,
+            external_name: None,
+            ty,
+            ..
+        } => (internal_name.clone(), ty),
+        FunctionParameter::UnlabeledAtCallsite { name, ty } => (format!("~{name}"), ty),
+        FunctionParameter::Rest { name, ty } => (format!("...{name}"), ty),
+        FunctionParameter::Pattern { pattern, ty } => (pattern.to_string(), ty),
+        FunctionParameter::SelfParameter => return "self".to_string(),
+    };
+    match ty {
+        Some(ty) => format!("{name}: {ty}"),
+        None => name,
+    }
+}
+
+fn variant_label(variant: &EnumVariant) -> String {
+    if variant.fields.is_empty() {
+        return variant.name.clone();
+    }
+    let fields = variant
+        .fields
+        .iter()
+        .map(|field| match &field.label {
+            Some(label) => format!("{label}: {}", field.ty),
+            None => field.ty.to_string(),
+        })
+        .collect::<Vec<_>>();
+    format!("{}({})", variant.name, fields.join(", "))
+}
+
+fn labeled(kind: &str, label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("{kind} {label}"),
+        None => kind.to_string(),
+    }
+}
+
+/// Escapes a label for a double-quoted DOT string.
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn infix_graph() {
+        let program = Parser::new("x :: 1 + \"a\\\"\";").parse().unwrap();
+        assert_eq!(
+            program_to_dot(&program),
+            r#"digraph ast {
+  node [shape=box, fontname=monospace];
+  n0 [label="Program"];
+  n1 [label="VariableDecl x\n0..14"];
+  n2 [label="Infix +\n5..14"];
+  n3 [label="Int 1\n5..6"];
+  n2 -> n3 [label="left"];
+  n4 [label="String \"a\\\"\"\n9..14"];
+  n2 -> n4 [label="right"];
+  n1 -> n2 [label="value"];
+  n0 -> n1 [label=";"];
+}
+"#
+        );
+    }
+}
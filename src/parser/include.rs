@@ -0,0 +1,243 @@
+//! Resolves `include` and `embed_text` against the file system, which stands
+//! in for a package system until there is one.
+//!
+//! `include "other.lv"` is replaced by the statements of `other.lv`, and
+//! `embed_text "data.txt"` by a string literal with the contents of
+//! `data.txt`. Paths are relative to the directory of the file they're in.
+
+use std::{
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    Parser,
+    ast::{Expression, ExpressionKind, ExpressionStatement, Program},
+    cfg::Config,
+    visit::{VisitMut, walk_expression, walk_statements},
+};
+use crate::span::Span;
+
+#[derive(Debug)]
+pub enum Error {
+    Read {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: super::Error,
+    },
+    /// A file ends up including itself, through each of the `include`s in
+    /// `includes`, given as the file it's in and its span. The first one is
+    /// in the file that's included again.
+    Cycle {
+        includes: Vec<(PathBuf, Span)>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Read { path, error } => {
+                write!(f, "error: couldn't read {}: {error}", path.display())
+            }
+            Error::Parse { path, error } => write!(f, "{}: {error}", path.display()),
+            Error::Cycle { includes } => {
+                write!(f, "error: a file includes itself")?;
+                for (path, span) in includes {
+                    write!(
+                        f,
+                        "\n{}: included at {}..{}",
+                        path.display(),
+                        span.start,
+                        span.end
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Configures `program`, which was parsed from the file at `path`, and
+/// splices in what it includes and embeds, configured the same way.
+/// Returns every file that was read along with its contents, since the
+/// program depends on them.
+pub fn resolve(
+    program: &mut Program,
+    path: &Path,
+    config: &Config,
+) -> Result<Vec<(PathBuf, String)>, Error> {
+    let _span = tracing::info_span!("include").entered();
+    config.apply(program);
+    let mut resolver = Resolver {
+        config,
+        including: vec![path.to_path_buf()],
+        includes: vec![],
+        files: vec![],
+        error: None,
+    };
+    resolver.visit_statements(&mut program.0);
+    match resolver.error {
+        Some(err) => Err(err),
+        None => Ok(resolver.files),
+    }
+}
+
+struct Resolver<'a> {
+    config: &'a Config,
+    /// The file being resolved, after the ones that include it.
+    including: Vec<PathBuf>,
+    /// The `include`s that lead from each file in `including` to the next.
+    includes: Vec<Span>,
+    files: Vec<(PathBuf, String)>,
+    /// The first thing that went wrong, after which nothing else is resolved.
+    error: Option<Error>,
+}
+
+impl Resolver<'_> {
+    fn read(&mut self, path: &str) -> Option<(PathBuf, String)> {
+        let including = self.including.last().unwrap();
+        let path = including.parent().unwrap_or(Path::new("")).join(path);
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                self.files.push((path.clone(), contents.clone()));
+                Some((path, contents))
+            }
+            Err(error) => {
+                self.error.get_or_insert(Error::Read { path, error });
+                None
+            }
+        }
+    }
+
+    fn include(&mut self, path: &str, span: Span) -> Vec<ExpressionStatement> {
+        let Some((path, source)) = self.read(path) else {
+            return vec![];
+        };
+        let same_file = |other: &PathBuf| match (fs::canonicalize(other), fs::canonicalize(&path)) {
+            (Ok(other), Ok(path)) => other == path,
+            _ => *other == path,
+        };
+        if let Some(start) = self.including.iter().position(same_file) {
+            let includes = self.including[start..]
+                .iter()
+                .cloned()
+                .zip(self.includes[start..].iter().copied().chain([span]))
+                .collect();
+            self.error.get_or_insert(Error::Cycle { includes });
+            return vec![];
+        }
+
+        let mut program = match Parser::new(&source).parse() {
+            Ok(program) => program,
+            Err(error) => {
+                self.error.get_or_insert(Error::Parse { path, error });
+                return vec![];
+            }
+        };
+        self.config.apply(&mut program);
+        self.including.push(path);
+        self.includes.push(span);
+        self.visit_statements(&mut program.0);
+        self.including.pop();
+        self.includes.pop();
+        program.0
+    }
+}
+
+impl VisitMut for Resolver<'_> {
+    fn visit_statements(&mut self, stmts: &mut Vec<ExpressionStatement>) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut resolved = vec![];
+        for stmt in std::mem::take(stmts) {
+            match &stmt.expr.kind {
+                ExpressionKind::Include(path) => {
+                    resolved.extend(self.include(path, stmt.expr.span))
+                }
+                _ => resolved.push(stmt),
+            }
+        }
+        *stmts = resolved;
+        walk_statements(self, stmts);
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        if let ExpressionKind::EmbedText(path) = &expr.kind {
+            if let Some((_, contents)) = self.read(path) {
+                expr.kind = ExpressionKind::StringLiteral(contents);
+            }
+            return;
+        }
+        walk_expression(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::printer::program_to_minified_source;
+
+    fn resolved(files: &[(&str, &str)]) -> Result<String, Error> {
+        let dir = std::env::temp_dir().join(format!(
+            "lovely-include-test-{}-{}",
+            std::process::id(),
+            files[0].0
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, source) in files {
+            fs::write(dir.join(name), source).unwrap();
+        }
+
+        let path = dir.join(files[0].0);
+        let mut program = Parser::new(files[0].1).parse().unwrap();
+        let result = resolve(&mut program, &path, &Config::default());
+        fs::remove_dir_all(dir).unwrap();
+        result.map(|_| program_to_minified_source(&program))
+    }
+
+    #[test]
+    fn splices_in_files() {
+        let minified = resolved(&[
+            (
+                "main.lv",
+                r#"include "lib.lv"; f :: fun() { include "body.lv" }; greeting :: embed_text "hi.txt""#,
+            ),
+            ("lib.lv", "x :: 1; y :: 2;"),
+            ("body.lv", r#"@cfg(target: "wasm") a; x + y"#),
+            ("hi.txt", "hello\n"),
+        ])
+        .unwrap();
+        assert_eq!(minified, r#"x::1;y::2;f::fun(){x+y};greeting::"hello\n""#);
+    }
+
+    #[test]
+    fn cycles_and_missing_files() {
+        let err = resolved(&[
+            ("a.lv", r#"include "b.lv""#),
+            ("b.lv", r#"x :: 1; include "c.lv""#),
+            ("c.lv", r#"include "b.lv""#),
+        ])
+        .unwrap_err();
+        let Error::Cycle { includes } = err else {
+            panic!("expected a cycle, got {err:?}");
+        };
+        let includes = includes
+            .iter()
+            .map(|(path, span)| {
+                (
+                    path.file_name().unwrap().to_str().unwrap(),
+                    span.start..span.end,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(includes, [("b.lv", 8..22), ("c.lv", 0..14)]);
+
+        let err = resolved(&[("c.lv", r#"x :: embed_text "missing.txt""#)]).unwrap_err();
+        assert!(matches!(err, Error::Read { path, .. } if path.ends_with("missing.txt")));
+    }
+}
@@ -1,30 +1,94 @@
 #![allow(dead_code)]
 
-use std::iter::Peekable;
+use std::fmt::{self, Display};
 
 use crate::{
     lexer::{
-        Lexer,
-        tokens::TokenKind::{self, *},
+        Lexer, int_value, string_value,
+        tokens::{
+            LexError, Token,
+            TokenKind::{self, *},
+        },
+        unescape,
     },
     span::Span,
 };
 use ast::{
-    Expression, ExpressionKind, ExpressionStatement, FunctionArgument, FunctionParameter,
-    InfixOperator, Precedence, PrefixOperator, Program, Type,
+    Associativity, Attribute, CustomOperator, EnumVariant, Expression, ExpressionKind,
+    ExpressionStatement, FunctionArgument, FunctionParameter, InfixOperator, MatchArm, Method,
+    Pattern, PatternKind, Precedence, PrefixOperator, Program, StructField, Type, TypeParameter,
+    VariantField, VariantFieldPattern,
 };
 
+#[cfg(any(test, feature = "arbitrary"))]
+pub mod arbitrary;
 pub mod ast;
+pub mod cfg;
+pub mod dot;
+pub mod include;
+pub mod printer;
+pub mod sexp;
+pub mod visit;
+
+/// How deep expressions and patterns can be nested, which keeps the parser
+/// and everything that walks the AST after it well within the stack.
+const MAX_DEPTH: usize = 256;
 
 type PrefixParseFn = Box<dyn Fn(&mut Parser) -> Result<Expression, Error>>;
+type PostfixParseFn = Box<dyn Fn(&mut Parser, Expression) -> Result<Expression, Error>>;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Error {
     NoToken,
     NoPrefixParseFn(TokenKind),
-    Expected { expected: String, got: String },
+    Expected {
+        expected: String,
+        got: String,
+    },
     Syntax(String),
     UnexpectedEof,
+    Lex {
+        error: LexError,
+        span: Span,
+    },
+    IntLiteralOutOfRange {
+        span: Span,
+    },
+    FloatLiteralOutOfRange {
+        span: Span,
+    },
+    NestingTooDeep {
+        span: Span,
+    },
+    /// Two parameters of a function have the same name, which is at both
+    /// spans. It isn't kept here as well, since every parse function returns
+    /// errors and their size adds up in deeply nested input.
+    DuplicateParameter {
+        first: Span,
+        second: Span,
+    },
+    /// Two parameters of a function have the same label at the call site,
+    /// which is at both spans.
+    DuplicateLabel {
+        first: Span,
+        second: Span,
+    },
+    /// Two fields of a struct or an enum variant have the same name, at both
+    /// spans.
+    DuplicateField {
+        first: Span,
+        second: Span,
+    },
+    /// Two variants of an enum have the same name, at both spans.
+    DuplicateVariant {
+        first: Span,
+        second: Span,
+    },
+    /// A parameter comes after a rest parameter, which has to be the last
+    /// one. The span is the span of the rest parameter's name.
+    RestParameterNotLast {
+        span: Span,
+    },
 }
 
 impl Error {
@@ -40,21 +104,84 @@ impl Error {
     }
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let at = |f: &mut fmt::Formatter<'_>, span: &Span| {
+            write!(f, "error at {}..{}: ", span.start, span.end)
+        };
+        let duplicate = |f: &mut fmt::Formatter<'_>, what: &str, first: &Span, second: &Span| {
+            at(f, second)?;
+            write!(f, "the same {what} as at {}..{}", first.start, first.end)
+        };
+        match self {
+            Error::NoToken | Error::UnexpectedEof => write!(f, "error: unexpected end of file"),
+            Error::NoPrefixParseFn(kind) => write!(f, "error: expected an expression, got {kind}"),
+            Error::Expected { expected, got } => write!(f, "error: expected {expected}, got {got}"),
+            Error::Syntax(message) => write!(f, "error: {message}"),
+            Error::Lex { error, span } => {
+                at(f, span)?;
+                write!(f, "{error}")
+            }
+            Error::IntLiteralOutOfRange { span } => {
+                at(f, span)?;
+                write!(f, "the integer literal is too big")
+            }
+            Error::FloatLiteralOutOfRange { span } => {
+                at(f, span)?;
+                write!(f, "the float literal is too big")
+            }
+            Error::NestingTooDeep { span } => {
+                at(f, span)?;
+                write!(f, "nested more than {MAX_DEPTH} deep")
+            }
+            Error::DuplicateParameter { first, second } => {
+                duplicate(f, "parameter name", first, second)
+            }
+            Error::DuplicateLabel { first, second } => duplicate(f, "label", first, second),
+            Error::DuplicateField { first, second } => duplicate(f, "field name", first, second),
+            Error::DuplicateVariant { first, second } => {
+                duplicate(f, "variant name", first, second)
+            }
+            Error::RestParameterNotLast { span } => {
+                at(f, span)?;
+                write!(f, "a rest parameter has to be the last one")
+            }
+        }
+    }
+}
+
 pub struct Parser<'src> {
     source: String,
-    lexer: Peekable<Lexer<'src>>,
+    lexer: Tokens<'src>,
+    // labels of the loops currently being parsed, innermost last
+    loop_labels: Vec<String>,
+    // operators declared so far, which apply to the rest of the source
+    operators: Vec<CustomOperator>,
+    /// How many expressions and patterns the parser is inside of.
+    depth: usize,
 }
 
 impl<'src> Parser<'src> {
     pub fn new(source: &'src str) -> Self {
-        let lexer = Lexer::new(source).peekable();
+        let lexer = Tokens {
+            lexer: Lexer::new(source),
+            tokens: vec![],
+            doc_comments: vec![],
+            position: 0,
+        };
         Self {
             source: source.to_string(),
             lexer,
+            loop_labels: vec![],
+            operators: vec![],
+            depth: 0,
         }
     }
 
     pub fn parse(&mut self) -> Result<Program, Error> {
+        // the whole source is lexed first, so that each is timed on its own
+        tracing::info_span!("lex").in_scope(|| self.lexer.lex_all());
+        let _span = tracing::info_span!("parse").entered();
         let mut stmts = vec![];
         while self.lexer.peek().is_some() {
             stmts.push(self.parse_expression_statement()?);
@@ -62,8 +189,24 @@ impl<'src> Parser<'src> {
         Ok(Program(stmts))
     }
 
+    /// Remembers where the parser is, so it can try parsing one construct and
+    /// [`rewind`](Self::rewind) to try another if that fails.
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.lexer.position,
+            loop_labels: self.loop_labels.len(),
+            operators: self.operators.len(),
+        }
+    }
+
+    fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.lexer.position = checkpoint.position;
+        self.loop_labels.truncate(checkpoint.loop_labels);
+        self.operators.truncate(checkpoint.operators);
+    }
+
     fn parse_expression_statement(&mut self) -> Result<ExpressionStatement, Error> {
-        let expr = self.parse_expression(Precedence::Lowest)?;
+        let expr = self.parse_statement_expression()?;
         let has_semicolon = self.check_semicolon()?;
         if has_semicolon {
             self.lexer.next();
@@ -75,106 +218,217 @@ impl<'src> Parser<'src> {
         })
     }
 
-    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, Error> {
-        let mut expr = self.prefix_parse_fn()?(self)?;
-
-        while self.cur_precedence()? > precedence {
-            match &self.peek_kind() {
-                IntLiteral => return Err(Error::syntax_err("consecutive ints")),
-                Eof => return Ok(expr),
-                Plus => {
-                    expr =
-                        self.parse_infix_expression(expr, InfixOperator::Plus, Precedence::Sum)?;
-                }
-                Minus => {
-                    expr =
-                        self.parse_infix_expression(expr, InfixOperator::Minus, Precedence::Sum)?;
-                }
-                Asterisk => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::Multiply,
-                        Precedence::Product,
-                    )?;
-                }
-                Slash => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::Divide,
-                        Precedence::Product,
-                    )?;
-                }
-                GreaterThan => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::GreaterThan,
-                        Precedence::Comparison,
-                    )?;
-                }
-                LessThan => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::LessThan,
-                        Precedence::Comparison,
-                    )?;
-                }
-                GreaterThanOrEqual => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::GreaterThanOrEqual,
-                        Precedence::Comparison,
-                    )?;
-                }
-                LessThanOrEqual => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::LessThanOrEqual,
-                        Precedence::Comparison,
-                    )?;
-                }
-                DoubleEqual => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::Equal,
-                        Precedence::Equality,
-                    )?;
-                }
-                NotEqual => {
-                    expr = self.parse_infix_expression(
-                        expr,
-                        InfixOperator::NotEqual,
-                        Precedence::Equality,
-                    )?;
+    /// Parses what a statement is made of, which is an expression or one of
+    /// the things only a statement can be.
+    fn parse_statement_expression(&mut self) -> Result<Expression, Error> {
+        if self.peek_directive() == Some("include") {
+            return self.parse_include();
+        }
+        if self.peek_kind() != &At {
+            return self.parse_expression(Precedence::Lowest);
+        }
+        match self.peek_attribute() {
+            Some("cfg") => self.parse_cfg_attribute(),
+            _ => self.parse_attributed_declaration(),
+        }
+    }
+
+    /// The name of the attribute that's next, like `cfg` for `@cfg(...)`.
+    fn peek_attribute(&mut self) -> Option<&str> {
+        let token = self
+            .lexer
+            .peek_nth(1)
+            .filter(|token| token.kind == Identifier)?;
+        Some(token.span.slice(&self.source))
+    }
+
+    /// The name of the directive that's next, like `include`, which are only
+    /// keywords when a file name follows them.
+    fn peek_directive(&mut self) -> Option<&str> {
+        if self.peek_nth_kind(1) != &StringLiteral {
+            return None;
+        }
+        let token = self.lexer.peek().filter(|token| token.kind == Identifier)?;
+        let name = token.span.slice(&self.source);
+        ["include", "embed_text"]
+            .into_iter()
+            .find(|directive| *directive == name)
+    }
+
+    /// `@cfg(key: "value", ...)` in front of a statement, which can have more
+    /// attributes of its own.
+    fn parse_cfg_attribute(&mut self) -> Result<Expression, Error> {
+        let Span { start, .. } = self.expect_token(At)?;
+        self.expect_ident()?;
+
+        self.expect_token(LParen)?;
+        let mut options = vec![];
+        while self.peek_kind() != &RParen {
+            let (key, _) = self.expect_ident()?;
+            self.expect_token(Colon)?;
+            let (value, _) = self.expect_string()?;
+            options.push((key, value));
+            if self.peek_kind() != &RParen {
+                self.expect_token(Comma)?;
+            }
+        }
+        self.expect_token(RParen)?;
+
+        let expr = self.nested(|parser| parser.parse_statement_expression())?;
+        let span = Span::from_range(start, expr.span.end);
+        Ok(Expression::new(
+            ExpressionKind::Cfg {
+                options,
+                expr: Box::new(expr),
+            },
+            span,
+        ))
+    }
+
+    /// A declaration with attributes in front of it, like
+    /// `@inline double :: fun(~x: Int) { x * 2 }`. Each declaration parses its
+    /// own attributes, so this only looks past them for what's declared.
+    fn parse_attributed_declaration(&mut self) -> Result<Expression, Error> {
+        let checkpoint = self.checkpoint();
+        self.parse_attributes()?;
+        let declared = (self.peek_kind().clone(), self.peek_nth_kind(1).clone());
+        self.rewind(checkpoint);
+        match declared {
+            (Struct, _) => self.parse_struct_declaration(),
+            (Enum, _) => self.parse_enum_declaration(),
+            (Identifier, Colon) => self.parse_variable_declaration(),
+            (tok, _) => Err(Error::expected("declaration", &tok.to_string())),
+        }
+    }
+
+    /// Parses the attributes in front of a declaration, if it has any, like
+    /// `@test` or `@deprecated("use g")`.
+    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, Error> {
+        let mut attributes = vec![];
+        while self.peek_kind() == &At {
+            let Span { start, .. } = self.expect_token(At)?;
+            let (name, Span { mut end, .. }) = self.expect_ident()?;
+            if name == "cfg" {
+                return Err(Error::syntax_err(
+                    "`@cfg` has to come before any other attributes",
+                ));
+            }
+
+            let mut arguments = vec![];
+            if self.peek_kind() == &LParen {
+                self.expect_token(LParen)?;
+                while self.peek_kind() != &RParen {
+                    let argument = match self.peek_kind() {
+                        IntLiteral | FloatLiteral | StringLiteral | CharLiteral | True | False => {
+                            self.prefix_parse_fn()?(self)?
+                        }
+                        tok => return Err(Error::expected("literal", &tok.to_string())),
+                    };
+                    arguments.push(argument);
+                    if self.peek_kind() != &RParen {
+                        self.expect_token(Comma)?;
+                    }
                 }
-                tok => return Err(Error::syntax_err(&format!("invalid operator: {tok}"))),
+                end = self.expect_token(RParen)?.end;
             }
+
+            attributes.push(Attribute {
+                name,
+                arguments,
+                span: Span::from_range(start, end),
+            });
         }
+        Ok(attributes)
+    }
 
-        Ok(expr)
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, Error> {
+        self.nested(|parser| {
+            let mut expr = parser.prefix_parse_fn()?(parser)?;
+            while parser.cur_precedence()? > precedence {
+                expr = parser.parse_operator(expr)?;
+            }
+            Ok(expr)
+        })
+    }
+
+    /// Parses the operator that's next and its right operand, if it has one,
+    /// applied to `left`. It's kept out of [`parse_expression`], since what it
+    /// needs would otherwise take up stack at every level of nesting.
+    ///
+    /// [`parse_expression`]: Self::parse_expression
+    fn parse_operator(&mut self, left: Expression) -> Result<Expression, Error> {
+        if let Some((operator, tokens)) = self.peek_custom_operator() {
+            return self.parse_custom_infix_expression(left, operator, tokens);
+        }
+        if let Some(operator) = infix_operator(self.peek_kind()) {
+            return self.parse_infix_expression(left, operator);
+        }
+        if let Some(postfix_parse_fn) = self.postfix_parse_fn() {
+            return postfix_parse_fn(self, left);
+        }
+        match &self.peek_kind() {
+            IntLiteral => Err(Error::syntax_err("consecutive ints")),
+            Eof => Ok(left),
+            GreaterThan | LessThan | GreaterThanOrEqual | LessThanOrEqual => {
+                self.parse_comparison_expression(left)
+            }
+            tok => Err(Error::syntax_err(&format!("invalid operator: {tok}"))),
+        }
+    }
+
+    /// Parses something nested one level deeper than what it's in, failing on
+    /// input that's nested so deep that the parser would run out of stack.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth == MAX_DEPTH {
+            let span = self
+                .lexer
+                .peek()
+                .map_or(Span::from_range(0, 0), |token| token.span);
+            return Err(Error::NestingTooDeep { span });
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     fn prefix_parse_fn(&mut self) -> Result<PrefixParseFn, Error> {
+        if self.peek_directive() == Some("embed_text") {
+            return Ok(Box::new(|parser| parser.parse_embed_text()));
+        }
         let peek_token_kind = self.peek_kind();
         match peek_token_kind {
             IntLiteral => Ok(Box::new(|parser| parser.parse_int_literal())),
+            FloatLiteral => Ok(Box::new(|parser| parser.parse_float_literal())),
+            StringLiteral => Ok(Box::new(|parser| parser.parse_string_literal())),
+            CharLiteral => Ok(Box::new(|parser| parser.parse_char_literal())),
+            Illegal(error) => Err(Error::Lex {
+                error: *error,
+                span: self.lexer.peek().unwrap().span,
+            }),
             True | False => Ok(Box::new(|parser| parser.parse_bool_literal())),
             Unit => Ok(Box::new(|parser| parser.parse_unit())),
             LParen => Ok(Box::new(|parser| parser.parse_grouped_expression())),
-            Identifier => {
-                let (name, span) = self.expect_ident()?;
-                match self.peek_kind() {
-                    Colon => Ok(Box::new(move |parser| {
-                        parser.parse_variable_declaration(&name, span.start)
-                    })),
-                    LParen => Ok(Box::new(move |parser| {
-                        parser.parse_function_call(&name, span.start)
-                    })),
-                    _ => Ok(Box::new(move |parser| {
-                        parser.parse_variable_ident(&name, span)
-                    })),
-                }
-            }
+            LBrace => Ok(Box::new(|parser| parser.parse_block_expression())),
+            Identifier => match self.peek_nth_kind(1) {
+                Colon => Ok(Box::new(|parser| parser.parse_variable_declaration())),
+                LParen => Ok(Box::new(|parser| parser.parse_function_call())),
+                _ => Ok(Box::new(|parser| parser.parse_variable_ident())),
+            },
+            SelfValue => Ok(Box::new(|parser| parser.parse_self())),
             Fun => Ok(Box::new(|parser| parser.parse_function_expression())),
+            BitOr | PipePipe => Ok(Box::new(|parser| parser.parse_lambda_expression())),
+            Impl => Ok(Box::new(|parser| parser.parse_impl_block())),
+            Struct => Ok(Box::new(|parser| parser.parse_struct_declaration())),
+            Enum => Ok(Box::new(|parser| parser.parse_enum_declaration())),
+            Operator => Ok(Box::new(|parser| parser.parse_operator_declaration())),
+            Match => Ok(Box::new(|parser| parser.parse_match_expression())),
+            While => Ok(Box::new(|parser| parser.parse_while_expression(None))),
+            Loop => Ok(Box::new(|parser| parser.parse_loop_expression(None))),
+            For => Ok(Box::new(|parser| parser.parse_for_expression(None))),
+            Break => Ok(Box::new(|parser| parser.parse_break_expression())),
+            Continue => Ok(Box::new(|parser| parser.parse_continue_expression())),
+            Return => Ok(Box::new(|parser| parser.parse_return_expression())),
             ExclamationMark => Ok(Box::new(|parser| {
                 parser.parse_prefix_expression(PrefixOperator::LogicalNot)
             })),
@@ -185,6 +439,29 @@ impl<'src> Parser<'src> {
         }
     }
 
+    /// The operators that follow the expression they apply to, which all bind
+    /// as tightly as can be.
+    fn postfix_parse_fn(&mut self) -> Option<PostfixParseFn> {
+        match self.peek_kind() {
+            Dot => Some(Box::new(|parser, expr| {
+                parser.parse_access_expression(expr)
+            })),
+            QuestionDot => Some(Box::new(|parser, expr| {
+                parser.parse_safe_access_expression(expr)
+            })),
+            Question => Some(Box::new(|parser, expr| parser.parse_try_expression(expr))),
+            LBracket => Some(Box::new(|parser, expr| parser.parse_index_expression(expr))),
+            LParen => Some(Box::new(|parser, expr| parser.parse_call_expression(expr))),
+            DotDot | DotDotEqual => Some(Box::new(|parser, start| {
+                parser.parse_range_expression(start)
+            })),
+            SingleEqual => Some(Box::new(|parser, target| {
+                parser.parse_assignment_expression(target)
+            })),
+            _ => None,
+        }
+    }
+
     fn parse_prefix_expression(&mut self, operator: PrefixOperator) -> Result<Expression, Error> {
         let Span { start, .. } = self.expect_token(match operator {
             PrefixOperator::LogicalNot => ExclamationMark,
@@ -205,10 +482,9 @@ impl<'src> Parser<'src> {
         &mut self,
         lhs: Expression,
         operator: InfixOperator,
-        precedence: Precedence,
     ) -> Result<Expression, Error> {
         self.lexer.next();
-        let rhs = self.parse_expression(precedence)?;
+        let rhs = self.parse_right_operand(operator.precedence(), operator.associativity())?;
         let start_position = lhs.span.start;
         let end_position = rhs.span.end;
         Ok(Expression::new(
@@ -221,11 +497,177 @@ impl<'src> Parser<'src> {
         ))
     }
 
+    fn parse_custom_infix_expression(
+        &mut self,
+        lhs: Expression,
+        operator: CustomOperator,
+        tokens: usize,
+    ) -> Result<Expression, Error> {
+        for _ in 0..tokens {
+            self.lexer.next();
+        }
+        let rhs = self.parse_right_operand(operator.precedence(), operator.associativity)?;
+        let span = Span::from_range(lhs.span.start, rhs.span.end);
+        Ok(Expression::new(
+            ExpressionKind::CustomInfix {
+                left: Box::new(lhs),
+                operator,
+                right: Box::new(rhs),
+            },
+            span,
+        ))
+    }
+
+    /// Parses what's right of a binary operator. A right associative operator
+    /// takes the operators that bind as tightly as itself along with it, so
+    /// `a ** b ** c` is `a ** (b ** c)`.
+    fn parse_right_operand(
+        &mut self,
+        precedence: Precedence,
+        associativity: Associativity,
+    ) -> Result<Expression, Error> {
+        match associativity {
+            Associativity::Left => self.parse_expression(precedence),
+            Associativity::Right => self.parse_expression(precedence.below()),
+        }
+    }
+
+    /// Parses `start..end` or `start..=end`. Ranges don't chain, so another
+    /// one right after is an error rather than a range of ranges.
+    fn parse_range_expression(&mut self, start: Expression) -> Result<Expression, Error> {
+        let inclusive = self.lexer.next().unwrap().kind == DotDotEqual;
+        let end = self.parse_expression(Precedence::Range)?;
+        if let DotDot | DotDotEqual = self.peek_kind() {
+            return Err(Error::syntax_err("ranges can't be chained"));
+        }
+        let span = Span::from_range(start.span.start, end.span.end);
+        Ok(Expression::new(
+            ExpressionKind::Range {
+                start: Box::new(start),
+                end: Box::new(end),
+                inclusive,
+            },
+            span,
+        ))
+    }
+
+    /// Parses `target = value`, which groups from the right so that
+    /// `a = b = c` assigns `b = c` to `a`.
+    fn parse_assignment_expression(&mut self, target: Expression) -> Result<Expression, Error> {
+        if !matches!(
+            target.kind,
+            ExpressionKind::Ident(_)
+                | ExpressionKind::FieldAccess { .. }
+                | ExpressionKind::Index { .. }
+        ) {
+            return Err(Error::syntax_err(
+                "only variables, fields and indexes can be assigned to",
+            ));
+        }
+        self.expect_token(SingleEqual)?;
+        let value = self.parse_expression(Precedence::Assignment.below())?;
+        let span = Span::from_range(target.span.start, value.span.end);
+        Ok(Expression::new(
+            ExpressionKind::Assign {
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+            span,
+        ))
+    }
+
+    /// Parses `a < b`, or a chain like `a < b <= c` if more comparisons follow.
+    fn parse_comparison_expression(&mut self, first: Expression) -> Result<Expression, Error> {
+        let start_position = first.span.start;
+        let mut operands = vec![first];
+        let mut operators = vec![];
+        while let Some(operator) = comparison_operator(self.peek_kind()) {
+            self.lexer.next();
+            operators.push(operator);
+            operands.push(self.parse_expression(Precedence::Comparison)?);
+        }
+        let span = Span::from_range(start_position, operands.last().unwrap().span.end);
+
+        if let [operator] = operators[..] {
+            let right = operands.pop().unwrap();
+            let left = operands.pop().unwrap();
+            return Ok(Expression::new(
+                ExpressionKind::Infix {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(right),
+                },
+                span,
+            ));
+        }
+
+        Ok(Expression::new(
+            ExpressionKind::ChainedComparison {
+                operands,
+                operators,
+            },
+            span,
+        ))
+    }
+
     fn parse_int_literal(&mut self) -> Result<Expression, Error> {
+        let span = self.lexer.peek().ok_or(Error::UnexpectedEof)?.span;
+        if let (value, Some(suffix)) = int_value(span.slice(&self.source)) {
+            self.lexer.next();
+            let value = value.ok_or(Error::IntLiteralOutOfRange { span })?;
+            return Ok(Expression::new(
+                ExpressionKind::SuffixedIntLiteral {
+                    value,
+                    suffix: suffix.to_string(),
+                },
+                span,
+            ));
+        }
+
         let (num, span) = self.expect_int()?;
         Ok(Expression::new(ExpressionKind::IntLiteral(num), span))
     }
 
+    fn parse_float_literal(&mut self) -> Result<Expression, Error> {
+        let span = self.expect_token(FloatLiteral)?;
+        // the lexer only makes these out of digits around a dot, which
+        // always parse without the `_`s between them, but can be too big to
+        // be anything but infinite
+        let value: f64 = span.slice(&self.source).replace('_', "").parse().unwrap();
+        if value.is_infinite() {
+            return Err(Error::FloatLiteralOutOfRange { span });
+        }
+        Ok(Expression::new(ExpressionKind::FloatLiteral(value), span))
+    }
+
+    fn parse_string_literal(&mut self) -> Result<Expression, Error> {
+        let (value, span) = self.expect_string()?;
+        Ok(Expression::new(ExpressionKind::StringLiteral(value), span))
+    }
+
+    fn parse_include(&mut self) -> Result<Expression, Error> {
+        let (_, Span { start, .. }) = self.expect_ident()?;
+        let (path, Span { end, .. }) = self.expect_string()?;
+        Ok(Expression::new(
+            ExpressionKind::Include(path),
+            Span::from_range(start, end),
+        ))
+    }
+
+    fn parse_embed_text(&mut self) -> Result<Expression, Error> {
+        let (_, Span { start, .. }) = self.expect_ident()?;
+        let (path, Span { end, .. }) = self.expect_string()?;
+        Ok(Expression::new(
+            ExpressionKind::EmbedText(path),
+            Span::from_range(start, end),
+        ))
+    }
+
+    fn parse_char_literal(&mut self) -> Result<Expression, Error> {
+        let (value, span) = self.expect_char()?;
+        Ok(Expression::new(ExpressionKind::CharLiteral(value), span))
+    }
+
     fn parse_bool_literal(&mut self) -> Result<Expression, Error> {
         match self.peek_kind() {
             True => {
@@ -245,22 +687,104 @@ impl<'src> Parser<'src> {
         Ok(Expression::new(ExpressionKind::Unit, span))
     }
 
-    fn parse_variable_ident(&mut self, name: &str, span: Span) -> Result<Expression, Error> {
+    fn parse_variable_ident(&mut self) -> Result<Expression, Error> {
+        let (name, span) = self.expect_ident()?;
+        Ok(Expression::new(ExpressionKind::Ident(name), span))
+    }
+
+    fn parse_self(&mut self) -> Result<Expression, Error> {
+        let span = self.expect_token(SelfValue)?;
         Ok(Expression::new(
-            ExpressionKind::Ident(name.to_string()),
+            ExpressionKind::Ident("self".to_string()),
             span,
         ))
     }
 
+    /// Parses a type name, or a tuple type like `(Int, Bool)`. Like tuple
+    /// expressions, a tuple type with one element needs a trailing comma.
     fn parse_type(&mut self) -> Result<Type, Error> {
-        let (name, _) = self.expect_ident()?;
-        Ok(Type::Ident(name))
+        if self.peek_kind() == &Fun {
+            return self.nested(Parser::parse_function_type);
+        }
+        if self.peek_kind() != &LParen {
+            let (name, _) = self.expect_ident()?;
+            if self.peek_kind() == &LParen {
+                return self.nested(|parser| parser.parse_type_arguments(name));
+            }
+            return Ok(Type::Ident(name));
+        }
+
+        self.nested(|parser| {
+            parser.expect_token(LParen)?;
+            let first = parser.parse_type()?;
+            if parser.peek_kind() == &RParen {
+                parser.expect_token(RParen)?;
+                return Ok(first);
+            }
+            let mut elements = vec![first];
+            while parser.peek_kind() == &Comma {
+                parser.expect_token(Comma)?;
+                if parser.peek_kind() == &RParen {
+                    break;
+                }
+                elements.push(parser.parse_type()?);
+            }
+            parser.expect_token(RParen)?;
+            Ok(Type::Tuple(elements.into()))
+        })
+    }
+
+    /// Parses the arguments of a type constructor, like `(String, Int)` in
+    /// `Map(String, Int)`, of which there has to be at least one.
+    fn parse_type_arguments(&mut self, name: String) -> Result<Type, Error> {
+        self.expect_token(LParen)?;
+        let mut arguments = vec![self.parse_type()?];
+        while self.peek_kind() == &Comma {
+            self.expect_token(Comma)?;
+            if self.peek_kind() == &RParen {
+                break;
+            }
+            arguments.push(self.parse_type()?);
+        }
+        self.expect_token(RParen)?;
+        Ok(Type::Applied {
+            name,
+            arguments: arguments.into(),
+        })
+    }
+
+    /// Parses `fun(Int, Int) Int`, whose return type is taken to be anything
+    /// after the parentheses that can start a type.
+    fn parse_function_type(&mut self) -> Result<Type, Error> {
+        self.expect_token(Fun)?;
+        self.expect_token(LParen)?;
+        let mut parameters = vec![];
+        while self.peek_kind() != &RParen {
+            parameters.push(self.parse_type()?);
+            if self.peek_kind() == &Comma {
+                self.expect_token(Comma)?;
+            } else {
+                break;
+            }
+        }
+        self.expect_token(RParen)?;
+
+        let mut return_type = None;
+        if let Identifier | LParen | Fun = self.peek_kind() {
+            return_type = Some(Box::new(self.parse_type()?));
+        }
+        Ok(Type::Function {
+            parameters: parameters.into(),
+            return_type,
+        })
     }
 
+    /// Parses `(expr)`, which only groups, or a tuple like `(a, b)`.
     fn parse_grouped_expression(&mut self) -> Result<Expression, Error> {
         let start_position = self.expect_token(LParen)?.start;
         let expr = self.parse_expression(Precedence::Lowest)?;
         match self.peek_kind() {
+            Comma => self.parse_tuple_expression(start_position, expr),
             RParen => {
                 let end_position = self.expect_token(RParen)?.end;
                 Ok(Expression::new(
@@ -272,82 +796,253 @@ impl<'src> Parser<'src> {
         }
     }
 
-    fn parse_function_call(
+    /// Parses the rest of a tuple after its first element. A trailing comma
+    /// is what makes `(a,)` a tuple with one element.
+    fn parse_tuple_expression(
         &mut self,
-        fn_name: &str,
-        start_position: usize,
+        start: usize,
+        first: Expression,
     ) -> Result<Expression, Error> {
-        self.expect_token(LParen)?;
-
-        let mut arguments = vec![];
-
-        while self.peek_kind() != &RParen {
-            arguments.push(self.parse_function_argument()?);
-            if self.peek_kind() == &Comma {
-                self.expect_token(Comma)?;
-                continue;
-            } else {
+        let mut elements = vec![first];
+        while self.peek_kind() == &Comma {
+            self.expect_token(Comma)?;
+            if self.peek_kind() == &RParen {
                 break;
             }
+            elements.push(self.parse_expression(Precedence::Lowest)?);
         }
+        let end = self.expect_token(RParen)?.end;
+        Ok(Expression::new(
+            ExpressionKind::Tuple(elements),
+            Span::from_range(start, end),
+        ))
+    }
+
+    fn parse_function_call(&mut self) -> Result<Expression, Error> {
+        let (name, Span { start, .. }) = self.expect_ident()?;
+        let (arguments, end_position) = self.parse_function_arguments()?;
 
-        let end_span = self.expect_token(RParen)?;
+        Ok(Expression::new(
+            ExpressionKind::FunctionCall { name, arguments },
+            Span::from_range(start, end_position),
+        ))
+    }
 
+    fn parse_call_expression(&mut self, callee: Expression) -> Result<Expression, Error> {
+        let start = callee.span.start;
+        let (arguments, end) = self.parse_function_arguments()?;
         Ok(Expression::new(
-            ExpressionKind::FunctionCall {
-                name: fn_name.to_string(),
+            ExpressionKind::Call {
+                callee: Box::new(callee),
                 arguments,
             },
-            Span::from_range(start_position, end_span.end),
+            Span::from_range(start, end),
         ))
     }
 
-    fn parse_function_argument(&mut self) -> Result<FunctionArgument, Error> {
-        let mut label = None;
-        let value: Expression;
+    fn parse_access_expression(&mut self, target: Expression) -> Result<Expression, Error> {
+        self.expect_token(Dot)?;
+        if let IntLiteral | FloatLiteral = self.peek_kind() {
+            return self.parse_tuple_field(target);
+        }
+        let (name, name_span) = self.expect_ident()?;
+        let start_position = target.span.start;
 
-        let tok = self.lexer.peek().ok_or(Error::UnexpectedEof)?;
-        let span = tok.span;
-        if let Identifier = tok.kind {
-            let (name, _) = self.expect_ident()?;
-            if self.peek_kind() == &Colon {
-                self.expect_token(Colon)?;
-                label = Some(name);
-                value = self.parse_expression(Precedence::Lowest)?;
-            } else {
-                value = Expression::new(ExpressionKind::Ident(name), span);
-            }
-        } else {
-            value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_kind() != &LParen {
+            return Ok(Expression::new(
+                ExpressionKind::FieldAccess {
+                    target: Box::new(target),
+                    field: name,
+                },
+                Span::from_range(start_position, name_span.end),
+            ));
         }
 
-        Ok(FunctionArgument { label, value })
+        let (arguments, end_position) = self.parse_function_arguments()?;
+        Ok(Expression::new(
+            ExpressionKind::MethodCall {
+                receiver: Box::new(target),
+                method: name,
+                arguments,
+            },
+            Span::from_range(start_position, end_position),
+        ))
     }
 
-    fn parse_variable_declaration(
-        &mut self,
-        name: &str,
-        start_position: usize,
-    ) -> Result<Expression, Error> {
-        self.expect_token(Colon)?;
+    /// Parses `target?.field`. There are no safe method calls, so a call
+    /// after it calls whatever the field holds.
+    fn parse_safe_access_expression(&mut self, target: Expression) -> Result<Expression, Error> {
+        self.expect_token(QuestionDot)?;
+        let (field, field_span) = self.expect_ident()?;
+        let span = Span::from_range(target.span.start, field_span.end);
+        Ok(Expression::new(
+            ExpressionKind::SafeFieldAccess {
+                target: Box::new(target),
+                field,
+            },
+            span,
+        ))
+    }
 
-        let mut ty = None;
-        if self.peek_kind() == &Identifier || self.peek_kind() == &Fun {
-            ty = Some(self.parse_type()?);
+    /// Parses the index in `t.0`. The `0.1` in `t.0.1` lexes as a float
+    /// literal, which stands for two indices in a row here.
+    fn parse_tuple_field(&mut self, target: Expression) -> Result<Expression, Error> {
+        let span = self.lexer.next().unwrap().span;
+        let text = span.slice(&self.source);
+        let mut expr = target;
+        let mut end = span.start;
+        for index in text.split('.') {
+            end += index.len();
+            let Ok(index) = index.parse::<usize>() else {
+                return Err(Error::expected("tuple index", text));
+            };
+            let span = Span::from_range(expr.span.start, end);
+            // past the dot before the next index
+            end += 1;
+            expr = Expression::new(
+                ExpressionKind::FieldAccess {
+                    target: Box::new(expr),
+                    field: index.to_string(),
+                },
+                span,
+            );
         }
+        Ok(expr)
+    }
 
-        #[allow(clippy::needless_late_init)]
-        let value: Expression;
-        let mutable: bool;
+    fn parse_index_expression(&mut self, target: Expression) -> Result<Expression, Error> {
+        self.expect_token(LBracket)?;
+        let index = self.parse_expression(Precedence::Lowest)?;
+        let end_span = self.expect_token(RBracket)?;
+        let start_position = target.span.start;
+        Ok(Expression::new(
+            ExpressionKind::Index {
+                target: Box::new(target),
+                index: Box::new(index),
+            },
+            Span::from_range(start_position, end_span.end),
+        ))
+    }
 
-        match self.peek_kind() {
-            Colon => {
-                self.expect_token(Colon)?;
+    fn parse_try_expression(&mut self, value: Expression) -> Result<Expression, Error> {
+        let Span { end, .. } = self.expect_token(Question)?;
+        let span = Span::from_range(value.span.start, end);
+        Ok(Expression::new(ExpressionKind::Try(Box::new(value)), span))
+    }
+
+    fn parse_function_arguments(&mut self) -> Result<(Vec<FunctionArgument>, usize), Error> {
+        self.expect_token(LParen)?;
+
+        let mut arguments = vec![];
+
+        while self.peek_kind() != &RParen {
+            arguments.push(self.parse_function_argument()?);
+            if self.peek_kind() == &Comma {
+                self.expect_token(Comma)?;
+                continue;
+            } else {
+                break;
+            }
+        }
+
+        let mut end = self.expect_token(RParen)?.end;
+
+        // a function right after the parentheses is passed last, like in
+        // `each(xs) fun(x) { ... }` or `each(xs) |x| ...`, but `||` after a
+        // call is an or, and one on the next line is a statement of its own
+        let lambda = self.peek_trailing_lambda(end);
+        let fun = self.lexer.peek().is_some_and(|token| {
+            token.kind == Fun && !self.source[end..token.span.start].contains('\n')
+        });
+        if fun || lambda {
+            let function = if lambda {
+                self.parse_lambda_expression()?
+            } else {
+                self.parse_function_expression()?
+            };
+            end = function.span.end;
+            arguments.push(FunctionArgument {
+                label: None,
+                value: function,
+                spread: false,
+            });
+        }
+
+        Ok((arguments, end))
+    }
+
+    fn parse_function_argument(&mut self) -> Result<FunctionArgument, Error> {
+        if self.peek_kind() == &Ellipsis {
+            self.expect_token(Ellipsis)?;
+            let value = self.parse_expression(Precedence::Lowest)?;
+            return Ok(FunctionArgument {
+                label: None,
+                value,
+                spread: true,
+            });
+        }
+
+        let mut label = None;
+        if self.peek_kind() == &Identifier && self.peek_nth_kind(1) == &Colon {
+            let (name, _) = self.expect_ident()?;
+            self.expect_token(Colon)?;
+            label = Some(name);
+        }
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        Ok(FunctionArgument {
+            label,
+            value,
+            spread: false,
+        })
+    }
+
+    fn parse_variable_declaration(&mut self) -> Result<Expression, Error> {
+        let doc = self.peek_doc_comment();
+        let start = self.lexer.peek().ok_or(Error::UnexpectedEof)?.span.start;
+        let attributes = self.parse_attributes()?;
+        let (name, _) = self.expect_ident()?;
+        self.expect_token(Colon)?;
+
+        // `name: while ...` labels a loop instead of declaring a variable
+        if let While | Loop | For = self.peek_kind() {
+            if !attributes.is_empty() {
+                return Err(Error::syntax_err(
+                    "attributes can only go in front of a declaration",
+                ));
+            }
+            let label = Some(name);
+            let mut expr = match self.peek_kind() {
+                While => self.parse_while_expression(label)?,
+                Loop => self.parse_loop_expression(label)?,
+                _ => self.parse_for_expression(label)?,
+            };
+            expr.span.start = start;
+            return Ok(expr);
+        }
+
+        let mut ty = None;
+        if let Identifier | Fun | LParen = self.peek_kind() {
+            let ty_start = self.lexer.peek().ok_or(Error::UnexpectedEof)?.span.start;
+            ty = Some((
+                self.parse_type()?,
+                Span::from_range(ty_start, self.lexer.last_end()),
+            ));
+        }
+
+        #[allow(clippy::needless_late_init)]
+        let value: Expression;
+        let mutable: bool;
+        let separator: Span;
+
+        match self.peek_kind() {
+            Colon => {
+                separator = self.expect_token(Colon)?;
                 mutable = false;
                 value = self.parse_expression(Precedence::Lowest)?;
             }
             SingleEqual => {
-                self.expect_token(SingleEqual)?;
+                separator = self.expect_token(SingleEqual)?;
                 mutable = true;
                 value = self.parse_expression(Precedence::Lowest)?;
             }
@@ -357,23 +1052,80 @@ impl<'src> Parser<'src> {
         let end_position = value.span.end;
         Ok(Expression::new(
             ExpressionKind::VariableDecl {
-                name: name.to_string(),
+                name,
                 value: Box::new(value),
                 mutable,
                 ty,
+                separator,
+                doc,
+                attributes,
             },
-            Span::from_range(start_position, end_position),
+            Span::from_range(start, end_position),
         ))
     }
 
     fn parse_function_expression(&mut self) -> Result<Expression, Error> {
         let start_span = self.expect_token(Fun)?;
+
+        let mut type_parameters = vec![];
+
+        if self.peek_kind() == &LBracket {
+            self.expect_token(LBracket)?;
+            while self.peek_kind() != &RBracket {
+                type_parameters.push(self.parse_type_parameter()?);
+                if self.peek_kind() == &Comma {
+                    self.expect_token(Comma)?;
+                    continue;
+                } else {
+                    break;
+                }
+            }
+            self.expect_token(RBracket)?;
+        }
+
         self.expect_token(LParen)?;
 
         let mut parameters = vec![];
+        let mut names: Vec<(String, Span)> = vec![];
+        let mut labels: Vec<(String, Span)> = vec![];
 
         while self.peek_kind() != &RParen {
-            parameters.push(self.parse_function_parameter()?);
+            if let Some(FunctionParameter::Rest { .. }) = parameters.last() {
+                let (_, span) = names.last().expect("a rest parameter has a name");
+                return Err(Error::RestParameterNotLast { span: *span });
+            }
+            let (parameter, span) = self.parse_function_parameter()?;
+            match &parameter {
+                FunctionParameter::LabeledAtCallsite {
+                    internal_name,
+                    external_name,
+                    label_span,
+                    ..
+                } => {
+                    add_parameter_name(&mut names, internal_name, span)?;
+                    // calls tell labeled parameters apart by their labels
+                    let label = external_name.as_ref().unwrap_or(internal_name);
+                    let label_span = *label_span;
+                    if let Some((_, first)) = labels.iter().find(|(other, _)| other == label) {
+                        return Err(Error::DuplicateLabel {
+                            first: *first,
+                            second: label_span,
+                        });
+                    }
+                    labels.push((label.clone(), label_span));
+                }
+                FunctionParameter::UnlabeledAtCallsite { name, .. }
+                | FunctionParameter::Rest { name, .. } => {
+                    add_parameter_name(&mut names, name, span)?;
+                }
+                FunctionParameter::Pattern { pattern, .. } => {
+                    for (name, span) in pattern.bindings() {
+                        add_parameter_name(&mut names, name, span)?;
+                    }
+                }
+                FunctionParameter::SelfParameter => {}
+            }
+            parameters.push(parameter);
             if self.peek_kind() == &Comma {
                 self.expect_token(Comma)?;
                 continue;
@@ -386,118 +1138,2267 @@ impl<'src> Parser<'src> {
 
         let mut return_type = None;
 
-        if let Identifier = self.peek_kind() {
+        if let Identifier | LParen | Fun = self.peek_kind() {
             return_type = Some(self.parse_type()?);
         }
 
+        let (body, end_position) = self.parse_block()?;
+
+        Ok(Expression::new(
+            ExpressionKind::Function {
+                type_parameters,
+                parameters,
+                return_type,
+                body,
+            },
+            Span::from_range(start_span.start, end_position),
+        ))
+    }
+
+    /// Parses the shorthand `|x, y| x + y` for `fun(~x, ~y) { x + y }`, whose
+    /// parameters are all unlabeled at the call site. `||` starts one without
+    /// parameters, and the body reaches as far as an expression can.
+    fn parse_lambda_expression(&mut self) -> Result<Expression, Error> {
+        let (parameters, start) = self.parse_lambda_parameters()?;
+        let body = self.parse_expression(Precedence::Lowest)?;
+        let span = Span::from_range(start, body.span.end);
+        Ok(Expression::new(
+            ExpressionKind::Function {
+                type_parameters: vec![],
+                parameters,
+                return_type: None,
+                body: vec![ExpressionStatement {
+                    expr: body,
+                    discarded: false,
+                }],
+            },
+            span,
+        ))
+    }
+
+    /// Parses the `|x, y|` or `||` a lambda starts with, returning its
+    /// parameters and where it starts.
+    fn parse_lambda_parameters(&mut self) -> Result<(Vec<FunctionParameter>, usize), Error> {
+        let mut parameters = vec![];
+        let start = if self.peek_kind() == &PipePipe {
+            self.expect_token(PipePipe)?.start
+        } else {
+            let start = self.expect_token(BitOr)?.start;
+            let mut names = vec![];
+            while self.peek_kind() != &BitOr {
+                if self.peek_kind() == &LParen {
+                    let pattern = self.parse_pattern()?;
+                    for (name, span) in pattern.bindings() {
+                        add_parameter_name(&mut names, name, span)?;
+                    }
+                    let ty = self.parse_parameter_type()?;
+                    parameters.push(FunctionParameter::Pattern { pattern, ty });
+                } else {
+                    let (name, span) = self.expect_ident()?;
+                    add_parameter_name(&mut names, &name, span)?;
+                    let ty = self.parse_parameter_type()?;
+                    parameters.push(FunctionParameter::UnlabeledAtCallsite { name, ty });
+                }
+                if self.peek_kind() == &Comma {
+                    self.expect_token(Comma)?;
+                } else {
+                    break;
+                }
+            }
+            self.expect_token(BitOr)?;
+            start
+        };
+        Ok((parameters, start))
+    }
+
+    /// Whether a `|` after a call that ends at `call_end` starts a lambda
+    /// that's passed to it last. It only does when its parameter list is
+    /// closed by another `|` on the same line as the call, so `f(x) | y`
+    /// isn't taken for one, but `f(x) | y | z` passes `|y| z`.
+    fn peek_trailing_lambda(&mut self, call_end: usize) -> bool {
+        if self.peek_kind() != &BitOr || self.peek_custom_operator().is_some() {
+            return false;
+        }
+        let checkpoint = self.checkpoint();
+        let is_lambda = self.parse_lambda_parameters().is_ok()
+            && !self.source[call_end..self.lexer.last_end()].contains('\n');
+        self.rewind(checkpoint);
+        is_lambda
+    }
+
+    fn parse_impl_block(&mut self) -> Result<Expression, Error> {
+        let start_span = self.expect_token(Impl)?;
+        let ty = self.parse_type()?;
         self.expect_token(LBrace)?;
 
-        let mut body = vec![];
+        let mut methods = vec![];
 
         while self.peek_kind() != &RBrace {
-            body.push(self.parse_expression_statement()?);
+            let doc = self.peek_doc_comment();
+            let attributes = self.parse_attributes()?;
+            let (name, _) = self.expect_ident()?;
+            self.expect_token(Colon)?;
+            self.expect_token(Colon)?;
+            let function = self.parse_function_expression()?;
+            methods.push(Method {
+                name,
+                function,
+                doc,
+                attributes,
+            });
+
+            if self.check_semicolon()? {
+                self.expect_token(Semicolon)?;
+            }
         }
 
         let end_span = self.expect_token(RBrace)?;
 
         Ok(Expression::new(
-            ExpressionKind::Function {
-                parameters,
-                return_type,
-                body,
-            },
+            ExpressionKind::Impl { ty, methods },
             Span::from_range(start_span.start, end_span.end),
         ))
     }
 
-    fn parse_function_parameter(&mut self) -> Result<FunctionParameter, Error> {
-        match self.peek_kind() {
-            Tilde => {
-                self.expect_token(Tilde)?;
-                let (name, _) = self.expect_ident()?;
-                self.expect_token(Colon)?;
-                let ty = self.parse_type()?;
-                Ok(FunctionParameter::UnlabeledAtCallsite { name, ty })
+    /// Parses `struct Point { x: Int, y: Int }`, with a comma after each
+    /// field but the last, where it's optional.
+    fn parse_struct_declaration(&mut self) -> Result<Expression, Error> {
+        let doc = self.peek_doc_comment();
+        let start = self.lexer.peek().ok_or(Error::UnexpectedEof)?.span.start;
+        let attributes = self.parse_attributes()?;
+        self.expect_token(Struct)?;
+        let (name, _) = self.expect_ident()?;
+        self.expect_token(LBrace)?;
+
+        let mut fields: Vec<StructField> = vec![];
+        while self.peek_kind() != &RBrace {
+            let (name, span) = self.expect_ident()?;
+            if let Some(first) = fields.iter().find(|field| field.name == name) {
+                return Err(Error::DuplicateField {
+                    first: first.span,
+                    second: span,
+                });
             }
-            Identifier => {
-                let (first, _) = self.expect_ident()?;
-                let mut second = None;
-                if let Identifier = self.peek_kind() {
-                    let (name, _) = self.expect_ident()?;
-                    second = Some(name);
+            self.expect_token(Colon)?;
+            let ty = self.parse_type()?;
+            fields.push(StructField { name, ty, span });
+            if self.peek_kind() != &RBrace {
+                self.expect_token(Comma)?;
+            }
+        }
+
+        let end_span = self.expect_token(RBrace)?;
+        Ok(Expression::new(
+            ExpressionKind::Struct {
+                name,
+                fields,
+                doc,
+                attributes,
+            },
+            Span::from_range(start, end_span.end),
+        ))
+    }
+
+    /// Parses `enum Shape { Circle(radius: Float), Square(Float), Empty }`,
+    /// where each variant can have fields, labeled or not.
+    fn parse_enum_declaration(&mut self) -> Result<Expression, Error> {
+        let doc = self.peek_doc_comment();
+        let start = self.lexer.peek().ok_or(Error::UnexpectedEof)?.span.start;
+        let attributes = self.parse_attributes()?;
+        self.expect_token(Enum)?;
+        let (name, _) = self.expect_ident()?;
+        self.expect_token(LBrace)?;
+
+        let mut variants: Vec<EnumVariant> = vec![];
+        while self.peek_kind() != &RBrace {
+            let (name, span) = self.expect_ident()?;
+            if let Some(first) = variants.iter().find(|variant| variant.name == name) {
+                return Err(Error::DuplicateVariant {
+                    first: first.span,
+                    second: span,
+                });
+            }
+            let fields = if self.peek_kind() == &LParen {
+                self.parse_variant_fields()?
+            } else {
+                vec![]
+            };
+            variants.push(EnumVariant { name, fields, span });
+            if self.peek_kind() != &RBrace {
+                self.expect_token(Comma)?;
+            }
+        }
+
+        let end_span = self.expect_token(RBrace)?;
+        Ok(Expression::new(
+            ExpressionKind::Enum {
+                name,
+                variants,
+                doc,
+                attributes,
+            },
+            Span::from_range(start, end_span.end),
+        ))
+    }
+
+    /// Parses the fields of an enum variant, like `(radius: Float)`.
+    fn parse_variant_fields(&mut self) -> Result<Vec<VariantField>, Error> {
+        self.expect_token(LParen)?;
+        let mut fields = vec![];
+        let mut labels: Vec<(String, Span)> = vec![];
+        while self.peek_kind() != &RParen {
+            let mut label = None;
+            if self.peek_kind() == &Identifier && self.peek_nth_kind(1) == &Colon {
+                let (name, span) = self.expect_ident()?;
+                if let Some((_, first)) = labels.iter().find(|(other, _)| *other == name) {
+                    return Err(Error::DuplicateField {
+                        first: *first,
+                        second: span,
+                    });
                 }
+                labels.push((name.clone(), span));
                 self.expect_token(Colon)?;
-                let ty = self.parse_type()?;
-                Ok(FunctionParameter::LabeledAtCallsite {
-                    internal_name: if let Some(second_ident) = &second {
-                        second_ident.to_string()
-                    } else {
-                        first.clone()
-                    },
-                    external_name: if second.is_some() { Some(first) } else { None },
-                    ty,
-                })
+                label = Some(name);
+            }
+            let ty = self.parse_type()?;
+            fields.push(VariantField { label, ty });
+            if self.peek_kind() != &RParen {
+                self.expect_token(Comma)?;
             }
-            tok => Err(Error::expected("parameter name", &tok.to_string())),
         }
+        self.expect_token(RParen)?;
+        Ok(fields)
     }
 
-    fn check_semicolon(&mut self) -> Result<bool, Error> {
-        Ok(self.peek_kind() == &Semicolon)
+    /// Parses `operator <+> (precedence: 6, assoc: left, method: combine)`,
+    /// where only the precedence is required. The operator is left associative
+    /// by default.
+    fn parse_operator_declaration(&mut self) -> Result<Expression, Error> {
+        let Span { start, .. } = self.expect_token(Operator)?;
+        let symbol_tokens = self.operator_tokens();
+        if symbol_tokens == 0 {
+            return Err(Error::expected("operator", &self.peek_kind().to_string()));
+        }
+        let mut symbol = String::new();
+        for _ in 0..symbol_tokens {
+            symbol.push_str(self.lexer.next().unwrap().span.slice(&self.source));
+        }
+        if TokenKind::SYMBOLS
+            .iter()
+            .any(|kind| kind.to_string() == symbol)
+        {
+            return Err(Error::syntax_err(&format!(
+                "`{symbol}` is a builtin operator"
+            )));
+        }
+        if self
+            .operators
+            .iter()
+            .any(|operator| operator.symbol == symbol)
+        {
+            return Err(Error::syntax_err(&format!(
+                "operator `{symbol}` is already declared"
+            )));
+        }
+
+        self.expect_token(LParen)?;
+        let mut precedence = None;
+        let mut associativity = Associativity::Left;
+        let mut method = None;
+        let end = loop {
+            if let RParen = self.peek_kind() {
+                break self.expect_token(RParen)?.end;
+            }
+            let (field, _) = self.expect_ident()?;
+            self.expect_token(Colon)?;
+            match field.as_str() {
+                "precedence" => {
+                    let (level, _) = self.expect_int()?;
+                    if !(1..=6).contains(&level) {
+                        return Err(Error::syntax_err(
+                            "operator precedence has to be from 1 to 6",
+                        ));
+                    }
+                    precedence = Some(level as u8);
+                }
+                "assoc" => {
+                    associativity = match self.expect_ident()?.0.as_str() {
+                        "left" => Associativity::Left,
+                        "right" => Associativity::Right,
+                        other => return Err(Error::expected("`left` or `right`", other)),
+                    }
+                }
+                "method" => method = Some(self.expect_ident()?.0),
+                other => {
+                    return Err(Error::expected("`precedence`, `assoc` or `method`", other));
+                }
+            }
+            if self.peek_kind() != &RParen {
+                self.expect_token(Comma)?;
+            }
+        };
+
+        let operator = CustomOperator {
+            symbol,
+            precedence: precedence.ok_or(Error::syntax_err("missing operator precedence"))?,
+            associativity,
+            method,
+        };
+        self.operators.push(operator.clone());
+        Ok(Expression::new(
+            ExpressionKind::OperatorDecl(operator),
+            Span::from_range(start, end),
+        ))
     }
 
-    fn expect_token(&mut self, kind: TokenKind) -> Result<Span, Error> {
-        let tok = self.lexer.next().ok_or(Error::UnexpectedEof)?;
-        if tok.kind != kind {
-            Err(Error::syntax_err(&format!(
-                "unexpected token: {}",
-                tok.kind
-            )))
-        } else {
-            Ok(tok.span)
+    fn parse_block(&mut self) -> Result<(Vec<ExpressionStatement>, usize), Error> {
+        self.expect_token(LBrace)?;
+
+        let mut body = vec![];
+
+        while self.peek_kind() != &RBrace {
+            body.push(self.parse_expression_statement()?);
         }
+
+        let end_span = self.expect_token(RBrace)?;
+
+        Ok((body, end_span.end))
     }
 
-    fn expect_int(&mut self) -> Result<(isize, Span), Error> {
-        let token = self.lexer.peek().ok_or(Error::UnexpectedEof)?;
-        let span = token.span;
-        if let IntLiteral = token.kind {
-            self.lexer.next();
-            Ok((span.slice(&self.source).parse().unwrap(), span))
-        } else {
-            Err(Error::expected("int literal", &token.kind.to_string()))
+    fn parse_block_expression(&mut self) -> Result<Expression, Error> {
+        let start = self.lexer.peek().unwrap().span.start;
+        let (body, end) = self.parse_block()?;
+        Ok(Expression::new(
+            ExpressionKind::Block(body),
+            Span::from_range(start, end),
+        ))
+    }
+
+    fn parse_while_expression(&mut self, label: Option<String>) -> Result<Expression, Error> {
+        let start_span = self.expect_token(While)?;
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        let (body, end_position) = self.parse_loop_body(label.as_deref())?;
+
+        Ok(Expression::new(
+            ExpressionKind::While {
+                label,
+                condition: Box::new(condition),
+                body,
+            },
+            Span::from_range(start_span.start, end_position),
+        ))
+    }
+
+    fn parse_loop_expression(&mut self, label: Option<String>) -> Result<Expression, Error> {
+        let start_span = self.expect_token(Loop)?;
+        let (body, end_position) = self.parse_loop_body(label.as_deref())?;
+
+        Ok(Expression::new(
+            ExpressionKind::Loop { label, body },
+            Span::from_range(start_span.start, end_position),
+        ))
+    }
+
+    fn parse_for_expression(&mut self, label: Option<String>) -> Result<Expression, Error> {
+        let start_span = self.expect_token(For)?;
+        let pattern = self.parse_pattern()?;
+        self.expect_token(In)?;
+        let iterable = self.parse_expression(Precedence::Lowest)?;
+        let (body, end_position) = self.parse_loop_body(label.as_deref())?;
+
+        Ok(Expression::new(
+            ExpressionKind::For {
+                label,
+                pattern: Box::new(pattern),
+                iterable: Box::new(iterable),
+                body,
+            },
+            Span::from_range(start_span.start, end_position),
+        ))
+    }
+
+    fn parse_loop_body(
+        &mut self,
+        label: Option<&str>,
+    ) -> Result<(Vec<ExpressionStatement>, usize), Error> {
+        self.loop_labels.extend(label.map(str::to_string));
+        let body = self.parse_block();
+        if label.is_some() {
+            self.loop_labels.pop();
         }
+        body
     }
 
-    fn expect_ident(&mut self) -> Result<(String, Span), Error> {
-        let token = self.lexer.peek().ok_or(Error::UnexpectedEof)?;
-        let kind = token.kind.clone();
-        let span = token.span;
-        if let Identifier = kind {
-            self.lexer.next();
-            // up to you whether you want to return a `String` or a `&str`
-            Ok((span.slice(&self.source).to_owned(), span))
-        } else {
-            Err(Error::expected("identifier", &kind.to_string()))
+    fn parse_break_expression(&mut self) -> Result<Expression, Error> {
+        let span = self.expect_token(Break)?;
+        let label = self.parse_loop_label()?;
+
+        let mut end_position = span.end;
+        let mut value = None;
+        if !matches!(self.peek_kind(), Semicolon | RBrace | RParen | Comma | Eof) {
+            let expr = self.parse_expression(Precedence::Lowest)?;
+            end_position = expr.span.end;
+            value = Some(Box::new(expr));
+        } else if let Some(label_span) = label.as_ref().map(|(_, span)| *span) {
+            end_position = label_span.end;
         }
+
+        Ok(Expression::new(
+            ExpressionKind::Break {
+                label: label.map(|(label, _)| label),
+                value,
+            },
+            Span::from_range(span.start, end_position),
+        ))
     }
 
-    fn cur_precedence(&mut self) -> Result<Precedence, Error> {
-        Ok(match self.peek_kind() {
-            DoubleEqual | NotEqual => Precedence::Equality,
-            LessThan | GreaterThan | LessThanOrEqual | GreaterThanOrEqual => Precedence::Comparison,
-            Plus | Minus => Precedence::Sum,
-            Asterisk | Slash => Precedence::Product,
-            LParen => Precedence::Group,
-            _ => Precedence::Lowest,
-        })
+    fn parse_continue_expression(&mut self) -> Result<Expression, Error> {
+        let span = self.expect_token(Continue)?;
+        let label = self.parse_loop_label()?;
+        let end_position = label.as_ref().map_or(span.end, |(_, span)| span.end);
+
+        Ok(Expression::new(
+            ExpressionKind::Continue {
+                label: label.map(|(label, _)| label),
+            },
+            Span::from_range(span.start, end_position),
+        ))
     }
 
-    fn cur_kind(&mut self) -> TokenKind {
-        self.lexer.next().map_or(TokenKind::Eof, |t| t.kind)
+    fn parse_return_expression(&mut self) -> Result<Expression, Error> {
+        let span = self.expect_token(Return)?;
+        let mut end_position = span.end;
+        let mut value = None;
+        if !matches!(self.peek_kind(), Semicolon | RBrace | RParen | Comma | Eof) {
+            let expr = self.parse_expression(Precedence::Lowest)?;
+            end_position = expr.span.end;
+            value = Some(Box::new(expr));
+        }
+
+        Ok(Expression::new(
+            ExpressionKind::Return(value),
+            Span::from_range(span.start, end_position),
+        ))
     }
 
-    fn peek_kind(&mut self) -> &TokenKind {
-        self.lexer.peek().map_or(&TokenKind::Eof, |t| &t.kind)
+    /// An identifier after `break` or `continue` is only a label if it names
+    /// one of the enclosing loops, otherwise it's the value being broken with.
+    fn parse_loop_label(&mut self) -> Result<Option<(String, Span)>, Error> {
+        let Some(token) = self.lexer.peek() else {
+            return Ok(None);
+        };
+        if token.kind != Identifier
+            || !self
+                .loop_labels
+                .iter()
+                .any(|l| l == token.span.slice(&self.source))
+        {
+            return Ok(None);
+        }
+        Ok(Some(self.expect_ident()?))
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Expression, Error> {
+        let start_span = self.expect_token(Match)?;
+        let value = self.parse_expression(Precedence::Lowest)?;
+        self.expect_token(LBrace)?;
+
+        let mut arms = vec![];
+
+        while self.peek_kind() != &RBrace {
+            let pattern = self.parse_pattern()?;
+            self.expect_token(FatArrow)?;
+            let body = self.parse_expression(Precedence::Lowest)?;
+            arms.push(MatchArm { pattern, body });
+
+            if self.peek_kind() == &Comma {
+                self.expect_token(Comma)?;
+                continue;
+            } else {
+                break;
+            }
+        }
+
+        let end_span = self.expect_token(RBrace)?;
+
+        Ok(Expression::new(
+            ExpressionKind::Match {
+                value: Box::new(value),
+                arms,
+            },
+            Span::from_range(start_span.start, end_span.end),
+        ))
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, Error> {
+        match self.peek_kind() {
+            Unit => {
+                let span = self.expect_token(Unit)?;
+                Ok(Pattern::new(PatternKind::Unit, span))
+            }
+            True => {
+                let span = self.expect_token(True)?;
+                Ok(Pattern::new(PatternKind::BoolLiteral(true), span))
+            }
+            False => {
+                let span = self.expect_token(False)?;
+                Ok(Pattern::new(PatternKind::BoolLiteral(false), span))
+            }
+            IntLiteral => {
+                let (num, span) = self.expect_int()?;
+                Ok(Pattern::new(PatternKind::IntLiteral(num.into()), span))
+            }
+            Minus => {
+                let start_span = self.expect_token(Minus)?;
+                let (num, span) = self.expect_int()?;
+                Ok(Pattern::new(
+                    PatternKind::IntLiteral(-i128::from(num)),
+                    Span::from_range(start_span.start, span.end),
+                ))
+            }
+            Identifier => {
+                let (name, span) = self.expect_ident()?;
+                if name == "_" {
+                    return Ok(Pattern::new(PatternKind::Wildcard, span));
+                }
+                if self.peek_kind() != &Dot {
+                    return Ok(Pattern::new(PatternKind::Binding(name), span));
+                }
+
+                self.expect_token(Dot)?;
+                let (variant, variant_span) = self.expect_ident()?;
+                let mut fields = vec![];
+                let mut end_position = variant_span.end;
+                if self.peek_kind() == &LParen {
+                    self.expect_token(LParen)?;
+                    while self.peek_kind() != &RParen {
+                        fields.push(self.nested(|parser| parser.parse_variant_field_pattern())?);
+                        if self.peek_kind() == &Comma {
+                            self.expect_token(Comma)?;
+                            continue;
+                        } else {
+                            break;
+                        }
+                    }
+                    end_position = self.expect_token(RParen)?.end;
+                }
+
+                Ok(Pattern::new(
+                    PatternKind::Variant {
+                        ty: name,
+                        variant,
+                        fields,
+                    },
+                    Span::from_range(span.start, end_position),
+                ))
+            }
+            // `(p)` only groups, like in types
+            LParen => self.nested(|parser| {
+                let start = parser.expect_token(LParen)?.start;
+                let first = parser.parse_pattern()?;
+                if parser.peek_kind() == &RParen {
+                    let end = parser.expect_token(RParen)?.end;
+                    return Ok(Pattern::new(first.kind, Span::from_range(start, end)));
+                }
+                let mut elements = vec![first];
+                while parser.peek_kind() == &Comma {
+                    parser.expect_token(Comma)?;
+                    if parser.peek_kind() == &RParen {
+                        break;
+                    }
+                    elements.push(parser.parse_pattern()?);
+                }
+                let end = parser.expect_token(RParen)?.end;
+                Ok(Pattern::new(
+                    PatternKind::Tuple(elements),
+                    Span::from_range(start, end),
+                ))
+            }),
+            tok => Err(Error::expected("pattern", &tok.to_string())),
+        }
+    }
+
+    fn parse_variant_field_pattern(&mut self) -> Result<VariantFieldPattern, Error> {
+        let mut label = None;
+        if self.peek_kind() == &Identifier && self.peek_nth_kind(1) == &Colon {
+            let (name, _) = self.expect_ident()?;
+            self.expect_token(Colon)?;
+            label = Some(name);
+        }
+
+        let pattern = self.parse_pattern()?;
+        Ok(VariantFieldPattern { label, pattern })
+    }
+
+    fn parse_type_parameter(&mut self) -> Result<TypeParameter, Error> {
+        let (name, span) = self.expect_ident()?;
+        let mut end_position = span.end;
+
+        let mut bounds = vec![];
+        if self.peek_kind() == &Colon {
+            self.expect_token(Colon)?;
+            loop {
+                let (bound, bound_span) = self.expect_ident()?;
+                bounds.push((bound, bound_span));
+                end_position = bound_span.end;
+                if self.peek_kind() == &Plus {
+                    self.expect_token(Plus)?;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        Ok(TypeParameter {
+            name,
+            bounds,
+            span: Span::from_range(span.start, end_position),
+        })
+    }
+
+    /// Parses a parameter, returning the span of its name along with it.
+    fn parse_function_parameter(&mut self) -> Result<(FunctionParameter, Span), Error> {
+        match self.peek_kind() {
+            SelfValue => {
+                let span = self.expect_token(SelfValue)?;
+                Ok((FunctionParameter::SelfParameter, span))
+            }
+            Tilde => {
+                self.expect_token(Tilde)?;
+                let (name, span) = self.expect_ident()?;
+                let ty = self.parse_parameter_type()?;
+                Ok((FunctionParameter::UnlabeledAtCallsite { name, ty }, span))
+            }
+            Ellipsis => {
+                self.expect_token(Ellipsis)?;
+                let (name, span) = self.expect_ident()?;
+                let ty = self.parse_parameter_type()?;
+                Ok((FunctionParameter::Rest { name, ty }, span))
+            }
+            LParen => {
+                let pattern = self.parse_pattern()?;
+                let span = pattern.span;
+                let ty = self.parse_parameter_type()?;
+                Ok((FunctionParameter::Pattern { pattern, ty }, span))
+            }
+            Identifier => {
+                let (first, first_span) = self.expect_ident()?;
+                let mut second = None;
+                if let Identifier = self.peek_kind() {
+                    second = Some(self.expect_ident()?);
+                }
+                let ty = self.parse_parameter_type()?;
+                Ok(match second {
+                    Some((internal_name, span)) => (
+                        FunctionParameter::LabeledAtCallsite {
+                            internal_name,
+                            external_name: Some(first),
+                            ty,
+                            label_span: first_span,
+                        },
+                        span,
+                    ),
+                    None => (
+                        FunctionParameter::LabeledAtCallsite {
+                            internal_name: first,
+                            external_name: None,
+                            ty,
+                            label_span: first_span,
+                        },
+                        first_span,
+                    ),
+                })
+            }
+            tok => Err(Error::expected("parameter name", &tok.to_string())),
+        }
+    }
+
+    /// Parameter types can be left off when they can be inferred from where
+    /// the function is used.
+    fn parse_parameter_type(&mut self) -> Result<Option<Type>, Error> {
+        if self.peek_kind() != &Colon {
+            return Ok(None);
+        }
+        self.expect_token(Colon)?;
+        Ok(Some(self.parse_type()?))
+    }
+
+    fn check_semicolon(&mut self) -> Result<bool, Error> {
+        Ok(self.peek_kind() == &Semicolon)
+    }
+
+    fn expect_token(&mut self, kind: TokenKind) -> Result<Span, Error> {
+        let tok = self.lexer.next().ok_or(Error::UnexpectedEof)?;
+        if tok.kind != kind {
+            Err(Error::syntax_err(&format!(
+                "unexpected token: {}",
+                tok.kind
+            )))
+        } else {
+            Ok(tok.span)
+        }
+    }
+
+    fn expect_int(&mut self) -> Result<(u64, Span), Error> {
+        let token = self.lexer.peek().ok_or(Error::UnexpectedEof)?;
+        let span = token.span;
+        if let IntLiteral = token.kind {
+            self.lexer.next();
+            let (value, suffix) = int_value(span.slice(&self.source));
+            if suffix.is_some() {
+                return Err(Error::syntax_err(
+                    "int literal suffixes aren't allowed here",
+                ));
+            }
+            let value = value.ok_or(Error::IntLiteralOutOfRange { span })?;
+            Ok((value, span))
+        } else {
+            Err(Error::expected("int literal", &token.kind.to_string()))
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<(String, Span), Error> {
+        let token = self.lexer.peek().ok_or(Error::UnexpectedEof)?;
+        let span = token.span;
+        if let StringLiteral = token.kind {
+            self.lexer.next();
+            let literal = span.slice(&self.source);
+            Ok((string_value(literal), span))
+        } else {
+            Err(Error::expected("string literal", &token.kind.to_string()))
+        }
+    }
+
+    fn expect_char(&mut self) -> Result<(char, Span), Error> {
+        let token = self.lexer.peek().ok_or(Error::UnexpectedEof)?;
+        let span = token.span;
+        if let CharLiteral = token.kind {
+            self.lexer.next();
+            let literal = span.slice(&self.source);
+            // the lexer makes sure there's exactly one (possibly escaped) char
+            let value = unescape(&literal[1..literal.len() - 1])
+                .chars()
+                .next()
+                .unwrap();
+            Ok((value, span))
+        } else {
+            Err(Error::expected("char literal", &token.kind.to_string()))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<(String, Span), Error> {
+        let token = self.lexer.peek().ok_or(Error::UnexpectedEof)?;
+        let kind = token.kind.clone();
+        let span = token.span;
+        if let Identifier = kind {
+            self.lexer.next();
+            // up to you whether you want to return a `String` or a `&str`
+            Ok((span.slice(&self.source).to_owned(), span))
+        } else {
+            Err(Error::expected("identifier", &kind.to_string()))
+        }
+    }
+
+    fn cur_precedence(&mut self) -> Result<Precedence, Error> {
+        if let Some((operator, _)) = self.peek_custom_operator() {
+            return Ok(operator.precedence());
+        }
+        if let Some(operator) = infix_operator(self.peek_kind()) {
+            return Ok(operator.precedence());
+        }
+        Ok(match self.peek_kind() {
+            LessThan | GreaterThan | LessThanOrEqual | GreaterThanOrEqual => Precedence::Comparison,
+            DotDot | DotDotEqual => Precedence::Range,
+            SingleEqual => Precedence::Assignment,
+            Dot | QuestionDot | Question | LBracket | LParen => Precedence::Access,
+            _ => Precedence::Lowest,
+        })
+    }
+
+    /// How many of the next tokens are made up of operator characters, with
+    /// nothing in between them.
+    fn operator_tokens(&mut self) -> usize {
+        let mut count = 0;
+        let mut end = None;
+        while let Some(token) = self.lexer.peek_nth(count) {
+            let text = token.span.slice(&self.source);
+            if end.is_some_and(|end| end != token.span.start)
+                || !TokenKind::SYMBOLS.contains(&token.kind)
+                || !text.chars().all(|c| OPERATOR_CHARS.contains(c))
+            {
+                break;
+            }
+            end = Some(token.span.end);
+            count += 1;
+        }
+        count
+    }
+
+    /// The longest declared operator the next tokens spell out, and how many
+    /// tokens that takes.
+    fn peek_custom_operator(&mut self) -> Option<(CustomOperator, usize)> {
+        if self.operators.is_empty() {
+            return None;
+        }
+        let mut symbol = String::new();
+        let mut found = None;
+        for n in 0..self.operator_tokens() {
+            symbol.push_str(self.lexer.peek_nth(n).unwrap().span.slice(&self.source));
+            if let Some(operator) = self.operators.iter().find(|op| op.symbol == symbol) {
+                found = Some((operator.clone(), n + 1));
+            }
+        }
+        found
+    }
+
+    fn cur_kind(&mut self) -> TokenKind {
+        self.lexer.next().map_or(TokenKind::Eof, |t| t.kind)
+    }
+
+    fn peek_kind(&mut self) -> &TokenKind {
+        self.lexer.peek().map_or(&TokenKind::Eof, |t| &t.kind)
+    }
+
+    fn peek_nth_kind(&mut self, n: usize) -> &TokenKind {
+        self.lexer.peek_nth(n).map_or(&TokenKind::Eof, |t| &t.kind)
+    }
+
+    /// The text of the doc comment right before the next token, without the
+    /// `///` or the space after it on each line.
+    fn peek_doc_comment(&mut self) -> Option<String> {
+        self.lexer.peek()?;
+        let lines = &self.lexer.doc_comments[self.lexer.position];
+        if lines.is_empty() {
+            return None;
+        }
+        let lines = lines.iter().map(|span| {
+            let line = span.slice(&self.source)["///".len()..].trim_end();
+            line.strip_prefix(' ').unwrap_or(line)
+        });
+        Some(lines.collect::<Vec<_>>().join("\n"))
+    }
+}
+
+/// The lexer's tokens, with as much lookahead as the parser asks for. Tokens
+/// are kept after they're consumed so the parser can rewind to a checkpoint.
+/// Doc comments are set aside, since the parser only looks for them in front
+/// of declarations.
+struct Tokens<'src> {
+    lexer: Lexer<'src>,
+    tokens: Vec<Token>,
+    /// The lines of the doc comment right before each of the tokens, if any.
+    doc_comments: Vec<Vec<Span>>,
+    position: usize,
+}
+
+impl Tokens<'_> {
+    fn peek(&mut self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    /// The token after the next `n` ones, so `peek_nth(0)` is the next token.
+    fn peek_nth(&mut self, n: usize) -> Option<&Token> {
+        while self.tokens.len() <= self.position + n {
+            let mut doc_comment = vec![];
+            let token = loop {
+                let token = self.lexer.next()?;
+                if token.kind != TokenKind::DocComment {
+                    break token;
+                }
+                doc_comment.push(token.span);
+            };
+            self.tokens.push(token);
+            self.doc_comments.push(doc_comment);
+        }
+        self.tokens.get(self.position + n)
+    }
+
+    /// Lexes the rest of the source, which is otherwise lexed as it's needed.
+    fn lex_all(&mut self) {
+        let mut n = 0;
+        while self.peek_nth(n).is_some() {
+            n += 1;
+        }
+    }
+
+    /// Where the last token that was taken ends.
+    fn last_end(&self) -> usize {
+        self.tokens[..self.position]
+            .last()
+            .map_or(0, |token| token.span.end)
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek()?.clone();
+        self.position += 1;
+        Some(token)
+    }
+}
+
+/// A point in the token stream the parser can go back to, from [`Parser::checkpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Checkpoint {
+    position: usize,
+    loop_labels: usize,
+    operators: usize,
+}
+
+/// The characters declared operators can be made of.
+pub(super) const OPERATOR_CHARS: &str = "+-*/<>=!&|^~";
+
+/// Adds the name of a parameter to the ones before it, which it can't repeat.
+fn add_parameter_name(
+    names: &mut Vec<(String, Span)>,
+    name: &str,
+    span: Span,
+) -> Result<(), Error> {
+    if let Some((_, first)) = names.iter().find(|(other, _)| other == name) {
+        return Err(Error::DuplicateParameter {
+            first: *first,
+            second: span,
+        });
+    }
+    names.push((name.to_string(), span));
+    Ok(())
+}
+
+/// The operators that are parsed as a plain [`ExpressionKind::Infix`], which
+/// leaves out the comparisons since they can be chained.
+fn infix_operator(kind: &TokenKind) -> Option<InfixOperator> {
+    match kind {
+        Plus => Some(InfixOperator::Plus),
+        Minus => Some(InfixOperator::Minus),
+        Asterisk => Some(InfixOperator::Multiply),
+        Slash => Some(InfixOperator::Divide),
+        DoubleAsterisk => Some(InfixOperator::Power),
+        Compose => Some(InfixOperator::Compose),
+        Pipe => Some(InfixOperator::Pipe),
+        DoubleEqual => Some(InfixOperator::Equal),
+        NotEqual => Some(InfixOperator::NotEqual),
+        ShiftLeft => Some(InfixOperator::ShiftLeft),
+        ShiftRight => Some(InfixOperator::ShiftRight),
+        AmpAmp => Some(InfixOperator::And),
+        PipePipe => Some(InfixOperator::Or),
+        QuestionQuestion => Some(InfixOperator::Coalesce),
+        _ => None,
+    }
+}
+
+fn comparison_operator(kind: &TokenKind) -> Option<InfixOperator> {
+    match kind {
+        LessThan => Some(InfixOperator::LessThan),
+        GreaterThan => Some(InfixOperator::GreaterThan),
+        LessThanOrEqual => Some(InfixOperator::LessThanOrEqual),
+        GreaterThanOrEqual => Some(InfixOperator::GreaterThanOrEqual),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse_expression(input: &str) -> Expression {
+        let mut parser = Parser::new(input);
+        let Program(mut stmts) = parser.parse().unwrap();
+        assert_eq!(stmts.len(), 1);
+        stmts.remove(0).expr
+    }
+
+    fn ident(name: &str, start: usize) -> Expression {
+        Expression::new(
+            ExpressionKind::Ident(name.to_string()),
+            Span::from_range(start, start + name.len()),
+        )
+    }
+
+    #[test]
+    fn enum_variants() {
+        assert_eq!(
+            parse_expression("Color.Red"),
+            Expression::new(
+                ExpressionKind::FieldAccess {
+                    target: Box::new(ident("Color", 0)),
+                    field: "Red".to_string(),
+                },
+                Span::from_range(0, 9),
+            )
+        );
+
+        assert_eq!(
+            parse_expression("Shape.Circle(radius: 3)"),
+            Expression::new(
+                ExpressionKind::MethodCall {
+                    receiver: Box::new(ident("Shape", 0)),
+                    method: "Circle".to_string(),
+                    arguments: vec![FunctionArgument {
+                        label: Some("radius".to_string()),
+                        value: Expression::new(
+                            ExpressionKind::IntLiteral(3),
+                            Span::from_range(21, 22)
+                        ),
+                        spread: false,
+                    }],
+                },
+                Span::from_range(0, 23),
+            )
+        );
+    }
+
+    #[test]
+    fn variant_patterns() {
+        let input =
+            "match shape { Shape.Circle(radius) => radius, Shape.Rect(size: Size.New(w, _)) => w }";
+        let ExpressionKind::Match { arms, .. } = parse_expression(input).kind else {
+            panic!("expected a match expression");
+        };
+        let patterns = arms.into_iter().map(|arm| arm.pattern).collect::<Vec<_>>();
+
+        assert_eq!(
+            patterns,
+            vec![
+                Pattern::new(
+                    PatternKind::Variant {
+                        ty: "Shape".to_string(),
+                        variant: "Circle".to_string(),
+                        fields: vec![VariantFieldPattern {
+                            label: None,
+                            pattern: Pattern::new(
+                                PatternKind::Binding("radius".to_string()),
+                                Span::from_range(27, 33),
+                            ),
+                        }],
+                    },
+                    Span::from_range(14, 34),
+                ),
+                Pattern::new(
+                    PatternKind::Variant {
+                        ty: "Shape".to_string(),
+                        variant: "Rect".to_string(),
+                        fields: vec![VariantFieldPattern {
+                            label: Some("size".to_string()),
+                            pattern: Pattern::new(
+                                PatternKind::Variant {
+                                    ty: "Size".to_string(),
+                                    variant: "New".to_string(),
+                                    fields: vec![
+                                        VariantFieldPattern {
+                                            label: None,
+                                            pattern: Pattern::new(
+                                                PatternKind::Binding("w".to_string()),
+                                                Span::from_range(72, 73),
+                                            ),
+                                        },
+                                        VariantFieldPattern {
+                                            label: None,
+                                            pattern: Pattern::new(
+                                                PatternKind::Wildcard,
+                                                Span::from_range(75, 76),
+                                            ),
+                                        },
+                                    ],
+                                },
+                                Span::from_range(63, 77),
+                            ),
+                        }],
+                    },
+                    Span::from_range(46, 78),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_parameters() {
+        let ExpressionKind::Function {
+            type_parameters, ..
+        } = parse_expression("fun[T: Comparable + Show, U](x: T, y: U) Bool { x < y }").kind
+        else {
+            panic!("expected a function expression");
+        };
+
+        assert_eq!(
+            type_parameters,
+            vec![
+                TypeParameter {
+                    name: "T".to_string(),
+                    bounds: vec![
+                        ("Comparable".to_string(), Span::from_range(7, 17)),
+                        ("Show".to_string(), Span::from_range(20, 24)),
+                    ],
+                    span: Span::from_range(4, 24),
+                },
+                TypeParameter {
+                    name: "U".to_string(),
+                    bounds: vec![],
+                    span: Span::from_range(26, 27),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn for_loops() {
+        let input = "rows: for x in xs { continue rows }";
+        let expr = parse_expression(input);
+        assert_eq!(expr.span, Span::from_range(0, input.len()));
+        let ExpressionKind::For {
+            label,
+            pattern,
+            iterable,
+            body,
+        } = expr.kind
+        else {
+            panic!("expected a for loop");
+        };
+        assert_eq!(label, Some("rows".to_string()));
+        assert_eq!(pattern.kind, PatternKind::Binding("x".to_string()));
+        assert_eq!(*iterable, ident("xs", 15));
+        assert_eq!(
+            body[0].expr.kind,
+            ExpressionKind::Continue {
+                label: Some("rows".to_string())
+            }
+        );
+
+        assert!(Parser::new("for x xs {}").parse().is_err());
+    }
+
+    #[test]
+    fn tuples() {
+        let ExpressionKind::Tuple(elements) = parse_expression("(a, (b,), (c))").kind else {
+            panic!("expected a tuple");
+        };
+        assert_eq!(elements.len(), 3);
+        assert!(matches!(&elements[1].kind, ExpressionKind::Tuple(inner) if inner.len() == 1));
+        // parentheses around a single element only group it
+        assert_eq!(
+            elements[2],
+            Expression::new(
+                ExpressionKind::Ident("c".to_string()),
+                Span::from_range(10, 13)
+            )
+        );
+
+        let expr = parse_expression("t.0.12");
+        assert_eq!(expr.span, Span::from_range(0, 6));
+        let ExpressionKind::FieldAccess { target, field } = expr.kind else {
+            panic!("expected a field access");
+        };
+        assert_eq!(field, "12");
+        assert_eq!(target.span, Span::from_range(0, 3));
+        assert!(matches!(target.kind, ExpressionKind::FieldAccess { field, .. } if field == "0"));
+
+        let ExpressionKind::VariableDecl { ty, .. } =
+            parse_expression("p : ((Int), (Bool,)) : x").kind
+        else {
+            panic!("expected a variable declaration");
+        };
+        let (ty, span) = ty.unwrap();
+        assert_eq!(ty.to_string(), "(Int, (Bool,))");
+        assert_eq!(span, Span::from_range(4, 20));
+
+        assert_eq!(
+            Parser::new("t.0u8").parse(),
+            Err(Error::expected("tuple index", "0u8"))
+        );
+    }
+
+    #[test]
+    fn function_types() {
+        let type_of = |input: &str| {
+            let ExpressionKind::VariableDecl { ty, .. } = parse_expression(input).kind else {
+                panic!("expected a variable declaration");
+            };
+            ty.unwrap().0.to_string()
+        };
+        assert_eq!(
+            type_of("f : fun(Int, (Bool,)) Int : g"),
+            "fun(Int, (Bool,)) Int"
+        );
+        assert_eq!(type_of("f : fun() : g"), "fun()");
+        // the return type goes as far as a type can
+        assert_eq!(
+            type_of("f : fun() fun(Int) (Int, Int) = g"),
+            "fun() fun(Int) (Int, Int)"
+        );
+
+        // a function can return one
+        let ExpressionKind::Function { return_type, .. } =
+            parse_expression("fun() fun(Int) { g }").kind
+        else {
+            panic!("expected a function");
+        };
+        assert_eq!(return_type.unwrap().to_string(), "fun(Int)");
+    }
+
+    #[test]
+    fn type_arguments() {
+        let ExpressionKind::Function { parameters, .. } =
+            parse_expression("fun(~m: Map(String, List((Int, Bool)),), ~f: fun(List(Int))) {}")
+                .kind
+        else {
+            panic!("expected a function");
+        };
+        let [
+            FunctionParameter::UnlabeledAtCallsite { ty: Some(m), .. },
+            FunctionParameter::UnlabeledAtCallsite { ty: Some(f), .. },
+        ] = &parameters[..]
+        else {
+            panic!("expected two typed parameters");
+        };
+        assert_eq!(m.to_string(), "Map(String, List((Int, Bool)))");
+        assert_eq!(f.to_string(), "fun(List(Int))");
+
+        assert_eq!(
+            Parser::new("x : List() : y").parse(),
+            Err(Error::expected("identifier", ")"))
+        );
+    }
+
+    #[test]
+    fn logical_operators() {
+        // `&&` binds tighter than `||`, and both looser than comparisons
+        let ExpressionKind::Infix {
+            left,
+            operator: InfixOperator::Or,
+            right,
+        } = parse_expression("a == b || c < d && e").kind
+        else {
+            panic!("expected `||` to apply last");
+        };
+        assert!(matches!(
+            left.kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::Equal,
+                ..
+            }
+        ));
+        let ExpressionKind::Infix {
+            left,
+            operator: InfixOperator::And,
+            ..
+        } = right.kind
+        else {
+            panic!("expected `&&` on the right of `||`");
+        };
+        assert!(matches!(
+            left.kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::LessThan,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn pipelines() {
+        // `|>` applies from the left, after everything but assignment
+        let ExpressionKind::Infix {
+            left,
+            operator: InfixOperator::Pipe,
+            right,
+        } = parse_expression("a + 1 |> f(b) |> g ~> h").kind
+        else {
+            panic!("expected the last `|>` to apply last");
+        };
+        assert!(matches!(
+            left.kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::Pipe,
+                ..
+            }
+        ));
+        assert!(matches!(
+            right.kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::Compose,
+                ..
+            }
+        ));
+
+        let ExpressionKind::Assign { value, .. } = parse_expression("x = y |> f").kind else {
+            panic!("expected the pipeline to be assigned");
+        };
+        assert!(matches!(
+            value.kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::Pipe,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn option_operators() {
+        // `??` groups from the right, tighter than comparisons and looser than ranges
+        let ExpressionKind::Infix {
+            left,
+            operator: InfixOperator::Equal,
+            ..
+        } = parse_expression("a?.b ?? c ?? d..e == f").kind
+        else {
+            panic!("expected `==` to apply last");
+        };
+        let ExpressionKind::Infix {
+            left,
+            operator: InfixOperator::Coalesce,
+            right,
+        } = left.kind
+        else {
+            panic!("expected `??` on the left of `==`");
+        };
+        assert_eq!(
+            left.kind,
+            ExpressionKind::SafeFieldAccess {
+                target: Box::new(ident("a", 0)),
+                field: "b".to_string(),
+            }
+        );
+        let ExpressionKind::Infix {
+            operator: InfixOperator::Coalesce,
+            right,
+            ..
+        } = right.kind
+        else {
+            panic!("expected the second `??` to apply first");
+        };
+        assert!(matches!(right.kind, ExpressionKind::Range { .. }));
+
+        // `?.` chains like `.`, and a call after it calls the field
+        let ExpressionKind::Call { callee, .. } = parse_expression("a?.b?.c()").kind else {
+            panic!("expected a call");
+        };
+        assert!(matches!(
+            callee.kind,
+            ExpressionKind::SafeFieldAccess { ref target, .. }
+                if matches!(target.kind, ExpressionKind::SafeFieldAccess { .. })
+        ));
+
+        assert_eq!(
+            Parser::new("t?.0").parse(),
+            Err(Error::expected("identifier", "integer literal"))
+        );
+    }
+
+    #[test]
+    fn try_expressions() {
+        // `?` binds as tightly as a call, so `-` applies to what it gives
+        let ExpressionKind::Prefix { expression, .. } = parse_expression("-a.b()?").kind else {
+            panic!("expected a negation");
+        };
+        let ExpressionKind::Try(value) = expression.kind else {
+            panic!("expected `?` to apply first");
+        };
+        assert!(matches!(value.kind, ExpressionKind::MethodCall { .. }));
+        assert_eq!(expression.span, Span::from_range(1, 7));
+
+        // `?.` is still a safe field access, and `? .` a field of what `?` gives
+        assert!(matches!(
+            parse_expression("a?.b").kind,
+            ExpressionKind::SafeFieldAccess { .. }
+        ));
+        let ExpressionKind::FieldAccess { target, .. } = parse_expression("a? .b").kind else {
+            panic!("expected a field access");
+        };
+        assert!(matches!(target.kind, ExpressionKind::Try(_)));
+        let ExpressionKind::Infix {
+            left,
+            operator: InfixOperator::Coalesce,
+            ..
+        } = parse_expression("f()? ?? b").kind
+        else {
+            panic!("expected `??`");
+        };
+        assert!(matches!(left.kind, ExpressionKind::Try(_)));
+    }
+
+    #[test]
+    fn return_expressions() {
+        let ExpressionKind::Return(Some(value)) = parse_expression("return a + 1").kind else {
+            panic!("expected a return with a value");
+        };
+        assert!(matches!(value.kind, ExpressionKind::Infix { .. }));
+
+        let ExpressionKind::Match { arms, .. } =
+            parse_expression("match x { 0 => return, _ => return }").kind
+        else {
+            panic!("expected a match");
+        };
+        assert_eq!(
+            arms[0].body,
+            Expression::new(ExpressionKind::Return(None), Span::from_range(15, 21))
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        let ExpressionKind::VariableDecl { value, .. } = parse_expression("x :: { a; b }").kind
+        else {
+            panic!("expected a variable declaration");
+        };
+        assert_eq!(value.span, Span::from_range(5, 13));
+        let ExpressionKind::Block(body) = value.kind else {
+            panic!("expected a block");
+        };
+        assert_eq!(
+            body,
+            [
+                ExpressionStatement {
+                    expr: ident("a", 7),
+                    discarded: true,
+                },
+                ExpressionStatement {
+                    expr: ident("b", 10),
+                    discarded: false,
+                },
+            ]
+        );
+
+        // a block after a loop's condition is still its body
+        let ExpressionKind::While {
+            condition, body, ..
+        } = parse_expression("while { c } { d }").kind
+        else {
+            panic!("expected a while loop");
+        };
+        assert!(matches!(condition.kind, ExpressionKind::Block(_)));
+        assert_eq!(body.len(), 1);
+    }
+
+    #[test]
+    fn assignments() {
+        let ExpressionKind::Assign { target, value } = parse_expression("a = b = c + 1").kind
+        else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(*target, ident("a", 0));
+        let ExpressionKind::Assign { target, value } = value.kind else {
+            panic!("expected assignments to group from the right");
+        };
+        assert_eq!(*target, ident("b", 4));
+        assert!(matches!(value.kind, ExpressionKind::Infix { .. }));
+
+        assert!(matches!(
+            parse_expression("p.x = xs[0]").kind,
+            ExpressionKind::Assign { .. }
+        ));
+        assert_eq!(
+            Parser::new("a + b = c").parse(),
+            Err(Error::syntax_err(
+                "only variables, fields and indexes can be assigned to"
+            ))
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let ExpressionKind::Range {
+            start,
+            end,
+            inclusive,
+        } = parse_expression("a..=b + 1").kind
+        else {
+            panic!("expected a range");
+        };
+        assert_eq!(*start, ident("a", 0));
+        assert!(matches!(end.kind, ExpressionKind::Infix { .. }));
+        assert!(inclusive);
+
+        let ExpressionKind::Infix { left, .. } = parse_expression("1..10 == r").kind else {
+            panic!("expected a comparison of ranges");
+        };
+        assert!(matches!(
+            left.kind,
+            ExpressionKind::Range {
+                inclusive: false,
+                ..
+            }
+        ));
+
+        assert_eq!(
+            Parser::new("1..2..3").parse(),
+            Err(Error::syntax_err("ranges can't be chained"))
+        );
+    }
+
+    #[test]
+    fn break_labels() {
+        let ExpressionKind::While { label, body, .. } =
+            parse_expression("outer: while true { break outer }").kind
+        else {
+            panic!("expected a while loop");
+        };
+        assert_eq!(label, Some("outer".to_string()));
+        assert_eq!(
+            body[0].expr.kind,
+            ExpressionKind::Break {
+                label: Some("outer".to_string()),
+                value: None,
+            }
+        );
+
+        // `outer` isn't a loop label here, so it's the value
+        let ExpressionKind::While { body, .. } =
+            parse_expression("while true { break outer }").kind
+        else {
+            panic!("expected a while loop");
+        };
+        assert_eq!(
+            body[0].expr.kind,
+            ExpressionKind::Break {
+                label: None,
+                value: Some(Box::new(ident("outer", 19))),
+            }
+        );
+    }
+
+    #[test]
+    fn string_and_char_literals() {
+        assert_eq!(
+            parse_expression(r#""tab\tquote\"""#).kind,
+            ExpressionKind::StringLiteral("tab\tquote\"".to_string())
+        );
+        assert_eq!(
+            parse_expression(r"'\u{e9}'").kind,
+            ExpressionKind::CharLiteral('é')
+        );
+
+        let err = Parser::new(r#""a\qb""#).parse().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Lex {
+                error: LexError::InvalidEscape,
+                span
+            } if span == Span::from_range(2, 4)
+        ));
+    }
+
+    #[test]
+    fn spread_arguments() {
+        let ExpressionKind::FunctionCall { arguments, .. } = parse_expression("f(1, ...rest)").kind
+        else {
+            panic!("expected a function call");
+        };
+        assert_eq!(
+            arguments[1],
+            FunctionArgument {
+                label: None,
+                value: ident("rest", 8),
+                spread: true,
+            }
+        );
+    }
+
+    #[test]
+    fn pattern_parameters() {
+        let ExpressionKind::Function { parameters, .. } =
+            parse_expression("fun((x, (y,)): (Int, (Int,)), (z)) {}").kind
+        else {
+            panic!("expected a function");
+        };
+        let [
+            FunctionParameter::Pattern {
+                pattern: first,
+                ty: Some(_),
+            },
+            FunctionParameter::Pattern {
+                pattern: second,
+                ty: None,
+            },
+        ] = &parameters[..]
+        else {
+            panic!("expected two pattern parameters");
+        };
+        assert_eq!(first.to_string(), "(x, (y,))");
+        assert_eq!(second.kind, PatternKind::Binding("z".to_string()));
+        assert_eq!(second.span, Span::from_range(30, 33));
+
+        let ExpressionKind::Function { parameters, .. } = parse_expression("|(a, b), c| a").kind
+        else {
+            panic!("expected a function");
+        };
+        assert!(matches!(
+            parameters[..],
+            [
+                FunctionParameter::Pattern { .. },
+                FunctionParameter::UnlabeledAtCallsite { .. }
+            ]
+        ));
+
+        assert_eq!(
+            Parser::new("fun((x, y), x) {}").parse(),
+            Err(Error::DuplicateParameter {
+                first: Span::from_range(5, 6),
+                second: Span::from_range(12, 13)
+            })
+        );
+    }
+
+    #[test]
+    fn rest_parameters() {
+        let ExpressionKind::Function { parameters, .. } =
+            parse_expression("fun(format: String, ...values) {}").kind
+        else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            parameters[1],
+            FunctionParameter::Rest {
+                name: "values".to_string(),
+                ty: None
+            }
+        );
+
+        assert_eq!(
+            Parser::new("fun(...rest, x) {}").parse(),
+            Err(Error::RestParameterNotLast {
+                span: Span::from_range(7, 11)
+            })
+        );
+        assert_eq!(
+            Parser::new("fun(x, ...x) {}").parse(),
+            Err(Error::DuplicateParameter {
+                first: Span::from_range(4, 5),
+                second: Span::from_range(10, 11)
+            })
+        );
+        assert!(Parser::new("fun(...rest,) {}").parse().is_ok());
+    }
+
+    #[test]
+    fn deeply_nested_input() {
+        let nested = |open: &str, close: &str, depth| {
+            format!("{}1{}", open.repeat(depth), close.repeat(depth))
+        };
+        assert!(Parser::new(&nested("(", ")", 255)).parse().is_ok());
+        assert!(Parser::new(&nested("loop { ", " }", 127)).parse().is_ok());
+        assert!(Parser::new(&nested("{ ", " }", 255)).parse().is_ok());
+
+        for input in [
+            nested("(", ")", 100_000),
+            nested("!", "", 100_000),
+            nested("loop { ", " }", 100_000),
+            nested("{ ", " }", 100_000),
+            nested("@cfg() ", "", 100_000),
+            nested("|x| ", "", 100_000),
+            format!("match x {{ {} => 1 }}", nested("A.B(", ")", 100_000)),
+        ] {
+            assert!(matches!(
+                Parser::new(&input).parse(),
+                Err(Error::NestingTooDeep { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn lambdas() {
+        let ExpressionKind::Function {
+            parameters, body, ..
+        } = parse_expression("|x, y: Int| x + y").kind
+        else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            parameters,
+            [
+                FunctionParameter::UnlabeledAtCallsite {
+                    name: "x".to_string(),
+                    ty: None
+                },
+                FunctionParameter::UnlabeledAtCallsite {
+                    name: "y".to_string(),
+                    ty: Some(Type::Ident("Int".to_string()))
+                },
+            ]
+        );
+        assert!(matches!(
+            &body[..],
+            [ExpressionStatement {
+                expr: Expression {
+                    kind: ExpressionKind::Infix { .. },
+                    ..
+                },
+                discarded: false
+            }]
+        ));
+
+        // without parameters, and with the body reaching as far as it can
+        let expr = parse_expression("|| a |> f");
+        assert_eq!(expr.span, Span::from_range(0, 9));
+        assert!(matches!(
+            expr.kind,
+            ExpressionKind::Function { parameters, .. } if parameters.is_empty()
+        ));
+
+        assert_eq!(
+            Parser::new("|x, x| x").parse(),
+            Err(Error::DuplicateParameter {
+                first: Span::from_range(1, 2),
+                second: Span::from_range(4, 5),
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_parameters() {
+        assert_eq!(
+            Parser::new("fun(x: Int, by x: Int) {}").parse(),
+            Err(Error::DuplicateParameter {
+                first: Span::from_range(4, 5),
+                second: Span::from_range(15, 16),
+            })
+        );
+        assert!(Parser::new("fun(self, x, by y, ~z) {}").parse().is_ok());
+        assert_eq!(
+            Parser::new("fun(x a: Int, x b: Int) {}").parse(),
+            Err(Error::DuplicateLabel {
+                first: Span::from_range(4, 5),
+                second: Span::from_range(14, 15),
+            })
+        );
+        assert_eq!(
+            Parser::new("fun(x: Int, x b: Int) {}").parse(),
+            Err(Error::DuplicateLabel {
+                first: Span::from_range(4, 5),
+                second: Span::from_range(12, 13),
+            })
+        );
+    }
+
+    #[test]
+    fn struct_declarations() {
+        let ExpressionKind::Struct { name, fields, .. } =
+            parse_expression("struct Point { x: Int, y: (Int, Int), }").kind
+        else {
+            panic!("expected a struct declaration");
+        };
+        assert_eq!(name, "Point");
+        let fields = fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.ty.to_string(), field.span))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("x", "Int".to_string(), Span::from_range(15, 16)),
+                ("y", "(Int, Int)".to_string(), Span::from_range(23, 24))
+            ]
+        );
+
+        assert_eq!(
+            Parser::new("struct P { x: Int, y: Int, x: Bool }").parse(),
+            Err(Error::DuplicateField {
+                first: Span::from_range(11, 12),
+                second: Span::from_range(27, 28),
+            })
+        );
+    }
+
+    #[test]
+    fn doc_comments() {
+        let Program(stmts) = Parser::new(
+            "/// The origin.\n///\n///   Never moves.\norigin :: 0\n\
+             /// A point.\nstruct Point { x: Int }\n\
+             /// A shape.\nenum Shape { Dot }\n\
+             impl Point {\n    /// Moves it.\n    shift :: fun(self) {}\n    scale :: fun(self) {}\n}\n\
+             /// Not a declaration.\nf()\n\
+             # Not a doc.\nx :: 1",
+        )
+        .parse()
+        .unwrap();
+        let docs = stmts
+            .iter()
+            .map(|stmt| match &stmt.expr.kind {
+                ExpressionKind::VariableDecl { doc, .. }
+                | ExpressionKind::Struct { doc, .. }
+                | ExpressionKind::Enum { doc, .. } => doc.clone(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            docs,
+            [
+                Some("The origin.\n\n  Never moves.".to_string()),
+                Some("A point.".to_string()),
+                Some("A shape.".to_string()),
+                None,
+                None,
+                None,
+            ]
+        );
+        let ExpressionKind::Impl { methods, .. } = &stmts[3].expr.kind else {
+            panic!("expected an impl");
+        };
+        assert_eq!(methods[0].doc.as_deref(), Some("Moves it."));
+        assert_eq!(methods[1].doc, None);
+
+        // anywhere else, doc comments are ignored like other comments
+        assert_eq!(
+            parse_expression("a + /// b\n1"),
+            parse_expression("a + ### b\n1")
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let Program(stmts) = Parser::new(
+            "/// Doubles it.\n@inline @deprecated(\"use twice\", 2)\ndouble :: fun(~x: Int) { x * 2 }\n\
+             @test struct S {}\n\
+             @cfg(target: \"wasm\") @a enum E {}\n\
+             impl S { @inline get :: fun(self) {} }",
+        )
+        .parse()
+        .unwrap();
+        let ExpressionKind::VariableDecl {
+            doc, attributes, ..
+        } = &stmts[0].expr.kind
+        else {
+            panic!("expected a variable declaration");
+        };
+        assert_eq!(doc.as_deref(), Some("Doubles it."));
+        assert_eq!(
+            attributes,
+            &[
+                Attribute {
+                    name: "inline".to_string(),
+                    arguments: vec![],
+                    span: Span::from_range(16, 23),
+                },
+                Attribute {
+                    name: "deprecated".to_string(),
+                    arguments: vec![
+                        Expression::new(
+                            ExpressionKind::StringLiteral("use twice".to_string()),
+                            Span::from_range(36, 47),
+                        ),
+                        Expression::new(ExpressionKind::IntLiteral(2), Span::from_range(49, 50)),
+                    ],
+                    span: Span::from_range(24, 51),
+                },
+            ]
+        );
+        // the declaration starts at its first attribute
+        assert_eq!(stmts[0].expr.span.start, 16);
+
+        let ExpressionKind::Struct { attributes, .. } = &stmts[1].expr.kind else {
+            panic!("expected a struct declaration");
+        };
+        assert_eq!(attributes[0].name, "test");
+        let ExpressionKind::Cfg { expr, .. } = &stmts[2].expr.kind else {
+            panic!("expected a cfg attribute");
+        };
+        let ExpressionKind::Enum { attributes, .. } = &expr.kind else {
+            panic!("expected an enum declaration");
+        };
+        assert_eq!(attributes[0].name, "a");
+        let ExpressionKind::Impl { methods, .. } = &stmts[3].expr.kind else {
+            panic!("expected an impl");
+        };
+        assert_eq!(methods[0].attributes[0].name, "inline");
+
+        assert_eq!(
+            Parser::new("@inline 1 + 2").parse(),
+            Err(Error::expected("declaration", "integer literal"))
+        );
+        assert_eq!(
+            Parser::new("@inline outer: loop {}").parse(),
+            Err(Error::syntax_err(
+                "attributes can only go in front of a declaration"
+            ))
+        );
+        assert_eq!(
+            Parser::new("@deprecated(x) f :: 1").parse(),
+            Err(Error::expected("literal", "identifier"))
+        );
+        assert_eq!(
+            Parser::new("@inline @cfg(a: \"b\") f :: 1").parse(),
+            Err(Error::syntax_err(
+                "`@cfg` has to come before any other attributes"
+            ))
+        );
+    }
+
+    #[test]
+    fn enum_declarations() {
+        let ExpressionKind::Enum { name, variants, .. } =
+            parse_expression("enum Shape { Circle(radius: Float), Rect(Int, Int), Empty, }").kind
+        else {
+            panic!("expected an enum declaration");
+        };
+        assert_eq!(name, "Shape");
+        let variants = variants
+            .iter()
+            .map(|variant| {
+                let fields = variant
+                    .fields
+                    .iter()
+                    .map(|field| (field.label.as_deref(), field.ty.to_string()))
+                    .collect::<Vec<_>>();
+                (variant.name.as_str(), fields)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            variants,
+            [
+                ("Circle", vec![(Some("radius"), "Float".to_string())]),
+                (
+                    "Rect",
+                    vec![(None, "Int".to_string()), (None, "Int".to_string())]
+                ),
+                ("Empty", vec![])
+            ]
+        );
+
+        assert_eq!(
+            Parser::new("enum E { A, B(Int), A }").parse(),
+            Err(Error::DuplicateVariant {
+                first: Span::from_range(9, 10),
+                second: Span::from_range(20, 21),
+            })
+        );
+        assert!(matches!(
+            Parser::new("enum E { A(x: Int, x: Int) }").parse(),
+            Err(Error::DuplicateField { .. })
+        ));
+    }
+
+    #[test]
+    fn trailing_closures() {
+        let ExpressionKind::MethodCall { arguments, .. } =
+            parse_expression("xs.each(1) fun(x) { x }").kind
+        else {
+            panic!("expected a method call");
+        };
+        assert_eq!(arguments.len(), 2);
+        assert!(matches!(
+            arguments[1],
+            FunctionArgument {
+                label: None,
+                value: Expression {
+                    kind: ExpressionKind::Function { .. },
+                    ..
+                },
+                spread: false,
+            }
+        ));
+
+        let call = parse_expression("each(xs) fun(x) { x }");
+        assert_eq!(call.span.end, 21);
+        let ExpressionKind::FunctionCall { arguments, .. } = call.kind else {
+            panic!("expected a function call");
+        };
+        assert_eq!(arguments.len(), 2);
+
+        let call = parse_expression("each(xs) |x| x + 1");
+        assert_eq!(call.span.end, 18);
+        let ExpressionKind::FunctionCall { arguments, .. } = call.kind else {
+            panic!("expected a function call");
+        };
+        assert!(matches!(
+            arguments[..],
+            [
+                _,
+                FunctionArgument {
+                    value: Expression {
+                        kind: ExpressionKind::Function { .. },
+                        ..
+                    },
+                    ..
+                }
+            ]
+        ));
+
+        // `||` is still an or, and a declared operator still applies
+        assert!(matches!(
+            parse_expression("f(x) || y").kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::Or,
+                ..
+            }
+        ));
+        let program = Parser::new("operator |+ (precedence: 2); f(x) |+ y")
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            program.0[1].expr.kind,
+            ExpressionKind::CustomInfix { .. }
+        ));
+
+        // a function on the next line is a statement of its own
+        let program = Parser::new("each(xs)\nfun(x) { x }").parse().unwrap();
+        let [call, function] = &program.0[..] else {
+            panic!("expected two statements");
+        };
+        assert!(matches!(
+            &call.expr.kind,
+            ExpressionKind::FunctionCall { arguments, .. } if arguments.len() == 1
+        ));
+        assert!(matches!(
+            function.expr.kind,
+            ExpressionKind::Function { .. }
+        ));
+
+        // a `|` without a closing one on the same line isn't passed
+        for input in ["f(x) | y", "f(x)\n|y| y", "f(x) |y,\nz| y"] {
+            let mut parser = Parser::new(input);
+            let call = parser.parse_expression(Precedence::Lowest).unwrap();
+            let ExpressionKind::FunctionCall { arguments, .. } = call.kind else {
+                panic!("expected a function call");
+            };
+            assert_eq!(arguments.len(), 1, "{input}");
+            assert_eq!(parser.peek_kind(), &BitOr, "{input}");
+        }
+        // while one that's closed is, whatever the spaces around it
+        let ExpressionKind::FunctionCall { arguments, .. } = parse_expression("f(x) | y | z").kind
+        else {
+            panic!("expected a function call");
+        };
+        assert_eq!(arguments.len(), 2);
+    }
+
+    #[test]
+    fn rewind_to_checkpoint() {
+        let mut parser = Parser::new("a: loop { x + }");
+        let checkpoint = parser.checkpoint();
+        parser.expect_ident().unwrap();
+        parser.expect_token(Colon).unwrap();
+        assert!(parser.parse_loop_expression(Some("a".to_string())).is_err());
+
+        parser.rewind(checkpoint);
+        assert_eq!(parser.loop_labels, Vec::<String>::new());
+        assert_eq!(
+            parser.expect_ident(),
+            Ok(("a".to_string(), Span::from_range(0, 1)))
+        );
+    }
+
+    #[test]
+    fn arguments_starting_with_an_ident() {
+        let ExpressionKind::FunctionCall { arguments, .. } =
+            parse_expression("f(x + 1, by: y.z)").kind
+        else {
+            panic!("expected a function call");
+        };
+        assert!(matches!(
+            &arguments[0],
+            FunctionArgument {
+                label: None,
+                value: Expression {
+                    kind: ExpressionKind::Infix { .. },
+                    ..
+                },
+                ..
+            }
+        ));
+        assert_eq!(arguments[1].label.as_deref(), Some("by"));
+        assert!(matches!(
+            arguments[1].value.kind,
+            ExpressionKind::FieldAccess { .. }
+        ));
+    }
+
+    #[test]
+    fn index_expressions() {
+        assert_eq!(
+            parse_expression("xs[i].y[0]"),
+            Expression::new(
+                ExpressionKind::Index {
+                    target: Box::new(Expression::new(
+                        ExpressionKind::FieldAccess {
+                            target: Box::new(Expression::new(
+                                ExpressionKind::Index {
+                                    target: Box::new(ident("xs", 0)),
+                                    index: Box::new(ident("i", 3)),
+                                },
+                                Span::from_range(0, 5),
+                            )),
+                            field: "y".to_string(),
+                        },
+                        Span::from_range(0, 7),
+                    )),
+                    index: Box::new(Expression::new(
+                        ExpressionKind::IntLiteral(0),
+                        Span::from_range(8, 9),
+                    )),
+                },
+                Span::from_range(0, 10),
+            )
+        );
+    }
+
+    #[test]
+    fn chained_comparisons() {
+        assert_eq!(
+            parse_expression("a < b <= c").kind,
+            ExpressionKind::ChainedComparison {
+                operands: vec![ident("a", 0), ident("b", 4), ident("c", 9)],
+                operators: vec![InfixOperator::LessThan, InfixOperator::LessThanOrEqual],
+            }
+        );
+
+        // grouping stops the chain
+        assert!(matches!(
+            parse_expression("(a < b) < c").kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::LessThan,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn int_literal_out_of_range() {
+        let err = Parser::new("x :: 99999999999999999999;")
+            .parse()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::IntLiteralOutOfRange {
+                span: Span::from_range(5, 25)
+            }
+        );
+
+        // whether a literal fits its type is up to the checker, as long as it
+        // fits in a u64
+        let max = u64::MAX;
+        assert!(
+            Parser::new(&format!("{max}; -{max}; {max}u8"))
+                .parse()
+                .is_ok()
+        );
+        for input in [
+            format!("{}", max as u128 + 1),
+            format!("{}i64", max as u128 + 1),
+            format!("match x {{ -{} => 1 }}", max as u128 + 1),
+        ] {
+            assert!(matches!(
+                Parser::new(&input).parse(),
+                Err(Error::IntLiteralOutOfRange { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn radixes_and_digit_separators() {
+        assert_eq!(
+            parse_expression("0xff").kind,
+            ExpressionKind::IntLiteral(255)
+        );
+        assert_eq!(
+            parse_expression("0b1000_0000u8").kind,
+            ExpressionKind::SuffixedIntLiteral {
+                value: 128,
+                suffix: "u8".to_string()
+            }
+        );
+        assert_eq!(
+            parse_expression("1_000.5").kind,
+            ExpressionKind::FloatLiteral(1000.5)
+        );
+        assert!(matches!(
+            Parser::new("match x { 0o17 => 1 }").parse(),
+            Ok(Program(stmts)) if matches!(
+                &stmts[0].expr.kind,
+                ExpressionKind::Match { arms, .. } if arms[0].pattern.kind == PatternKind::IntLiteral(15)
+            )
+        ));
+        assert!(matches!(
+            Parser::new("0x1_0000_0000_0000_0000").parse(),
+            Err(Error::IntLiteralOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn float_literals() {
+        assert_eq!(
+            parse_expression("1.5").kind,
+            ExpressionKind::FloatLiteral(1.5)
+        );
+        let ExpressionKind::MethodCall { receiver, .. } = parse_expression("2.max(3)").kind else {
+            panic!("expected a method call");
+        };
+        assert_eq!(receiver.kind, ExpressionKind::IntLiteral(2));
+
+        let huge = format!("{}.0", "9".repeat(400));
+        assert_eq!(
+            Parser::new(&huge).parse(),
+            Err(Error::FloatLiteralOutOfRange {
+                span: Span::from_range(0, 402)
+            })
+        );
+    }
+
+    #[test]
+    fn custom_operators() {
+        let program = Parser::new("operator <- (precedence: 3); a <- b; a < -b")
+            .parse()
+            .unwrap();
+        let operator = CustomOperator {
+            symbol: "<-".to_string(),
+            precedence: 3,
+            associativity: Associativity::Left,
+            method: None,
+        };
+        assert_eq!(
+            program.0[1].expr.kind,
+            ExpressionKind::CustomInfix {
+                left: Box::new(ident("a", 29)),
+                operator,
+                right: Box::new(ident("b", 34)),
+            }
+        );
+        // with a space in between, it's two operators
+        assert!(matches!(
+            program.0[2].expr.kind,
+            ExpressionKind::Infix {
+                operator: InfixOperator::LessThan,
+                ..
+            }
+        ));
+
+        for (source, error) in [
+            ("operator + (precedence: 1)", "`+` is a builtin operator"),
+            (
+                "operator <> (precedence: 7)",
+                "operator precedence has to be from 1 to 6",
+            ),
+            ("operator <> (assoc: left)", "missing operator precedence"),
+            (
+                "operator <> (precedence: 1) operator <> (precedence: 2)",
+                "operator `<>` is already declared",
+            ),
+        ] {
+            assert_eq!(
+                Parser::new(source).parse(),
+                Err(Error::syntax_err(error)),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn postfix_operators() {
+        let expr = parse_expression("f(1)[0](2).x");
+        let ExpressionKind::FieldAccess { target, .. } = expr.kind else {
+            panic!("expected a field access, got {expr:?}");
+        };
+        let ExpressionKind::Call { callee, arguments } = target.kind else {
+            panic!("expected a call, got {target:?}");
+        };
+        assert_eq!(arguments.len(), 1);
+        assert!(matches!(callee.kind, ExpressionKind::Index { .. }));
+
+        // postfix operators bind tighter than prefix ones
+        assert!(matches!(
+            parse_expression("-(f)(1)").kind,
+            ExpressionKind::Prefix { expression, .. }
+                if matches!(expression.kind, ExpressionKind::Call { .. })
+        ));
     }
 }
@@ -0,0 +1,873 @@
+//! Prints the AST back out as source code.
+//!
+//! Minified source leaves out every space, parenthesis and semicolon that
+//! isn't needed for it to parse back to an equivalent program. A discarded
+//! statement keeps its semicolon, since that's what makes it discarded and
+//! gets its value checked as unused, and one that isn't only gets one where
+//! the next statement would otherwise be read as part of it. Doc comments
+//! are left out along with every other comment.
+
+use super::{
+    OPERATOR_CHARS,
+    ast::{
+        Associativity, Attribute, Expression, ExpressionKind, ExpressionStatement,
+        FunctionArgument, FunctionParameter, Pattern, PatternKind, Precedence, Program, Type,
+        TypeParameter,
+    },
+};
+use unicode_ident::is_xid_continue;
+
+pub fn program_to_minified_source(program: &Program) -> String {
+    let mut printer = Printer::default();
+    printer.statements(&program.0);
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    /// Whether an operator has been declared, after which operator characters
+    /// that end up next to each other could be read as one operator.
+    custom_operators: bool,
+}
+
+impl Printer {
+    /// Appends a token, separated from the previous one by a space if the two
+    /// would otherwise be read as a single token.
+    fn token(&mut self, token: &str) {
+        let (Some(last), Some(first)) = (self.out.chars().last(), token.chars().next()) else {
+            self.out.push_str(token);
+            return;
+        };
+        let is_operator = |c: char| OPERATOR_CHARS.contains(c);
+        if is_xid_continue(last) && is_xid_continue(first)
+            || self.custom_operators && is_operator(last) && is_operator(first)
+            || matches!(
+                (last, first),
+                ('=' | '!' | '<' | '>' | '~', '=' | '<' | '>')
+                    | ('.', '.')
+                    // `?.` and `??` are operators of their own
+                    | ('?', '.' | '?')
+                    | ('"', '"')
+                    // `r"` starts a raw string
+                    | ('r', '"')
+            )
+        {
+            self.out.push(' ');
+        }
+        self.out.push_str(token);
+    }
+
+    fn statements(&mut self, stmts: &[ExpressionStatement]) {
+        let sources = stmts
+            .iter()
+            .map(|stmt| {
+                let mut printer = Printer {
+                    custom_operators: self.custom_operators,
+                    ..Printer::default()
+                };
+                printer.expression(&stmt.expr);
+                self.custom_operators = printer.custom_operators;
+                printer.out
+            })
+            .collect::<Vec<_>>();
+
+        for (i, (stmt, source)) in stmts.iter().zip(&sources).enumerate() {
+            self.token(source);
+            // a discarded statement keeps its semicolon, or its value would
+            // be used, say as what a block evaluates to
+            let needs_semicolon = stmt.discarded
+                || sources
+                    .get(i + 1)
+                    .is_some_and(|next| runs_into(&stmt.expr, source, next));
+            if needs_semicolon {
+                self.token(";");
+            }
+        }
+    }
+
+    fn block(&mut self, stmts: &[ExpressionStatement]) {
+        self.token("{");
+        self.statements(stmts);
+        self.token("}");
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Unit => self.token("unit"),
+            ExpressionKind::BoolLiteral(value) => self.token(&value.to_string()),
+            ExpressionKind::IntLiteral(value) => self.token(&value.to_string()),
+            ExpressionKind::SuffixedIntLiteral { value, suffix } => {
+                self.token(&format!("{value}{suffix}"))
+            }
+            // `Display` never uses an exponent, but leaves off the fraction
+            // of whole numbers, which would make them ints
+            ExpressionKind::FloatLiteral(value) if value.fract() == 0.0 => {
+                self.token(&format!("{value}.0"))
+            }
+            ExpressionKind::FloatLiteral(value) => self.token(&value.to_string()),
+            ExpressionKind::StringLiteral(value) => self.string_literal(value),
+            ExpressionKind::CharLiteral(value) => {
+                self.token(&format!("'{}'", escape(*value, '\'')))
+            }
+            ExpressionKind::Ident(name) => self.token(name),
+            ExpressionKind::Prefix {
+                operator,
+                expression,
+            } => {
+                self.token(&operator.to_string());
+                self.operand(expression, |p| p < Precedence::Prefix);
+            }
+            ExpressionKind::Infix {
+                left,
+                operator,
+                right,
+            } => self.binary(
+                left,
+                &operator.to_string(),
+                operator.precedence(),
+                operator.associativity(),
+                right,
+            ),
+            ExpressionKind::CustomInfix {
+                left,
+                operator,
+                right,
+            } => self.binary(
+                left,
+                &operator.symbol,
+                operator.precedence(),
+                operator.associativity,
+                right,
+            ),
+            ExpressionKind::ChainedComparison {
+                operands,
+                operators,
+            } => {
+                self.operand(&operands[0], |p| {
+                    p <= Precedence::Comparison || ends_with_break(&operands[0])
+                });
+                for (operator, operand) in operators.iter().zip(&operands[1..]) {
+                    self.token(&operator.to_string());
+                    self.operand(operand, |p| {
+                        p <= Precedence::Comparison || ends_with_break(operand)
+                    });
+                }
+            }
+            ExpressionKind::Tuple(elements) => {
+                self.token("(");
+                self.list(elements, Printer::expression);
+                if elements.len() == 1 {
+                    self.token(",");
+                }
+                self.token(")");
+            }
+            ExpressionKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                self.operand(start, |p| p <= Precedence::Range || ends_with_break(start));
+                self.token(if *inclusive { "..=" } else { ".." });
+                self.operand(end, |p| p <= Precedence::Range);
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                mutable,
+                ty,
+                attributes,
+                ..
+            } => {
+                self.attributes(attributes);
+                self.token(name);
+                self.token(":");
+                if let Some((ty, _)) = ty {
+                    self.ty(ty);
+                }
+                self.token(if *mutable { "=" } else { ":" });
+                self.expression(value);
+            }
+            ExpressionKind::Block(body) => self.block(body),
+            ExpressionKind::Assign { target, value } => {
+                self.expression(target);
+                self.token("=");
+                self.expression(value);
+            }
+            ExpressionKind::Function {
+                type_parameters,
+                parameters,
+                return_type,
+                body,
+            } => {
+                self.token("fun");
+                if !type_parameters.is_empty() {
+                    self.token("[");
+                    self.list(type_parameters, Printer::type_parameter);
+                    self.token("]");
+                }
+                self.token("(");
+                self.list(parameters, Printer::parameter);
+                self.token(")");
+                if let Some(ty) = return_type {
+                    self.ty(ty);
+                }
+                self.block(body);
+            }
+            ExpressionKind::FunctionCall { name, arguments } => {
+                self.token(name);
+                self.arguments(arguments);
+            }
+            ExpressionKind::Call { callee, arguments } => {
+                // `f(x)` and `a.f(x)` would call a function or method by name
+                let named = matches!(
+                    callee.kind,
+                    ExpressionKind::Ident(_) | ExpressionKind::FieldAccess { .. }
+                );
+                self.operand(callee, |p| named || p < Precedence::Access);
+                self.arguments(arguments);
+            }
+            ExpressionKind::FieldAccess { target, field } => {
+                // `1.0` would be a float literal
+                let int_literal = matches!(target.kind, ExpressionKind::IntLiteral(_));
+                self.operand(target, |p| p < Precedence::Access || int_literal);
+                self.token(".");
+                self.token(field);
+            }
+            ExpressionKind::SafeFieldAccess { target, field } => {
+                self.operand(target, |p| p < Precedence::Access);
+                self.token("?.");
+                self.token(field);
+            }
+            ExpressionKind::Try(value) => {
+                self.operand(value, |p| p < Precedence::Access);
+                self.token("?");
+            }
+            ExpressionKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => {
+                self.operand(receiver, |p| p < Precedence::Access);
+                self.token(".");
+                self.token(method);
+                self.arguments(arguments);
+            }
+            ExpressionKind::Index { target, index } => {
+                self.operand(target, |p| p < Precedence::Access);
+                self.token("[");
+                self.open_ended(index);
+                self.token("]");
+            }
+            ExpressionKind::Impl { ty, methods } => {
+                self.token("impl");
+                self.ty(ty);
+                self.token("{");
+                for method in methods {
+                    self.attributes(&method.attributes);
+                    self.token(&method.name);
+                    self.token("::");
+                    self.expression(&method.function);
+                }
+                self.token("}");
+            }
+            ExpressionKind::Struct {
+                name,
+                fields,
+                attributes,
+                ..
+            } => {
+                self.attributes(attributes);
+                self.token("struct");
+                self.token(name);
+                self.token("{");
+                self.list(fields, |printer, field| {
+                    printer.token(&field.name);
+                    printer.token(":");
+                    printer.ty(&field.ty);
+                });
+                self.token("}");
+            }
+            ExpressionKind::Enum {
+                name,
+                variants,
+                attributes,
+                ..
+            } => {
+                self.attributes(attributes);
+                self.token("enum");
+                self.token(name);
+                self.token("{");
+                self.list(variants, |printer, variant| {
+                    printer.token(&variant.name);
+                    if !variant.fields.is_empty() {
+                        printer.token("(");
+                        printer.list(&variant.fields, |printer, field| {
+                            if let Some(label) = &field.label {
+                                printer.token(label);
+                                printer.token(":");
+                            }
+                            printer.ty(&field.ty);
+                        });
+                        printer.token(")");
+                    }
+                });
+                self.token("}");
+            }
+            ExpressionKind::OperatorDecl(operator) => {
+                self.token("operator");
+                self.token(&operator.symbol);
+                self.token("(");
+                self.token("precedence");
+                self.token(":");
+                self.token(&operator.precedence.to_string());
+                if operator.associativity == Associativity::Right {
+                    self.token(",");
+                    self.token("assoc");
+                    self.token(":");
+                    self.token("right");
+                }
+                if let Some(method) = &operator.method {
+                    self.token(",");
+                    self.token("method");
+                    self.token(":");
+                    self.token(method);
+                }
+                self.token(")");
+                self.custom_operators = true;
+            }
+            ExpressionKind::Match { value, arms } => {
+                self.token("match");
+                self.open_ended(value);
+                self.token("{");
+                self.list(arms, |printer, arm| {
+                    printer.pattern(&arm.pattern);
+                    printer.token("=>");
+                    printer.expression(&arm.body);
+                });
+                self.token("}");
+            }
+            ExpressionKind::While {
+                label,
+                condition,
+                body,
+            } => {
+                self.loop_label(label);
+                self.token("while");
+                self.open_ended(condition);
+                self.block(body);
+            }
+            ExpressionKind::Loop { label, body } => {
+                self.loop_label(label);
+                self.token("loop");
+                self.block(body);
+            }
+            ExpressionKind::For {
+                label,
+                pattern,
+                iterable,
+                body,
+            } => {
+                self.loop_label(label);
+                self.token("for");
+                self.pattern(pattern);
+                self.token("in");
+                self.open_ended(iterable);
+                self.block(body);
+            }
+            ExpressionKind::Break { label, value } => {
+                self.token("break");
+                if let Some(label) = label {
+                    self.token(label);
+                }
+                if let Some(value) = value {
+                    // `break x: loop {}` would take `x` as the label
+                    self.operand(value, |_| label.is_none() && starts_with_label(value));
+                }
+            }
+            ExpressionKind::Continue { label } => {
+                self.token("continue");
+                if let Some(label) = label {
+                    self.token(label);
+                }
+            }
+            ExpressionKind::Return(value) => {
+                self.token("return");
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            ExpressionKind::Cfg { options, expr } => {
+                self.token("@cfg");
+                self.token("(");
+                self.list(options, |printer, (key, value)| {
+                    printer.token(key);
+                    printer.token(":");
+                    printer.string_literal(value);
+                });
+                self.token(")");
+                self.expression(expr);
+            }
+            ExpressionKind::Include(path) => {
+                self.token("include");
+                self.string_literal(path);
+            }
+            ExpressionKind::EmbedText(path) => {
+                self.token("embed_text");
+                self.string_literal(path);
+            }
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.token(&format!("@{}", attribute.name));
+            if !attribute.arguments.is_empty() {
+                self.token("(");
+                self.list(&attribute.arguments, Printer::expression);
+                self.token(")");
+            }
+        }
+    }
+
+    fn string_literal(&mut self, value: &str) {
+        let escaped = value.chars().map(|c| escape(c, '"')).collect::<String>();
+        self.token(&format!("\"{escaped}\""));
+    }
+
+    fn binary(
+        &mut self,
+        left: &Expression,
+        operator: &str,
+        precedence: Precedence,
+        associativity: Associativity,
+        right: &Expression,
+    ) {
+        // `(a < b) < c` would turn into a chained comparison without the parentheses
+        let chains = precedence == Precedence::Comparison;
+        self.operand(left, |p| {
+            p < precedence
+                || (chains || associativity_of(left) == Some(Associativity::Right))
+                    && p == precedence
+                || ends_with_break(left)
+        });
+        self.token(operator);
+        self.operand(right, |p| {
+            p < precedence || associativity == Associativity::Left && p == precedence
+        });
+    }
+
+    /// Prints an expression, in parentheses if `needs_parens` holds for its precedence.
+    fn operand(&mut self, expr: &Expression, needs_parens: impl Fn(Precedence) -> bool) {
+        if needs_parens(precedence(expr)) {
+            self.token("(");
+            self.expression(expr);
+            self.token(")");
+        } else {
+            self.expression(expr);
+        }
+    }
+
+    /// Prints an expression that's followed by a token `break` would read as its value.
+    fn open_ended(&mut self, expr: &Expression) {
+        self.operand(expr, |_| ends_with_break(expr));
+    }
+
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Printer, &T)) {
+        for (i, it) in items.iter().enumerate() {
+            if i > 0 {
+                self.token(",");
+            }
+            item(self, it);
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[FunctionArgument]) {
+        self.token("(");
+        self.list(arguments, |printer, arg| {
+            if arg.spread {
+                printer.token("...");
+            }
+            if let Some(label) = &arg.label {
+                printer.token(label);
+                printer.token(":");
+            }
+            // `f(x :: 1)` would take `x` as the label
+            let unlabeled = arg.label.is_none() && !arg.spread;
+            printer.operand(&arg.value, |_| unlabeled && starts_with_label(&arg.value));
+        });
+        self.token(")");
+    }
+
+    fn type_parameter(&mut self, type_parameter: &TypeParameter) {
+        self.token(&type_parameter.name);
+        for (i, (bound, _)) in type_parameter.bounds.iter().enumerate() {
+            self.token(if i == 0 { ":" } else { "+" });
+            self.token(bound);
+        }
+    }
+
+    fn parameter(&mut self, parameter: &FunctionParameter) {
+        let ty = match parameter {
+            FunctionParameter::SelfParameter => {
+                self.token("self");
+                return;
+            }
+            FunctionParameter::UnlabeledAtCallsite { name, ty } => {
+                self.token("~");
+                self.token(name);
+                ty
+            }
+            FunctionParameter::Rest { name, ty } => {
+                self.token("...");
+                self.token(name);
+                ty
+            }
+            // anything but a tuple would be read back as a name
+            FunctionParameter::Pattern { pattern, ty } => {
+                if let PatternKind::Tuple(_) = pattern.kind {
+                    self.pattern(pattern);
+                } else {
+                    self.token("(");
+                    self.pattern(pattern);
+                    self.token(")");
+                }
+                ty
+            }
+            FunctionParameter::LabeledAtCallsite {
+                internal_name,
+                external_name,
+                ty,
+                ..
+            } => {
+                if let Some(external_name) = external_name {
+                    self.token(external_name);
+                }
+                self.token(internal_name);
+                ty
+            }
+        };
+        if let Some(ty) = ty {
+            self.token(":");
+            self.ty(ty);
+        }
+    }
+
+    fn ty(&mut self, ty: &Type) {
+        match ty {
+            Type::Ident(name) => self.token(name),
+            Type::Tuple(elements) => {
+                self.token("(");
+                self.list(elements, Printer::ty);
+                if elements.len() == 1 {
+                    self.token(",");
+                }
+                self.token(")");
+            }
+            Type::Applied { name, arguments } => {
+                self.token(name);
+                self.token("(");
+                self.list(arguments, Printer::ty);
+                self.token(")");
+            }
+            Type::Function {
+                parameters,
+                return_type,
+            } => {
+                self.token("fun");
+                self.token("(");
+                self.list(parameters, Printer::ty);
+                self.token(")");
+                if let Some(return_type) = return_type {
+                    self.ty(return_type);
+                }
+            }
+        }
+    }
+
+    fn loop_label(&mut self, label: &Option<String>) {
+        if let Some(label) = label {
+            self.token(label);
+            self.token(":");
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Wildcard => self.token("_"),
+            PatternKind::Binding(name) => self.token(name),
+            PatternKind::Unit => self.token("unit"),
+            PatternKind::BoolLiteral(value) => self.token(&value.to_string()),
+            PatternKind::IntLiteral(value) => self.token(&value.to_string()),
+            PatternKind::Variant {
+                ty,
+                variant,
+                fields,
+            } => {
+                self.token(ty);
+                self.token(".");
+                self.token(variant);
+                if !fields.is_empty() {
+                    self.token("(");
+                    self.list(fields, |printer, field| {
+                        if let Some(label) = &field.label {
+                            printer.token(label);
+                            printer.token(":");
+                        }
+                        printer.pattern(&field.pattern);
+                    });
+                    self.token(")");
+                }
+            }
+            PatternKind::Tuple(elements) => {
+                self.token("(");
+                self.list(elements, Printer::pattern);
+                if elements.len() == 1 {
+                    self.token(",");
+                }
+                self.token(")");
+            }
+        }
+    }
+}
+
+/// How tightly an expression holds together, as the parser sees it. Anything
+/// that isn't an operator application is as tight as can be.
+fn precedence(expr: &Expression) -> Precedence {
+    match &expr.kind {
+        ExpressionKind::Prefix { .. } => Precedence::Prefix,
+        ExpressionKind::Infix { operator, .. } => operator.precedence(),
+        ExpressionKind::CustomInfix { operator, .. } => operator.precedence(),
+        ExpressionKind::ChainedComparison { .. } => Precedence::Comparison,
+        ExpressionKind::Range { .. } => Precedence::Range,
+        ExpressionKind::Assign { .. } => Precedence::Assignment,
+        ExpressionKind::VariableDecl { .. }
+        | ExpressionKind::Break { .. }
+        | ExpressionKind::Continue { .. }
+        | ExpressionKind::Return(_)
+        | ExpressionKind::Cfg { .. } => Precedence::Lowest,
+        _ => Precedence::Access,
+    }
+}
+
+/// The associativity of the operator the expression applies, if it's a
+/// binary operator.
+fn associativity_of(expr: &Expression) -> Option<Associativity> {
+    match &expr.kind {
+        ExpressionKind::Infix { operator, .. } => Some(operator.associativity()),
+        ExpressionKind::CustomInfix { operator, .. } => Some(operator.associativity),
+        _ => None,
+    }
+}
+
+/// Whether a statement, printed as `source`, would be read as one with the
+/// next one without a semicolon between them.
+pub(super) fn runs_into(stmt: &Expression, source: &str, next: &str) -> bool {
+    ends_with_break(stmt)
+        || next.starts_with(['(', '[', '-'])
+        // a function after a call would be passed to it
+        || next.starts_with("fun(")
+        || next.starts_with("fun[")
+        // and a string after `include` would be its file
+        || next.starts_with('"') && ends_with_directive(source)
+}
+
+/// Whether the last thing in the expression is a `break`, `continue` or
+/// `return`, which would take whatever comes next as its value or label.
+fn ends_with_break(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Break { value: None, .. }
+        | ExpressionKind::Continue { .. }
+        | ExpressionKind::Return(None) => true,
+        ExpressionKind::Break {
+            value: Some(value), ..
+        }
+        | ExpressionKind::Return(Some(value))
+        | ExpressionKind::VariableDecl { value, .. }
+        | ExpressionKind::Assign { value, .. }
+        | ExpressionKind::Cfg { expr: value, .. } => ends_with_break(value),
+        ExpressionKind::Prefix { expression, .. } => {
+            precedence(expression) >= Precedence::Prefix && ends_with_break(expression)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the source ends with a name that a string after it would turn
+/// into a directive, like `include`.
+fn ends_with_directive(source: &str) -> bool {
+    ["include", "embed_text"].iter().any(|directive| {
+        source
+            .strip_suffix(directive)
+            .is_some_and(|rest| !rest.ends_with(is_xid_continue))
+    })
+}
+
+/// Whether the expression starts with an identifier followed by a colon,
+/// like a variable declaration or a labeled loop.
+fn starts_with_label(expr: &Expression) -> bool {
+    let source = source(expr);
+    let name_end = source
+        .find(|c: char| !is_xid_continue(c))
+        .unwrap_or(source.len());
+    name_end > 0 && source[name_end..].starts_with(':')
+}
+
+pub(super) fn source(expr: &Expression) -> String {
+    let mut printer = Printer::default();
+    printer.expression(expr);
+    printer.out
+}
+
+fn escape(c: char, quote: char) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        '\r' => "\\r".to_string(),
+        '\0' => "\\0".to_string(),
+        '\\' => "\\\\".to_string(),
+        c if c == quote => format!("\\{c}"),
+        c if c.is_control() => format!("\\u{{{:x}}}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Parser, sexp::program_to_sexp};
+
+    /// Minifies the input, making sure it parses back to the same program.
+    fn minify(input: &str) -> String {
+        let program = Parser::new(input).parse().unwrap();
+        let minified = program_to_minified_source(&program);
+        let reparsed = Parser::new(&minified).parse().unwrap();
+        assert_eq!(
+            program_to_sexp(&reparsed),
+            program_to_sexp(&program),
+            "{minified}"
+        );
+        minified
+    }
+
+    #[test]
+    fn whitespace_and_semicolons() {
+        assert_eq!(
+            minify("x :: 1;\ny : Int = x + 2;\n-y;\nfoo(x, by: y);"),
+            "x::1;y:Int=x+2;-y;foo(x,by:y);"
+        );
+        assert_eq!(minify("loop { x := 1; break; (x) }"), "loop{x:=1;break;x}");
+        assert_eq!(minify("a == b; b >= c"), "a==b;b>=c");
+        // statements that aren't discarded only need to be told apart
+        assert_eq!(
+            minify("a == b b >= c x :: 1 y :: -x {} !y"),
+            "a==b b>=c x::1 y::-x{}!y"
+        );
+        assert_eq!(minify("f(x); fun(y) { y }"), "f(x);fun(y){y}");
+        assert_eq!(minify("f(x) fun(y) { y }"), "f(x,fun(y){y})");
+        assert_eq!(minify("fun(x, ...xs: Int) {}"), "fun(x,...xs:Int){}");
+        assert_eq!(
+            minify("f : fun(Int, fun() Bool) (Int,) : g"),
+            "f:fun(Int,fun()Bool)(Int,):g"
+        );
+        assert_eq!(minify("f : Map(K, List(V)) : g"), "f:Map(K,List(V)):g");
+        // a pattern that isn't a tuple keeps its parentheses
+        assert_eq!(minify("fun((a, b), (c)) {}"), "fun((a,b),(c)){}");
+        assert_eq!(minify("match p { (x,) => x }"), "match p{(x,)=>x}");
+        assert_eq!(
+            minify("@test @deprecated(\"old\", 2) f :: fun() {}\n@inline g :: 1"),
+            "@test@deprecated(\"old\",2)f::fun(){}@inline g::1"
+        );
+        // lambdas are printed as the functions they stand for
+        assert_eq!(
+            minify("xs.each(|x| x + 1); || 1"),
+            "xs.each(fun(~x){x+1});fun(){1}"
+        );
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(
+            minify("(1 + 2) * 3 - (4 - 5) + (-x).y + -(x.y)"),
+            "(1+2)*3-(4-5)+(-x).y+-x.y"
+        );
+        assert_eq!(
+            minify("(a < b) < c; a < b < (c == d)"),
+            "(a<b)<c;a<b<(c==d)"
+        );
+        assert_eq!(minify("f((x + 1), (1 + x))"), "f(x+1,1+x)");
+        assert_eq!(
+            minify("(a ** b) ** c; a ** (b ** c); -(a ** b)"),
+            "(a**b)**c;a**b**c;-(a**b)"
+        );
+        assert_eq!(
+            minify("fun() { return; (a) }; fun() { return a; -b }; (return) + 1"),
+            "fun(){return;a};fun(){return a;-b};(return)+1"
+        );
+        assert_eq!(
+            minify("x :: { a; b }; { c; }; while { d } { e }; ({ f }).g"),
+            "x::{a;b};{c;};while{d}{e};{f}.g"
+        );
+        assert_eq!(
+            minify("a = (b = c); (a = b) + 1; p.x = 1; y := x = 2"),
+            "a=b=c;(a=b)+1;p.x=1;y:=x=2"
+        );
+        assert_eq!(
+            minify("(a || b) && c; a || (b && c); !(a && b); (a == b) && c"),
+            "(a||b)&&c;a||b&&c;!(a&&b);a==b&&c"
+        );
+        assert_eq!(
+            minify("(xs |> f(1)) |> g; xs |> (f |> g); x = (a |> f ~> g); (a |> f) + 1"),
+            "xs|>f(1)|>g;xs|>(f|>g);x=a|>f~>g;(a|>f)+1"
+        );
+        assert_eq!(
+            minify("(1..10)..(0 ..= n + 1); (a..b) < c; (a < b)..c; x.y..-z"),
+            "(1..10)..(0..=n+1);a..b<c;(a<b)..c;x.y..-z"
+        );
+        assert_eq!(
+            minify("(a ?? b) ?? c; a ?? (b ?? c); (a ?? b) == c; (a + b)?.c?.d; (a?.b).c"),
+            "(a??b)??c;a??b??c;a??b==c;(a+b)?.c?.d;a?.b.c"
+        );
+        assert_eq!(
+            minify("(a?).b; (a?)?.b; (a?) ?? b; -(a.b()?); (-a)?"),
+            "a? .b;a? ?.b;a? ??b;-a.b()?;(-a)?"
+        );
+        assert_eq!(
+            minify("x : (Int, (Bool,)) = ((1), (2,)); (1).0; x.1.0"),
+            "x:(Int,(Bool,))=(1,(2,));(1).0;x.1.0"
+        );
+        assert_eq!(
+            minify("struct P { x: Int, y: (Int,), } P(x: 1, y: (2,)).x"),
+            "struct P{x:Int,y:(Int,)}P(x:1,y:(2,)).x"
+        );
+        assert_eq!(
+            minify("enum S { A(x: Int, (Int, Bool)), B, } S.A(x: 1, (2, true)); S.B"),
+            "enum S{A(x:Int,(Int,Bool)),B}S.A(x:1,(2,true));S.B"
+        );
+    }
+
+    #[test]
+    fn custom_operators() {
+        assert_eq!(
+            minify("operator <+> (precedence: 5, assoc: left); a + -b; (a <+> b) + c"),
+            "operator<+>(precedence:5);a+ -b;a<+>b+c"
+        );
+        assert_eq!(
+            minify("operator ^^ (precedence: 6, assoc: right); (a ^^ b) ^^ c; a ^^ (b * c)"),
+            "operator^^(precedence:6,assoc:right);(a^^b)^^c;a^^b*c"
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(
+            minify(r#"s :: "a\"b\n'"; c :: '\''; d :: 255u8"#),
+            r#"s::"a\"b\n'";c::'\'';d::255u8"#
+        );
+        assert_eq!(
+            minify("x :: 1.50; y :: 100000000000000000000.0; z :: 0.1 + 2.0"),
+            "x::1.5;y::100000000000000000000.0;z::0.1+2.0"
+        );
+        assert_eq!(minify(r#"r"\d"; r; "\n""#), r#""\\d";r;"\n""#);
+    }
+}
@@ -0,0 +1,1350 @@
+//! A compact s-expression format for the AST, e.g. `(infix + (int 1) (int 2))`.
+//!
+//! Spans are left out so that dumps only change when the shape of the tree
+//! does, which makes them easy to diff. Reading a dump back gives every node
+//! an empty span at the start of the source. When the spans are what a test
+//! is about, [`program_to_sexp_with_spans`] puts them back in.
+//!
+//! A program is its statements one after another, each followed by `;` if
+//! its value is discarded. Optional fields are written as `:key value`, or
+//! just `:key` for flags.
+
+use std::fmt::Write;
+
+use super::ast::{
+    Associativity, Attribute, CustomOperator, EnumVariant, Expression, ExpressionKind,
+    ExpressionStatement, FunctionArgument, FunctionParameter, InfixOperator, MatchArm, Method,
+    Pattern, PatternKind, PrefixOperator, Program, StructField, Type, TypeParameter, VariantField,
+    VariantFieldPattern,
+};
+use crate::{lexer::unescape, span::Span};
+
+const MAX_WIDTH: usize = 80;
+/// The `:key`s that don't have a value.
+const FLAGS: [&str; 3] = [":mutable", ":unlabeled", ":inclusive"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    UnexpectedEof,
+    Invalid(String),
+}
+
+impl Error {
+    fn invalid(what: &str, sexp: &Sexp) -> Self {
+        Self::Invalid(format!("expected {what}, got {}", sexp.flat()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Sexp {
+    Atom(String),
+    String(String),
+    Char(char),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn atom(atom: impl ToString) -> Self {
+        Sexp::Atom(atom.to_string())
+    }
+
+    fn list(head: &str, items: impl IntoIterator<Item = Sexp>) -> Self {
+        Sexp::List(std::iter::once(Sexp::atom(head)).chain(items).collect())
+    }
+
+    fn flat(&self) -> String {
+        match self {
+            Sexp::Atom(atom) => atom.clone(),
+            Sexp::String(value) => format!("{value:?}"),
+            Sexp::Char(value) => format!("{value:?}"),
+            Sexp::List(items) => {
+                let items = items.iter().map(Sexp::flat).collect::<Vec<_>>();
+                format!("({})", items.join(" "))
+            }
+        }
+    }
+
+    /// Writes the expression on one line if it fits. Otherwise the atoms right
+    /// after the head, like the operator of an infix expression, stay on its
+    /// line and every other item goes on its own line, `:key`s together with
+    /// their value.
+    fn render(&self, indent: usize, out: &mut String) {
+        let flat = self.flat();
+        let Sexp::List(items) = self else {
+            out.push_str(&flat);
+            return;
+        };
+        if indent + flat.len() <= MAX_WIDTH || items.len() < 2 {
+            out.push_str(&flat);
+            return;
+        }
+
+        write!(out, "({}", items[0].flat()).unwrap();
+        let mut rest = items[1..].iter().peekable();
+        while let Some(item) = rest.next_if(
+            |item| matches!(item, Sexp::Atom(atom) if !atom.starts_with(':') && atom != ";"),
+        ) {
+            write!(out, " {}", item.flat()).unwrap();
+        }
+        while let Some(item) = rest.next() {
+            if is_atom(item, ";") {
+                out.push_str(" ;");
+                continue;
+            }
+            write!(out, "\n{:width$}", "", width = indent + 2).unwrap();
+            item.render(indent + 2, out);
+            match item {
+                Sexp::Atom(key) if key.starts_with(':') && !FLAGS.contains(&key.as_str()) => {
+                    if let Some(value) = rest.next() {
+                        out.push(' ');
+                        value.render(indent + 3 + key.len(), out);
+                    }
+                }
+                _ => {}
+            }
+        }
+        out.push(')');
+    }
+}
+
+pub fn program_to_sexp(program: &Program) -> String {
+    Writer { spans: false }.program(program)
+}
+
+/// Like [`program_to_sexp`], but with the byte range of every expression and
+/// pattern after its head, e.g. `(infix@0..5 + (int@0..1 1) (int@4..5 2))`.
+/// These dumps are meant for snapshots of where things are in the source and
+/// can't be read back.
+pub fn program_to_sexp_with_spans(program: &Program) -> String {
+    Writer { spans: true }.program(program)
+}
+
+pub fn program_from_sexp(input: &str) -> Result<Program, Error> {
+    let mut reader = Reader {
+        chars: input.chars().peekable(),
+    };
+    let mut items = vec![];
+    while let Some(item) = reader.read()? {
+        items.push(item);
+    }
+    Ok(Program(read_statements(&items)?))
+}
+
+struct Writer {
+    spans: bool,
+}
+
+impl Writer {
+    fn program(&self, program: &Program) -> String {
+        let mut out = String::new();
+        for stmt in &program.0 {
+            self.expression(&stmt.expr).render(0, &mut out);
+            if stmt.discarded {
+                out.push_str(" ;");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn expression(&self, expr: &Expression) -> Sexp {
+        self.spanned(self.expression_kind(expr), expr.span)
+    }
+
+    fn expression_kind(&self, expr: &Expression) -> Sexp {
+        match &expr.kind {
+            ExpressionKind::Unit => Sexp::atom("unit"),
+            ExpressionKind::BoolLiteral(value) => Sexp::list("bool", [Sexp::atom(value)]),
+            ExpressionKind::IntLiteral(value) => Sexp::list("int", [Sexp::atom(value)]),
+            ExpressionKind::SuffixedIntLiteral { value, suffix } => {
+                Sexp::list("int", [Sexp::atom(value), Sexp::atom(suffix)])
+            }
+            ExpressionKind::FloatLiteral(value) => {
+                Sexp::list("float", [Sexp::atom(format!("{value:?}"))])
+            }
+            ExpressionKind::StringLiteral(value) => {
+                Sexp::list("string", [Sexp::String(value.clone())])
+            }
+            ExpressionKind::CharLiteral(value) => Sexp::list("char", [Sexp::Char(*value)]),
+            ExpressionKind::Ident(name) => Sexp::list("ident", [Sexp::atom(name)]),
+            ExpressionKind::Prefix {
+                operator,
+                expression: operand,
+            } => Sexp::list("prefix", [Sexp::atom(operator), self.expression(operand)]),
+            ExpressionKind::Infix {
+                left,
+                operator,
+                right,
+            } => Sexp::list(
+                "infix",
+                [
+                    Sexp::atom(operator),
+                    self.expression(left),
+                    self.expression(right),
+                ],
+            ),
+            ExpressionKind::CustomInfix {
+                left,
+                operator,
+                right,
+            } => {
+                let mut items = custom_operator(operator);
+                items.extend([self.expression(left), self.expression(right)]);
+                Sexp::list("custom", items)
+            }
+            ExpressionKind::ChainedComparison {
+                operands,
+                operators,
+            } => {
+                let mut items = vec![self.expression(&operands[0])];
+                for (operator, operand) in operators.iter().zip(&operands[1..]) {
+                    items.push(Sexp::atom(operator));
+                    items.push(self.expression(operand));
+                }
+                Sexp::list("chain", items)
+            }
+            ExpressionKind::Tuple(elements) => Sexp::list(
+                "tuple",
+                elements.iter().map(|element| self.expression(element)),
+            ),
+            ExpressionKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let mut items = vec![];
+                if *inclusive {
+                    items.push(Sexp::atom(":inclusive"));
+                }
+                items.extend([self.expression(start), self.expression(end)]);
+                Sexp::list("range", items)
+            }
+            ExpressionKind::VariableDecl {
+                name,
+                value,
+                mutable,
+                ty,
+                doc,
+                attributes,
+                ..
+            } => {
+                let mut items = vec![Sexp::atom(name)];
+                items.extend(doc_sexp(doc));
+                items.extend(self.attributes(attributes));
+                if let Some((ty, _)) = ty {
+                    items.extend([Sexp::atom(":type"), type_sexp(ty)]);
+                }
+                if *mutable {
+                    items.push(Sexp::atom(":mutable"));
+                }
+                items.push(self.expression(value));
+                Sexp::list("let", items)
+            }
+            ExpressionKind::Block(body) => Sexp::list("block", self.statements(body)),
+            ExpressionKind::Assign { target, value } => Sexp::list(
+                "assign",
+                vec![self.expression(target), self.expression(value)],
+            ),
+            ExpressionKind::Function {
+                type_parameters,
+                parameters,
+                return_type,
+                body,
+            } => {
+                let mut items = vec![];
+                if !type_parameters.is_empty() {
+                    let type_parameters = type_parameters.iter().map(|t| {
+                        Sexp::List(
+                            std::iter::once(&t.name)
+                                .chain(t.bounds.iter().map(|(bound, _)| bound))
+                                .map(Sexp::atom)
+                                .collect(),
+                        )
+                    });
+                    items.extend([
+                        Sexp::atom(":type-params"),
+                        Sexp::List(type_parameters.collect()),
+                    ]);
+                }
+                items.extend([
+                    Sexp::atom(":params"),
+                    Sexp::List(parameters.iter().map(|p| self.parameter(p)).collect()),
+                ]);
+                if let Some(ty) = return_type {
+                    items.extend([Sexp::atom(":returns"), type_sexp(ty)]);
+                }
+                items.extend(self.statements(body));
+                Sexp::list("fun", items)
+            }
+            ExpressionKind::FunctionCall { name, arguments } => Sexp::list(
+                "call",
+                std::iter::once(Sexp::atom(name))
+                    .chain(arguments.iter().map(|arg| self.argument(arg))),
+            ),
+            ExpressionKind::Call { callee, arguments } => Sexp::list(
+                "call",
+                std::iter::once(self.expression(callee))
+                    .chain(arguments.iter().map(|arg| self.argument(arg))),
+            ),
+            ExpressionKind::FieldAccess { target, field } => {
+                Sexp::list("field", [self.expression(target), Sexp::atom(field)])
+            }
+            ExpressionKind::SafeFieldAccess { target, field } => {
+                Sexp::list("safe-field", [self.expression(target), Sexp::atom(field)])
+            }
+            ExpressionKind::Try(value) => Sexp::list("try", [self.expression(value)]),
+            ExpressionKind::MethodCall {
+                receiver,
+                method,
+                arguments,
+            } => Sexp::list(
+                "method",
+                [self.expression(receiver), Sexp::atom(method)]
+                    .into_iter()
+                    .chain(arguments.iter().map(|arg| self.argument(arg))),
+            ),
+            ExpressionKind::Index { target, index } => {
+                Sexp::list("index", [self.expression(target), self.expression(index)])
+            }
+            ExpressionKind::Impl { ty, methods } => Sexp::list(
+                "impl",
+                std::iter::once(type_sexp(ty)).chain(methods.iter().map(|m| {
+                    let mut items = vec![Sexp::atom(&m.name)];
+                    items.extend(doc_sexp(&m.doc));
+                    items.extend(self.attributes(&m.attributes));
+                    items.push(self.expression(&m.function));
+                    Sexp::List(items)
+                })),
+            ),
+            ExpressionKind::Struct {
+                name,
+                fields,
+                doc,
+                attributes,
+            } => Sexp::list(
+                "struct",
+                std::iter::once(Sexp::atom(name))
+                    .chain(doc_sexp(doc))
+                    .chain(self.attributes(attributes))
+                    .chain(fields.iter().map(|field| {
+                        Sexp::List(vec![Sexp::atom(&field.name), type_sexp(&field.ty)])
+                    })),
+            ),
+            ExpressionKind::Enum {
+                name,
+                variants,
+                doc,
+                attributes,
+            } => Sexp::list(
+                "enum",
+                std::iter::once(Sexp::atom(name))
+                    .chain(doc_sexp(doc))
+                    .chain(self.attributes(attributes))
+                    .chain(variants.iter().map(|variant| {
+                        Sexp::list(
+                            &variant.name,
+                            variant.fields.iter().map(|field| match &field.label {
+                                Some(label) => {
+                                    Sexp::list("arg", [Sexp::atom(label), type_sexp(&field.ty)])
+                                }
+                                None => type_sexp(&field.ty),
+                            }),
+                        )
+                    })),
+            ),
+            ExpressionKind::OperatorDecl(operator) => {
+                Sexp::list("operator", custom_operator(operator))
+            }
+            ExpressionKind::Match { value, arms } => Sexp::list(
+                "match",
+                std::iter::once(self.expression(value)).chain(arms.iter().map(|arm| {
+                    Sexp::list(
+                        "arm",
+                        [self.pattern(&arm.pattern), self.expression(&arm.body)],
+                    )
+                })),
+            ),
+            ExpressionKind::While {
+                label,
+                condition,
+                body,
+            } => {
+                let mut items = loop_label(label);
+                items.push(self.expression(condition));
+                items.extend(self.statements(body));
+                Sexp::list("while", items)
+            }
+            ExpressionKind::For {
+                label,
+                pattern,
+                iterable,
+                body,
+            } => {
+                let mut items = loop_label(label);
+                items.push(self.pattern(pattern));
+                items.push(self.expression(iterable));
+                items.extend(self.statements(body));
+                Sexp::list("for", items)
+            }
+            ExpressionKind::Loop { label, body } => {
+                let mut items = loop_label(label);
+                items.extend(self.statements(body));
+                Sexp::list("loop", items)
+            }
+            ExpressionKind::Break { label, value } => {
+                let mut items = loop_label(label);
+                items.extend(value.as_deref().map(|value| self.expression(value)));
+                Sexp::list("break", items)
+            }
+            ExpressionKind::Continue { label } => Sexp::list("continue", loop_label(label)),
+            ExpressionKind::Return(value) => Sexp::list(
+                "return",
+                value.as_deref().map(|value| self.expression(value)),
+            ),
+            ExpressionKind::Cfg { options, expr } => Sexp::list(
+                "cfg",
+                options
+                    .iter()
+                    .map(|(key, value)| {
+                        Sexp::List(vec![Sexp::atom(key), Sexp::String(value.clone())])
+                    })
+                    .chain([self.expression(expr)]),
+            ),
+            ExpressionKind::Include(path) => Sexp::list("include", [Sexp::String(path.clone())]),
+            ExpressionKind::EmbedText(path) => {
+                Sexp::list("embed_text", [Sexp::String(path.clone())])
+            }
+        }
+    }
+
+    /// Tacks the span onto the head of the node, e.g. `(int@4..5 1)`.
+    fn spanned(&self, mut sexp: Sexp, span: Span) -> Sexp {
+        if !self.spans {
+            return sexp;
+        }
+        let head = match &mut sexp {
+            Sexp::List(items) => items.first_mut(),
+            atom => Some(atom),
+        };
+        if let Some(Sexp::Atom(head)) = head {
+            write!(head, "@{}..{}", span.start, span.end).unwrap();
+        }
+        sexp
+    }
+
+    fn statements(&self, stmts: &[ExpressionStatement]) -> Vec<Sexp> {
+        let mut items = vec![];
+        for stmt in stmts {
+            items.push(self.expression(&stmt.expr));
+            if stmt.discarded {
+                items.push(Sexp::atom(";"));
+            }
+        }
+        items
+    }
+
+    /// The `:attrs ((name arg ...) ...)` of a declaration, if it has any.
+    fn attributes(&self, attributes: &[Attribute]) -> Vec<Sexp> {
+        if attributes.is_empty() {
+            return vec![];
+        }
+        let attributes = attributes.iter().map(|attribute| {
+            Sexp::list(
+                &attribute.name,
+                attribute.arguments.iter().map(|arg| self.expression(arg)),
+            )
+        });
+        vec![Sexp::atom(":attrs"), Sexp::List(attributes.collect())]
+    }
+
+    fn argument(&self, argument: &FunctionArgument) -> Sexp {
+        let value = self.expression(&argument.value);
+        match (&argument.label, argument.spread) {
+            (_, true) => Sexp::list("spread", [value]),
+            (Some(label), false) => Sexp::list("arg", [Sexp::atom(label), value]),
+            (None, false) => value,
+        }
+    }
+
+    fn pattern(&self, pat: &Pattern) -> Sexp {
+        self.spanned(self.pattern_kind(pat), pat.span)
+    }
+
+    fn pattern_kind(&self, pat: &Pattern) -> Sexp {
+        match &pat.kind {
+            PatternKind::Wildcard => Sexp::atom("_"),
+            PatternKind::Binding(name) => Sexp::list("bind", [Sexp::atom(name)]),
+            PatternKind::Unit => Sexp::atom("unit"),
+            PatternKind::BoolLiteral(value) => Sexp::list("bool", [Sexp::atom(value)]),
+            PatternKind::IntLiteral(value) => Sexp::list("int", [Sexp::atom(value)]),
+            PatternKind::Variant {
+                ty,
+                variant,
+                fields,
+            } => Sexp::list(
+                "variant",
+                [Sexp::atom(ty), Sexp::atom(variant)]
+                    .into_iter()
+                    .chain(fields.iter().map(|field| match &field.label {
+                        Some(label) => {
+                            Sexp::list("arg", [Sexp::atom(label), self.pattern(&field.pattern)])
+                        }
+                        None => self.pattern(&field.pattern),
+                    })),
+            ),
+            PatternKind::Tuple(elements) => Sexp::list(
+                "tuple",
+                elements.iter().map(|element| self.pattern(element)),
+            ),
+        }
+    }
+    fn parameter(&self, parameter: &FunctionParameter) -> Sexp {
+        let (name, label, ty) = match parameter {
+            FunctionParameter::LabeledAtCallsite {
+                internal_name,
+                external_name,
+                ty,
+                ..
+            } => (
+                internal_name,
+                external_name
+                    .iter()
+                    .flat_map(|l| [Sexp::atom(":label"), Sexp::atom(l)])
+                    .collect(),
+                ty,
+            ),
+            FunctionParameter::UnlabeledAtCallsite { name, ty } => {
+                (name, vec![Sexp::atom(":unlabeled")], ty)
+            }
+            FunctionParameter::Rest { name, ty } => (name, vec![Sexp::atom(":rest")], ty),
+            FunctionParameter::Pattern { pattern, ty } => {
+                let mut items = vec![self.pattern(pattern)];
+                if let Some(ty) = ty {
+                    items.extend([Sexp::atom(":type"), type_sexp(ty)]);
+                }
+                return Sexp::list("param", items);
+            }
+            FunctionParameter::SelfParameter => return Sexp::atom("self"),
+        };
+        let mut items = vec![Sexp::atom(name)];
+        items.extend(label);
+        if let Some(ty) = ty {
+            items.extend([Sexp::atom(":type"), type_sexp(ty)]);
+        }
+        Sexp::list("param", items)
+    }
+}
+
+fn type_sexp(ty: &Type) -> Sexp {
+    match ty {
+        Type::Ident(name) => Sexp::atom(name),
+        Type::Tuple(elements) => Sexp::list("tuple", elements.iter().map(type_sexp)),
+        Type::Applied { name, arguments } => Sexp::list(
+            "apply",
+            [Sexp::atom(name)]
+                .into_iter()
+                .chain(arguments.iter().map(type_sexp)),
+        ),
+        Type::Function {
+            parameters,
+            return_type,
+        } => Sexp::list(
+            "fun",
+            parameters.iter().map(type_sexp).chain(
+                return_type
+                    .iter()
+                    .flat_map(|ty| [Sexp::atom(":returns"), type_sexp(ty)]),
+            ),
+        ),
+    }
+}
+
+fn custom_operator(operator: &CustomOperator) -> Vec<Sexp> {
+    let mut items = vec![
+        Sexp::atom(&operator.symbol),
+        Sexp::atom(":precedence"),
+        Sexp::atom(operator.precedence),
+    ];
+    if operator.associativity == Associativity::Right {
+        items.extend([Sexp::atom(":assoc"), Sexp::atom("right")]);
+    }
+    if let Some(method) = &operator.method {
+        items.extend([Sexp::atom(":method"), Sexp::atom(method)]);
+    }
+    items
+}
+
+fn loop_label(label: &Option<String>) -> Vec<Sexp> {
+    label
+        .iter()
+        .flat_map(|l| [Sexp::atom(":label"), Sexp::atom(l)])
+        .collect()
+}
+
+struct Reader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Reader<'_> {
+    /// Reads the next s-expression, or `None` at the end of the input.
+    fn read(&mut self) -> Result<Option<Sexp>, Error> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(c) = self.chars.next() else {
+            return Ok(None);
+        };
+
+        let sexp = match c {
+            '(' => {
+                let mut items = vec![];
+                loop {
+                    while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+                    if self.chars.next_if_eq(&')').is_some() {
+                        break;
+                    }
+                    items.push(self.read()?.ok_or(Error::UnexpectedEof)?);
+                }
+                Sexp::List(items)
+            }
+            ')' => return Err(Error::Invalid("unexpected `)`".to_string())),
+            '"' => Sexp::String(unescape(&self.read_quoted('"')?)),
+            '\'' => {
+                let value = unescape(&self.read_quoted('\'')?);
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Sexp::Char(c),
+                    _ => return Err(Error::Invalid(format!("invalid char '{value}'"))),
+                }
+            }
+            c => {
+                let mut atom = String::from(c);
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| !c.is_whitespace() && !matches!(c, '(' | ')'))
+                {
+                    atom.push(c);
+                }
+                Sexp::Atom(atom)
+            }
+        };
+        Ok(Some(sexp))
+    }
+
+    /// Reads up to the closing quote, leaving escape sequences as they are.
+    fn read_quoted(&mut self, quote: char) -> Result<String, Error> {
+        let mut contents = String::new();
+        loop {
+            match self.chars.next().ok_or(Error::UnexpectedEof)? {
+                c if c == quote => return Ok(contents),
+                '\\' => {
+                    contents.push('\\');
+                    contents.push(self.chars.next().ok_or(Error::UnexpectedEof)?);
+                }
+                c => contents.push(c),
+            }
+        }
+    }
+}
+
+/// The items of a list after its head, with `:key value` fields and `:flag`s
+/// taken out as they're read.
+struct Fields<'a> {
+    items: Vec<&'a Sexp>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(items: &'a [Sexp]) -> Self {
+        Self {
+            items: items.iter().collect(),
+        }
+    }
+
+    fn flag(&mut self, key: &str) -> bool {
+        match self.items.iter().position(|item| is_atom(item, key)) {
+            Some(index) => {
+                self.items.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn key(&mut self, key: &str) -> Result<Option<&'a Sexp>, Error> {
+        let Some(index) = self.items.iter().position(|item| is_atom(item, key)) else {
+            return Ok(None);
+        };
+        if index + 1 >= self.items.len() {
+            return Err(Error::UnexpectedEof);
+        }
+        self.items.remove(index);
+        Ok(Some(self.items.remove(index)))
+    }
+
+    fn next(&mut self) -> Result<&'a Sexp, Error> {
+        if self.items.is_empty() {
+            return Err(Error::UnexpectedEof);
+        }
+        Ok(self.items.remove(0))
+    }
+
+    fn rest(&mut self) -> Vec<&'a Sexp> {
+        std::mem::take(&mut self.items)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        match self.items.first() {
+            Some(item) => Err(Error::invalid("the end of the list", item)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_atom(sexp: &Sexp, value: &str) -> bool {
+    matches!(sexp, Sexp::Atom(atom) if atom == value)
+}
+
+fn atom(sexp: &Sexp) -> Result<&str, Error> {
+    match sexp {
+        Sexp::Atom(atom) => Ok(atom),
+        _ => Err(Error::invalid("an atom", sexp)),
+    }
+}
+
+/// The `:doc "text"` of a declaration, if it has one.
+fn doc_sexp(doc: &Option<String>) -> impl Iterator<Item = Sexp> {
+    doc.iter()
+        .flat_map(|doc| [Sexp::atom(":doc"), Sexp::String(doc.clone())])
+}
+
+fn read_doc(fields: &mut Fields) -> Result<Option<String>, Error> {
+    fields.key(":doc")?.map(string).transpose()
+}
+
+fn read_attributes(fields: &mut Fields) -> Result<Vec<Attribute>, Error> {
+    match fields.key(":attrs")? {
+        Some(Sexp::List(attributes)) => attributes
+            .iter()
+            .map(|attribute| match attribute {
+                Sexp::List(items) if !items.is_empty() => Ok(Attribute {
+                    name: atom(&items[0])?.to_string(),
+                    arguments: items[1..]
+                        .iter()
+                        .map(read_expression)
+                        .collect::<Result<_, _>>()?,
+                    span: no_span(),
+                }),
+                _ => Err(Error::invalid("an attribute", attribute)),
+            })
+            .collect(),
+        Some(other) => Err(Error::invalid("attributes", other)),
+        None => Ok(vec![]),
+    }
+}
+
+fn string(sexp: &Sexp) -> Result<String, Error> {
+    match sexp {
+        Sexp::String(value) => Ok(value.clone()),
+        _ => Err(Error::invalid("a string", sexp)),
+    }
+}
+
+fn parsed<T: std::str::FromStr>(sexp: &Sexp) -> Result<T, Error> {
+    atom(sexp)?
+        .parse()
+        .map_err(|_| Error::invalid("a value", sexp))
+}
+
+fn no_span() -> Span {
+    Span::from_range(0, 0)
+}
+
+fn read_statements<'a>(
+    items: impl IntoIterator<Item = &'a Sexp>,
+) -> Result<Vec<ExpressionStatement>, Error> {
+    let mut stmts: Vec<ExpressionStatement> = vec![];
+    for item in items {
+        if is_atom(item, ";") {
+            match stmts.last_mut() {
+                Some(stmt) if !stmt.discarded => stmt.discarded = true,
+                _ => return Err(Error::invalid("a statement", item)),
+            }
+        } else {
+            stmts.push(ExpressionStatement {
+                expr: read_expression(item)?,
+                discarded: false,
+            });
+        }
+    }
+    Ok(stmts)
+}
+
+fn read_expression(sexp: &Sexp) -> Result<Expression, Error> {
+    if is_atom(sexp, "unit") {
+        return Ok(Expression::new(ExpressionKind::Unit, no_span()));
+    }
+    let Sexp::List(items) = sexp else {
+        return Err(Error::invalid("an expression", sexp));
+    };
+    let (head, items) = items
+        .split_first()
+        .ok_or_else(|| Error::invalid("an expression", sexp))?;
+    let mut fields = Fields::new(items);
+
+    let kind = match atom(head)? {
+        "bool" => ExpressionKind::BoolLiteral(parsed(fields.next()?)?),
+        "int" => {
+            let value = fields.next()?;
+            match fields.rest()[..] {
+                [] => ExpressionKind::IntLiteral(parsed(value)?),
+                [suffix] => ExpressionKind::SuffixedIntLiteral {
+                    value: parsed(value)?,
+                    suffix: atom(suffix)?.to_string(),
+                },
+                _ => return Err(Error::invalid("an int literal", sexp)),
+            }
+        }
+        "float" => ExpressionKind::FloatLiteral(parsed(fields.next()?)?),
+        "string" => match fields.next()? {
+            Sexp::String(value) => ExpressionKind::StringLiteral(value.clone()),
+            other => return Err(Error::invalid("a string", other)),
+        },
+        "char" => match fields.next()? {
+            Sexp::Char(value) => ExpressionKind::CharLiteral(*value),
+            other => return Err(Error::invalid("a char", other)),
+        },
+        "ident" => ExpressionKind::Ident(atom(fields.next()?)?.to_string()),
+        "prefix" => ExpressionKind::Prefix {
+            operator: prefix_operator(fields.next()?)?,
+            expression: Box::new(read_expression(fields.next()?)?),
+        },
+        "infix" => ExpressionKind::Infix {
+            operator: infix_operator(fields.next()?)?,
+            left: Box::new(read_expression(fields.next()?)?),
+            right: Box::new(read_expression(fields.next()?)?),
+        },
+        "custom" => ExpressionKind::CustomInfix {
+            operator: read_custom_operator(&mut fields)?,
+            left: Box::new(read_expression(fields.next()?)?),
+            right: Box::new(read_expression(fields.next()?)?),
+        },
+        "chain" => {
+            let mut operands = vec![read_expression(fields.next()?)?];
+            let mut operators = vec![];
+            for pair in fields.rest().chunks(2) {
+                let [operator, operand] = pair else {
+                    return Err(Error::invalid("an operand", pair[0]));
+                };
+                operators.push(infix_operator(operator)?);
+                operands.push(read_expression(operand)?);
+            }
+            ExpressionKind::ChainedComparison {
+                operands,
+                operators,
+            }
+        }
+        "tuple" => ExpressionKind::Tuple(
+            fields
+                .rest()
+                .into_iter()
+                .map(read_expression)
+                .collect::<Result<_, _>>()?,
+        ),
+        "range" => ExpressionKind::Range {
+            inclusive: fields.flag(":inclusive"),
+            start: Box::new(read_expression(fields.next()?)?),
+            end: Box::new(read_expression(fields.next()?)?),
+        },
+        "let" => ExpressionKind::VariableDecl {
+            name: atom(fields.next()?)?.to_string(),
+            doc: read_doc(&mut fields)?,
+            attributes: read_attributes(&mut fields)?,
+            ty: fields
+                .key(":type")?
+                .map(|ty| Ok((read_type(ty)?, no_span())))
+                .transpose()?,
+            mutable: fields.flag(":mutable"),
+            separator: no_span(),
+            value: Box::new(read_expression(fields.next()?)?),
+        },
+        "block" => ExpressionKind::Block(read_statements(fields.rest())?),
+        "assign" => ExpressionKind::Assign {
+            target: Box::new(read_expression(fields.next()?)?),
+            value: Box::new(read_expression(fields.next()?)?),
+        },
+        "fun" => {
+            let type_parameters = match fields.key(":type-params")? {
+                Some(Sexp::List(params)) => params
+                    .iter()
+                    .map(read_type_parameter)
+                    .collect::<Result<_, _>>()?,
+                Some(other) => return Err(Error::invalid("type parameters", other)),
+                None => vec![],
+            };
+            let parameters = match fields.key(":params")? {
+                Some(Sexp::List(params)) => params
+                    .iter()
+                    .map(read_parameter)
+                    .collect::<Result<_, _>>()?,
+                Some(other) => return Err(Error::invalid("parameters", other)),
+                None => return Err(Error::invalid("`:params`", sexp)),
+            };
+            ExpressionKind::Function {
+                type_parameters,
+                parameters,
+                return_type: fields.key(":returns")?.map(read_type).transpose()?,
+                body: read_statements(fields.rest())?,
+            }
+        }
+        // a name for calling a function by name, an expression for calling anything else
+        "call" => match fields.next()? {
+            Sexp::Atom(name) => ExpressionKind::FunctionCall {
+                name: name.to_string(),
+                arguments: read_arguments(fields.rest())?,
+            },
+            callee => ExpressionKind::Call {
+                callee: Box::new(read_expression(callee)?),
+                arguments: read_arguments(fields.rest())?,
+            },
+        },
+        "field" => ExpressionKind::FieldAccess {
+            target: Box::new(read_expression(fields.next()?)?),
+            field: atom(fields.next()?)?.to_string(),
+        },
+        "safe-field" => ExpressionKind::SafeFieldAccess {
+            target: Box::new(read_expression(fields.next()?)?),
+            field: atom(fields.next()?)?.to_string(),
+        },
+        "try" => ExpressionKind::Try(Box::new(read_expression(fields.next()?)?)),
+        "method" => ExpressionKind::MethodCall {
+            receiver: Box::new(read_expression(fields.next()?)?),
+            method: atom(fields.next()?)?.to_string(),
+            arguments: read_arguments(fields.rest())?,
+        },
+        "index" => ExpressionKind::Index {
+            target: Box::new(read_expression(fields.next()?)?),
+            index: Box::new(read_expression(fields.next()?)?),
+        },
+        "impl" => ExpressionKind::Impl {
+            ty: read_type(fields.next()?)?,
+            methods: fields
+                .rest()
+                .into_iter()
+                .map(|method| match method {
+                    Sexp::List(items) => {
+                        let mut fields = Fields::new(items);
+                        let method = Method {
+                            name: atom(fields.next()?)?.to_string(),
+                            doc: read_doc(&mut fields)?,
+                            attributes: read_attributes(&mut fields)?,
+                            function: read_expression(fields.next()?)?,
+                        };
+                        fields.finish()?;
+                        Ok(method)
+                    }
+                    _ => Err(Error::invalid("a method", method)),
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        "struct" => ExpressionKind::Struct {
+            name: atom(fields.next()?)?.to_string(),
+            doc: read_doc(&mut fields)?,
+            attributes: read_attributes(&mut fields)?,
+            fields: fields
+                .rest()
+                .into_iter()
+                .map(|field| match field {
+                    Sexp::List(items) if items.len() == 2 => Ok(StructField {
+                        name: atom(&items[0])?.to_string(),
+                        ty: read_type(&items[1])?,
+                        span: no_span(),
+                    }),
+                    _ => Err(Error::invalid("a field", field)),
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        "enum" => ExpressionKind::Enum {
+            name: atom(fields.next()?)?.to_string(),
+            doc: read_doc(&mut fields)?,
+            attributes: read_attributes(&mut fields)?,
+            variants: fields
+                .rest()
+                .into_iter()
+                .map(read_variant)
+                .collect::<Result<_, _>>()?,
+        },
+        "operator" => ExpressionKind::OperatorDecl(read_custom_operator(&mut fields)?),
+        "match" => ExpressionKind::Match {
+            value: Box::new(read_expression(fields.next()?)?),
+            arms: fields
+                .rest()
+                .into_iter()
+                .map(|arm| match arm {
+                    Sexp::List(items) if items.len() == 3 && is_atom(&items[0], "arm") => {
+                        Ok(MatchArm {
+                            pattern: read_pattern(&items[1])?,
+                            body: read_expression(&items[2])?,
+                        })
+                    }
+                    _ => Err(Error::invalid("a match arm", arm)),
+                })
+                .collect::<Result<_, _>>()?,
+        },
+        "while" => ExpressionKind::While {
+            label: read_label(&mut fields)?,
+            condition: Box::new(read_expression(fields.next()?)?),
+            body: read_statements(fields.rest())?,
+        },
+        "loop" => ExpressionKind::Loop {
+            label: read_label(&mut fields)?,
+            body: read_statements(fields.rest())?,
+        },
+        "for" => ExpressionKind::For {
+            label: read_label(&mut fields)?,
+            pattern: Box::new(read_pattern(fields.next()?)?),
+            iterable: Box::new(read_expression(fields.next()?)?),
+            body: read_statements(fields.rest())?,
+        },
+        "break" => ExpressionKind::Break {
+            label: read_label(&mut fields)?,
+            value: match fields.rest()[..] {
+                [] => None,
+                [value] => Some(Box::new(read_expression(value)?)),
+                _ => return Err(Error::invalid("a break", sexp)),
+            },
+        },
+        "continue" => ExpressionKind::Continue {
+            label: read_label(&mut fields)?,
+        },
+        "return" => ExpressionKind::Return(match fields.rest()[..] {
+            [] => None,
+            [value] => Some(Box::new(read_expression(value)?)),
+            _ => return Err(Error::invalid("a return", sexp)),
+        }),
+        "cfg" => {
+            let items = fields.rest();
+            let Some((expr, options)) = items.split_last() else {
+                return Err(Error::UnexpectedEof);
+            };
+            ExpressionKind::Cfg {
+                options: options
+                    .iter()
+                    .map(|option| {
+                        if let Sexp::List(pair) = option
+                            && let [key, Sexp::String(value)] = &pair[..]
+                        {
+                            return Ok((atom(key)?.to_string(), value.clone()));
+                        }
+                        Err(Error::invalid("a cfg option", option))
+                    })
+                    .collect::<Result<_, _>>()?,
+                expr: Box::new(read_expression(expr)?),
+            }
+        }
+        "include" => ExpressionKind::Include(string(fields.next()?)?),
+        "embed_text" => ExpressionKind::EmbedText(string(fields.next()?)?),
+        _ => return Err(Error::invalid("an expression", sexp)),
+    };
+
+    fields.finish()?;
+    Ok(Expression::new(kind, no_span()))
+}
+
+fn read_custom_operator(fields: &mut Fields) -> Result<CustomOperator, Error> {
+    let symbol = atom(fields.next()?)?.to_string();
+    let precedence = fields.key(":precedence")?.ok_or(Error::UnexpectedEof)?;
+    let associativity = match fields.key(":assoc")? {
+        None => Associativity::Left,
+        Some(assoc) if is_atom(assoc, "right") => Associativity::Right,
+        Some(assoc) if is_atom(assoc, "left") => Associativity::Left,
+        Some(other) => return Err(Error::invalid("`left` or `right`", other)),
+    };
+    Ok(CustomOperator {
+        symbol,
+        precedence: parsed(precedence)?,
+        associativity,
+        method: fields
+            .key(":method")?
+            .map(atom)
+            .transpose()?
+            .map(str::to_string),
+    })
+}
+
+fn read_label(fields: &mut Fields) -> Result<Option<String>, Error> {
+    fields
+        .key(":label")?
+        .map(|label| atom(label).map(str::to_string))
+        .transpose()
+}
+
+fn read_type(sexp: &Sexp) -> Result<Type, Error> {
+    match sexp {
+        Sexp::List(items) if items.first().is_some_and(|head| is_atom(head, "tuple")) => Ok(
+            Type::Tuple(items[1..].iter().map(read_type).collect::<Result<_, _>>()?),
+        ),
+        Sexp::List(items)
+            if items.len() > 2 && items.first().is_some_and(|head| is_atom(head, "apply")) =>
+        {
+            Ok(Type::Applied {
+                name: atom(&items[1])?.to_string(),
+                arguments: items[2..].iter().map(read_type).collect::<Result<_, _>>()?,
+            })
+        }
+        Sexp::List(items) if items.first().is_some_and(|head| is_atom(head, "fun")) => {
+            let mut fields = Fields::new(&items[1..]);
+            let return_type = fields.key(":returns")?.map(read_type).transpose()?;
+            let parameters = fields
+                .rest()
+                .into_iter()
+                .map(read_type)
+                .collect::<Result<_, _>>()?;
+            Ok(Type::Function {
+                parameters,
+                return_type: return_type.map(Box::new),
+            })
+        }
+        _ => Ok(Type::Ident(atom(sexp)?.to_string())),
+    }
+}
+
+fn read_variant(sexp: &Sexp) -> Result<EnumVariant, Error> {
+    let Sexp::List(items) = sexp else {
+        return Err(Error::invalid("a variant", sexp));
+    };
+    let (name, fields) = items
+        .split_first()
+        .ok_or_else(|| Error::invalid("a variant", sexp))?;
+    Ok(EnumVariant {
+        name: atom(name)?.to_string(),
+        fields: fields
+            .iter()
+            .map(|field| match field {
+                Sexp::List(items) if items.len() == 3 && is_atom(&items[0], "arg") => {
+                    Ok(VariantField {
+                        label: Some(atom(&items[1])?.to_string()),
+                        ty: read_type(&items[2])?,
+                    })
+                }
+                _ => Ok(VariantField {
+                    label: None,
+                    ty: read_type(field)?,
+                }),
+            })
+            .collect::<Result<_, _>>()?,
+        span: no_span(),
+    })
+}
+
+fn read_type_parameter(sexp: &Sexp) -> Result<TypeParameter, Error> {
+    let Sexp::List(items) = sexp else {
+        return Err(Error::invalid("a type parameter", sexp));
+    };
+    let (name, bounds) = items
+        .split_first()
+        .ok_or_else(|| Error::invalid("a type parameter", sexp))?;
+    Ok(TypeParameter {
+        name: atom(name)?.to_string(),
+        bounds: bounds
+            .iter()
+            .map(|b| atom(b).map(|bound| (bound.to_string(), no_span())))
+            .collect::<Result<_, _>>()?,
+        span: no_span(),
+    })
+}
+
+fn read_parameter(sexp: &Sexp) -> Result<FunctionParameter, Error> {
+    if is_atom(sexp, "self") {
+        return Ok(FunctionParameter::SelfParameter);
+    }
+    let Sexp::List(items) = sexp else {
+        return Err(Error::invalid("a parameter", sexp));
+    };
+    if !items.first().is_some_and(|head| is_atom(head, "param")) {
+        return Err(Error::invalid("a parameter", sexp));
+    }
+    let mut fields = Fields::new(&items[1..]);
+
+    let name = fields.next()?;
+    let ty = fields.key(":type")?.map(read_type).transpose()?;
+    if let Sexp::List(_) = name {
+        fields.finish()?;
+        let pattern = read_pattern(name)?;
+        return Ok(FunctionParameter::Pattern { pattern, ty });
+    }
+    let name = atom(name)?.to_string();
+    let parameter = if fields.flag(":unlabeled") {
+        FunctionParameter::UnlabeledAtCallsite { name, ty }
+    } else if fields.flag(":rest") {
+        FunctionParameter::Rest { name, ty }
+    } else {
+        FunctionParameter::LabeledAtCallsite {
+            internal_name: name,
+            external_name: read_label(&mut fields)?,
+            ty,
+            label_span: no_span(),
+        }
+    };
+    fields.finish()?;
+    Ok(parameter)
+}
+
+fn read_arguments(items: Vec<&Sexp>) -> Result<Vec<FunctionArgument>, Error> {
+    items
+        .into_iter()
+        .map(|item| match item {
+            Sexp::List(items) if items.len() == 3 && is_atom(&items[0], "arg") => {
+                Ok(FunctionArgument {
+                    label: Some(atom(&items[1])?.to_string()),
+                    value: read_expression(&items[2])?,
+                    spread: false,
+                })
+            }
+            Sexp::List(items) if items.len() == 2 && is_atom(&items[0], "spread") => {
+                Ok(FunctionArgument {
+                    label: None,
+                    value: read_expression(&items[1])?,
+                    spread: true,
+                })
+            }
+            _ => Ok(FunctionArgument {
+                label: None,
+                value: read_expression(item)?,
+                spread: false,
+            }),
+        })
+        .collect()
+}
+
+fn read_pattern(sexp: &Sexp) -> Result<Pattern, Error> {
+    let kind = match sexp {
+        Sexp::Atom(atom) if atom == "_" => PatternKind::Wildcard,
+        Sexp::Atom(atom) if atom == "unit" => PatternKind::Unit,
+        Sexp::List(items) if !items.is_empty() => {
+            let mut fields = Fields::new(&items[1..]);
+            let kind = match atom(&items[0])? {
+                "bind" => PatternKind::Binding(atom(fields.next()?)?.to_string()),
+                "bool" => PatternKind::BoolLiteral(parsed(fields.next()?)?),
+                "int" => PatternKind::IntLiteral(parsed(fields.next()?)?),
+                "tuple" => PatternKind::Tuple(
+                    fields
+                        .rest()
+                        .into_iter()
+                        .map(read_pattern)
+                        .collect::<Result<_, _>>()?,
+                ),
+                "variant" => PatternKind::Variant {
+                    ty: atom(fields.next()?)?.to_string(),
+                    variant: atom(fields.next()?)?.to_string(),
+                    fields: fields
+                        .rest()
+                        .into_iter()
+                        .map(|field| match field {
+                            Sexp::List(items) if items.len() == 3 && is_atom(&items[0], "arg") => {
+                                Ok(VariantFieldPattern {
+                                    label: Some(atom(&items[1])?.to_string()),
+                                    pattern: read_pattern(&items[2])?,
+                                })
+                            }
+                            _ => Ok(VariantFieldPattern {
+                                label: None,
+                                pattern: read_pattern(field)?,
+                            }),
+                        })
+                        .collect::<Result<_, _>>()?,
+                },
+                _ => return Err(Error::invalid("a pattern", sexp)),
+            };
+            fields.finish()?;
+            kind
+        }
+        _ => return Err(Error::invalid("a pattern", sexp)),
+    };
+    Ok(Pattern::new(kind, no_span()))
+}
+
+fn prefix_operator(sexp: &Sexp) -> Result<PrefixOperator, Error> {
+    match atom(sexp)? {
+        "!" => Ok(PrefixOperator::LogicalNot),
+        "-" => Ok(PrefixOperator::Negative),
+        _ => Err(Error::invalid("a prefix operator", sexp)),
+    }
+}
+
+fn infix_operator(sexp: &Sexp) -> Result<InfixOperator, Error> {
+    Ok(match atom(sexp)? {
+        "+" => InfixOperator::Plus,
+        "-" => InfixOperator::Minus,
+        "/" => InfixOperator::Divide,
+        "*" => InfixOperator::Multiply,
+        "**" => InfixOperator::Power,
+        "==" => InfixOperator::Equal,
+        "!=" => InfixOperator::NotEqual,
+        "<" => InfixOperator::LessThan,
+        ">" => InfixOperator::GreaterThan,
+        "<=" => InfixOperator::LessThanOrEqual,
+        ">=" => InfixOperator::GreaterThanOrEqual,
+        "<<" => InfixOperator::ShiftLeft,
+        ">>" => InfixOperator::ShiftRight,
+        "&&" => InfixOperator::And,
+        "||" => InfixOperator::Or,
+        "~>" => InfixOperator::Compose,
+        "|>" => InfixOperator::Pipe,
+        "??" => InfixOperator::Coalesce,
+        _ => return Err(Error::invalid("an infix operator", sexp)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    fn sexp(input: &str) -> String {
+        program_to_sexp(&Parser::new(input).parse().unwrap())
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(sexp("1 + 2"), "(infix + (int 1) (int 2))\n");
+        assert_eq!(
+            sexp("x := -y; f(1, by: 'a', ...z);"),
+            "(let x :mutable (prefix - (ident y))) ;\n\
+             (call f (int 1) (arg by (char 'a')) (spread (ident z))) ;\n"
+        );
+    }
+
+    #[test]
+    fn long_lists_break_lines() {
+        assert_eq!(
+            sexp("add :: fun(~first: Int, to second: Int) Int { first + second };"),
+            "(let add\n  (fun\n    \
+             :params ((param first :unlabeled :type Int)\n\
+             \x20             (param second :label to :type Int))\n    \
+             :returns Int\n    (infix + (ident first) (ident second)))) ;\n"
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let program = Parser::new("x :: 1 + 2;\nmatch x { _ => unit }")
+            .parse()
+            .unwrap();
+        assert_eq!(
+            program_to_sexp_with_spans(&program),
+            "(let@0..10 x (infix@5..10 + (int@5..6 1) (int@9..10 2))) ;\n\
+             (match@12..33 (ident@18..19 x) (arm _@22..23 unit@27..31))\n"
+        );
+    }
+
+    #[test]
+    fn malformed_dumps() {
+        assert_eq!(
+            program_from_sexp("(infix + (int 1)"),
+            Err(Error::UnexpectedEof)
+        );
+        assert_eq!(
+            program_from_sexp("(int 1) ; ;"),
+            Err(Error::Invalid("expected a statement, got ;".to_string()))
+        );
+    }
+}
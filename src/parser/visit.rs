@@ -0,0 +1,124 @@
+//! Walks the AST mutably, for passes that rewrite the program in place.
+//!
+//! A pass overrides the methods for what it's interested in and calls the
+//! matching `walk_` function for whatever it leaves alone, which visits the
+//! children.
+
+use super::ast::{Expression, ExpressionKind, ExpressionStatement};
+
+pub trait VisitMut {
+    fn visit_statements(&mut self, stmts: &mut Vec<ExpressionStatement>) {
+        walk_statements(self, stmts);
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression) {
+        walk_expression(self, expr);
+    }
+}
+
+pub fn walk_statements<V: VisitMut + ?Sized>(visitor: &mut V, stmts: &mut [ExpressionStatement]) {
+    for stmt in stmts {
+        visitor.visit_expression(&mut stmt.expr);
+    }
+}
+
+pub fn walk_expression<V: VisitMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match &mut expr.kind {
+        ExpressionKind::Unit
+        | ExpressionKind::BoolLiteral(_)
+        | ExpressionKind::IntLiteral(_)
+        | ExpressionKind::SuffixedIntLiteral { .. }
+        | ExpressionKind::FloatLiteral(_)
+        | ExpressionKind::StringLiteral(_)
+        | ExpressionKind::CharLiteral(_)
+        | ExpressionKind::Ident(_)
+        | ExpressionKind::OperatorDecl(_)
+        | ExpressionKind::Struct { .. }
+        | ExpressionKind::Enum { .. }
+        | ExpressionKind::Include(_)
+        | ExpressionKind::EmbedText(_)
+        | ExpressionKind::Continue { .. }
+        | ExpressionKind::Break { value: None, .. }
+        | ExpressionKind::Return(None) => {}
+        ExpressionKind::Prefix { expression, .. } => visitor.visit_expression(expression),
+        ExpressionKind::Infix { left, right, .. }
+        | ExpressionKind::CustomInfix { left, right, .. }
+        | ExpressionKind::Range {
+            start: left,
+            end: right,
+            ..
+        }
+        | ExpressionKind::Assign {
+            target: left,
+            value: right,
+        } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        ExpressionKind::ChainedComparison {
+            operands: elements, ..
+        }
+        | ExpressionKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        ExpressionKind::VariableDecl { value, .. }
+        | ExpressionKind::Break {
+            value: Some(value), ..
+        }
+        | ExpressionKind::Return(Some(value))
+        | ExpressionKind::Cfg { expr: value, .. } => visitor.visit_expression(value),
+        ExpressionKind::Function { body, .. }
+        | ExpressionKind::Loop { body, .. }
+        | ExpressionKind::Block(body) => visitor.visit_statements(body),
+        ExpressionKind::FunctionCall { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(&mut argument.value);
+            }
+        }
+        ExpressionKind::Call { callee, arguments } => {
+            visitor.visit_expression(callee);
+            for argument in arguments {
+                visitor.visit_expression(&mut argument.value);
+            }
+        }
+        ExpressionKind::FieldAccess { target, .. }
+        | ExpressionKind::SafeFieldAccess { target, .. }
+        | ExpressionKind::Try(target) => visitor.visit_expression(target),
+        ExpressionKind::MethodCall {
+            receiver,
+            arguments,
+            ..
+        } => {
+            visitor.visit_expression(receiver);
+            for argument in arguments {
+                visitor.visit_expression(&mut argument.value);
+            }
+        }
+        ExpressionKind::Index { target, index } => {
+            visitor.visit_expression(target);
+            visitor.visit_expression(index);
+        }
+        ExpressionKind::Impl { methods, .. } => {
+            for method in methods {
+                visitor.visit_expression(&mut method.function);
+            }
+        }
+        ExpressionKind::Match { value, arms } => {
+            visitor.visit_expression(value);
+            for arm in arms {
+                visitor.visit_expression(&mut arm.body);
+            }
+        }
+        ExpressionKind::While {
+            condition: iterable,
+            body,
+            ..
+        }
+        | ExpressionKind::For { iterable, body, .. } => {
+            visitor.visit_expression(iterable);
+            visitor.visit_statements(body);
+        }
+    }
+}
@@ -10,24 +10,21 @@ impl Span {
     }
 
     fn line_col(text: &str, index: usize) -> (usize, usize) {
-        let bytes = text.chars();
-
         let mut line = 1;
         let mut column = 1;
-        let mut position = 1;
 
-        for byte in bytes {
-            if position >= index {
+        // columns count chars, while `index` counts bytes
+        for (offset, c) in text.char_indices() {
+            if offset + 1 >= index {
                 break;
             }
-            match byte {
+            match c {
                 '\n' => {
                     line += 1;
                     column = 1
                 }
                 _ => column += 1,
             }
-            position += 1;
         }
 
         (line, column)
@@ -42,8 +39,8 @@ impl Span {
     }
 
     pub fn slice<'src>(&self, source: &'src str) -> &'src str {
-        // SAFETY: `start` and `end` are UTF-8 char indices, not byte indexes
-        // for ascii, this should work fine though
+        // `start` and `end` are byte offsets, which the lexer only puts at
+        // char boundaries
         &source[self.start..self.end]
     }
 }
@@ -59,5 +56,9 @@ mod tests {
 
         let text = "hi there\nI am Tom\nWho are you???";
         assert_eq!(Span::line_col(text, 25), (3, 7));
+
+        // `é` takes two bytes, but one column
+        let text = "\u{e9}t\u{e9} :: 1";
+        assert_eq!(Span::line_col(text, 6), (1, 4));
     }
 }
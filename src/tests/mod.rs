@@ -2,7 +2,11 @@ use std::fs;
 
 use insta::glob;
 
-use crate::{checker::Checker, lexer::Lexer, parser::Parser};
+use crate::{
+    checker::Checker,
+    lexer::Lexer,
+    parser::{Parser, printer, sexp},
+};
 
 #[test]
 fn compiler_tests() {
@@ -23,5 +27,17 @@ fn compiler_tests() {
         let mut checker = Checker::new();
         let checked_program = checker.check_program(&ast);
         insta::assert_debug_snapshot!(checked_program);
+
+        // test the s-expression dump, which has to read back to the same tree
+        let dump = sexp::program_to_sexp(&ast);
+        let reread = sexp::program_from_sexp(&dump).unwrap();
+        assert_eq!(sexp::program_to_sexp(&reread), dump);
+        insta::assert_snapshot!(dump);
+
+        // test that the minified source means the same thing
+        let minified = printer::program_to_minified_source(&ast);
+        let reparsed = Parser::new(&minified).parse().unwrap();
+        assert_eq!(printer::program_to_minified_source(&reparsed), minified);
+        Checker::new().check_program(&reparsed);
     });
 }
@@ -0,0 +1,211 @@
+//! Generates a TextMate grammar from the lexer's keywords and symbols, so
+//! editor highlighting can't drift away from what the lexer accepts.
+//!
+//! `editors/vscode` is a VS Code extension around the generated grammar, and
+//! a test makes sure the copy checked in there is up to date.
+
+use crate::lexer::{
+    INT_SUFFIXES, KEYWORDS,
+    tokens::TokenKind::{self, *},
+};
+
+/// What follows the `\` of an escape sequence, as `escape_sequence` in the lexer reads it.
+const ESCAPE: &str = r#"\\(?:[ntr0\\"']|u\{[0-9a-fA-F]{1,6}\})"#;
+
+enum Json {
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn string(value: impl ToString) -> Self {
+        Json::String(value.to_string())
+    }
+
+    fn write(&self, indent: usize, out: &mut String) {
+        let (open, close, items) = match self {
+            Json::String(value) => {
+                out.push('"');
+                for c in value.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+                return;
+            }
+            Json::Array(items) => ('[', ']', items.iter().map(|item| (None, item)).collect()),
+            Json::Object(fields) => (
+                '{',
+                '}',
+                fields
+                    .iter()
+                    .map(|(key, value)| (Some(key), value))
+                    .collect::<Vec<_>>(),
+            ),
+        };
+
+        out.push(open);
+        for (i, (key, value)) in items.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push('\n');
+            out.push_str(&" ".repeat(indent + 2));
+            if let Some(key) = key {
+                Json::string(key).write(0, out);
+                out.push_str(": ");
+            }
+            value.write(indent + 2, out);
+        }
+        if !items.is_empty() {
+            out.push('\n');
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push(close);
+    }
+}
+
+/// The grammar as `.tmLanguage.json`.
+pub fn grammar() -> String {
+    let escape = || Json::Array(vec![rule("constant.character.escape.lovely", ESCAPE)]);
+    let mut patterns = vec![
+        rule("comment.line.documentation.lovely", "///.*$"),
+        rule("comment.line.number-sign.lovely", "#.*$"),
+        // raw strings have no escapes
+        Json::Object(vec![
+            ("name", Json::string("string.quoted.triple.raw.lovely")),
+            ("begin", Json::string("\\br\"\"\"")),
+            ("end", Json::string("\"\"\"")),
+        ]),
+        Json::Object(vec![
+            ("name", Json::string("string.quoted.double.raw.lovely")),
+            ("begin", Json::string("\\br\"")),
+            ("end", Json::string("\"")),
+        ]),
+        Json::Object(vec![
+            ("name", Json::string("string.quoted.triple.lovely")),
+            ("begin", Json::string("\"\"\"")),
+            ("end", Json::string("\"\"\"")),
+            ("patterns", escape()),
+        ]),
+        Json::Object(vec![
+            ("name", Json::string("string.quoted.double.lovely")),
+            ("begin", Json::string("\"")),
+            ("end", Json::string("\"")),
+            ("patterns", escape()),
+        ]),
+        rule(
+            "string.quoted.single.lovely",
+            &format!(r"'(?:[^'\\\n]|{ESCAPE})'"),
+        ),
+        rule(
+            "constant.numeric.float.lovely",
+            r"\b[0-9][0-9_]*\.[0-9][0-9_]*\b",
+        ),
+        rule(
+            "constant.numeric.integer.lovely",
+            &format!(
+                r"\b(?:0x[0-9a-fA-F][0-9a-fA-F_]*|0o[0-7][0-7_]*|0b[01][01_]*|[0-9][0-9_]*)(?:{})?\b",
+                INT_SUFFIXES.join("|")
+            ),
+        ),
+    ];
+
+    let keywords = KEYWORDS
+        .iter()
+        .map(|(keyword, kind)| (keyword.to_string(), keyword_scope(kind)));
+    for (scope, words) in group(keywords) {
+        patterns.push(rule(scope, &format!(r"\b(?:{})\b", words.join("|"))));
+    }
+    patterns.push(rule("entity.name.type.lovely", r"\b[A-Z][A-Za-z0-9_]*\b"));
+
+    // at the same position the first rule that matches wins, so `=>` has to
+    // come before `=` even though they're scoped differently
+    let symbols = TokenKind::SYMBOLS
+        .iter()
+        .map(|kind| (kind.to_string(), symbol_scope(kind)))
+        .collect::<Vec<_>>();
+    let mut lengths = symbols
+        .iter()
+        .map(|(symbol, _)| symbol.len())
+        .collect::<Vec<_>>();
+    lengths.sort_by_key(|length| std::cmp::Reverse(*length));
+    lengths.dedup();
+    for length in lengths {
+        let symbols = symbols
+            .iter()
+            .filter(|(symbol, _)| symbol.len() == length)
+            .map(|(symbol, scope)| (regex_escape(symbol), *scope));
+        for (scope, symbols) in group(symbols) {
+            patterns.push(rule(scope, &symbols.join("|")));
+        }
+    }
+
+    let mut out = String::new();
+    Json::Object(vec![
+        ("name", Json::string("lovely")),
+        ("scopeName", Json::string("source.lovely")),
+        ("fileTypes", Json::Array(vec![Json::string("lv")])),
+        ("patterns", Json::Array(patterns)),
+    ])
+    .write(0, &mut out);
+    out.push('\n');
+    out
+}
+
+fn rule(scope: &str, regex: &str) -> Json {
+    Json::Object(vec![
+        ("name", Json::string(scope)),
+        ("match", Json::string(regex)),
+    ])
+}
+
+/// Collects the items of each scope, keeping the order they first appear in.
+fn group(
+    items: impl IntoIterator<Item = (String, &'static str)>,
+) -> Vec<(&'static str, Vec<String>)> {
+    let mut groups: Vec<(&str, Vec<String>)> = vec![];
+    for (item, scope) in items {
+        match groups.iter_mut().find(|(s, _)| *s == scope) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((scope, vec![item])),
+        }
+    }
+    groups
+}
+
+fn keyword_scope(kind: &TokenKind) -> &'static str {
+    match kind {
+        Fun | Impl | Struct | Enum | Operator => "storage.type.lovely",
+        Unit | True | False => "constant.language.lovely",
+        SelfValue => "variable.language.self.lovely",
+        _ => "keyword.control.lovely",
+    }
+}
+
+fn symbol_scope(kind: &TokenKind) -> &'static str {
+    match kind {
+        LParen | RParen | LBrace | RBrace | LBracket | RBracket | Colon | Comma | Dot
+        | Ellipsis | DotDot | DotDotEqual | Tilde | Semicolon | FatArrow | At => {
+            "punctuation.lovely"
+        }
+        _ => "keyword.operator.lovely",
+    }
+}
+
+fn regex_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if r"\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
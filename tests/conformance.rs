@@ -0,0 +1,17 @@
+use std::{env, path::Path};
+
+/// Set `BLESS=1` to update the `.expected` files instead.
+#[test]
+fn conformance() {
+    let bless = env::var_os("BLESS").is_some();
+    let mismatches = lovely::conformance::run(Path::new("tests/cases"), bless).unwrap();
+    for mismatch in &mismatches {
+        eprintln!(
+            "{}\n--- expected\n{}--- actual\n{}",
+            mismatch.path.display(),
+            mismatch.expected,
+            mismatch.actual
+        );
+    }
+    assert!(mismatches.is_empty(), "{} case(s) failed", mismatches.len());
+}
@@ -0,0 +1,16 @@
+use std::{env, fs};
+
+const PATH: &str = "editors/vscode/syntaxes/lovely.tmLanguage.json";
+
+/// Set `BLESS=1` to regenerate the grammar instead.
+#[test]
+fn grammar_is_up_to_date() {
+    let grammar = lovely::textmate::grammar();
+    if env::var_os("BLESS").is_some() {
+        fs::write(PATH, &grammar).unwrap();
+    }
+    assert!(
+        fs::read_to_string(PATH).unwrap() == grammar,
+        "{PATH} is out of date, rerun this test with BLESS=1"
+    );
+}